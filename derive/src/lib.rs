@@ -0,0 +1,73 @@
+// #[derive(PackRecord)], for structs with named fields; each field is a column, in order,
+// whose kind comes from the field's type (see pack_it::record::RecordValue).
+// `#[pack_it(rename = "other")]` changes a column's name
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr, Result};
+
+#[proc_macro_derive(PackRecord, attributes(pack_it))]
+pub fn derive_pack_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input, "PackRecord needs named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input, "PackRecord is only for structs")),
+    };
+
+    let mut columns = Vec::with_capacity(fields.len());
+    let mut values = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named");
+        let ty = &field.ty;
+        let name = column_name(field)?.unwrap_or_else(|| ident.to_string());
+        columns.push(quote! {
+            ::pack_it::TableField::new(
+                #name,
+                <#ty as ::pack_it::record::RecordValue>::kind(),
+                <#ty as ::pack_it::record::RecordValue>::nullable(),
+            )
+        });
+        values.push(quote! {
+            ::pack_it::record::RecordValue::value(&self.#ident)
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pack_it::PackRecord for #ident #ty_generics #where_clause {
+            fn schema() -> ::std::vec::Vec<::pack_it::TableField> {
+                ::std::vec![#(#columns),*]
+            }
+
+            fn push_into(&self, table: &mut ::pack_it::Table) -> ::pack_it::record::Result<()> {
+                table.push_row(&[#(#values),*])
+            }
+        }
+    })
+}
+
+fn column_name(field: &syn::Field) -> Result<Option<String>> {
+    let mut name = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("pack_it")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(name)
+}
@@ -0,0 +1,87 @@
+#ifndef PACK_IT_H
+#define PACK_IT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct PackItPacker PackItPacker;
+
+enum PackItKind {
+    PACK_IT_BOOL = 0,
+    PACK_IT_UUID = 1,
+    PACK_IT_U8 = 2,
+    PACK_IT_I32 = 3,
+    PACK_IT_I64 = 4,
+    PACK_IT_F64 = 5,
+    PACK_IT_STRING = 6,
+    PACK_IT_TIMESTAMP_SECS_Z = 7,
+    /* WKB, through pack_it_push_bytes */
+    PACK_IT_GEOMETRY = 8,
+    PACK_IT_I8 = 9,
+    PACK_IT_I16 = 10,
+    PACK_IT_U16 = 11,
+    PACK_IT_U32 = 12,
+    PACK_IT_U64 = 13,
+    PACK_IT_F32 = 14,
+    /* days since 1970-01-01, through pack_it_push_i32 */
+    PACK_IT_DATE = 15,
+    /* the same, in millis, through pack_it_push_i64 */
+    PACK_IT_DATE_MILLIS = 16,
+    /* millis since midnight, through pack_it_push_i32 */
+    PACK_IT_TIME_MILLIS = 17,
+    /* micros since midnight, through pack_it_push_i64 */
+    PACK_IT_TIME_MICROS = 18,
+    /* any length, through pack_it_push_bytes */
+    PACK_IT_BINARY = 19,
+    /* strings, with 64-bit offsets, through pack_it_push_str */
+    PACK_IT_LARGE_STRING = 20,
+    /* always null; pack_it_push_null is accepted, but does nothing */
+    PACK_IT_NULL = 21,
+    /* strings, annotated as json, through pack_it_push_str */
+    PACK_IT_JSON = 22,
+    /* network order, 4 or 16 bytes, through pack_it_push_bytes */
+    PACK_IT_IPV4 = 23,
+    PACK_IT_IPV6 = 24,
+};
+
+/* all functions returning int return 0 on success, or -1 and set pack_it_last_error() */
+
+/* returns NULL on failure */
+PackItPacker *pack_it_packer_create(const char *path, size_t n_fields, const char *const *names,
+                                    const uint32_t *kinds, const bool *nullable);
+
+int pack_it_push_null(PackItPacker *packer, size_t col);
+int pack_it_push_bool(PackItPacker *packer, size_t col, bool val);
+int pack_it_push_u8(PackItPacker *packer, size_t col, uint8_t val);
+int pack_it_push_u16(PackItPacker *packer, size_t col, uint16_t val);
+int pack_it_push_u32(PackItPacker *packer, size_t col, uint32_t val);
+int pack_it_push_u64(PackItPacker *packer, size_t col, uint64_t val);
+int pack_it_push_i8(PackItPacker *packer, size_t col, int8_t val);
+int pack_it_push_i16(PackItPacker *packer, size_t col, int16_t val);
+int pack_it_push_i32(PackItPacker *packer, size_t col, int32_t val);
+int pack_it_push_i64(PackItPacker *packer, size_t col, int64_t val);
+int pack_it_push_f32(PackItPacker *packer, size_t col, float val);
+int pack_it_push_f64(PackItPacker *packer, size_t col, double val);
+int pack_it_push_str(PackItPacker *packer, size_t col, const char *val, size_t len);
+int pack_it_push_bytes(PackItPacker *packer, size_t col, const uint8_t *val, size_t len);
+
+/* call after each row */
+int pack_it_end_row(PackItPacker *packer);
+int pack_it_flush(PackItPacker *packer);
+
+/* both consume the packer */
+int pack_it_finish(PackItPacker *packer);
+void pack_it_free(PackItPacker *packer);
+
+const char *pack_it_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
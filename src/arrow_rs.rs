@@ -0,0 +1,97 @@
+// bridges to the official arrow crate, through the C data interface, so the arrays don't depend on
+// arrow-rs' version, and arrow2's own (very version specific) conversions aren't needed; the
+// arrow-rs api this compiles against is still pinned to a major in Cargo.toml
+
+use std::io::Write;
+use std::mem::transmute;
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Result};
+use arrow2::array::Array;
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::Field as ArrowField;
+use arrow2::ffi;
+use arrow_array::ffi::{FFI_ArrowArray, FFI_ArrowSchema};
+use arrow_array::{make_array, ArrayRef, RecordBatch};
+use arrow_schema::{Field, Schema};
+
+use crate::{Packer, Table, TableField};
+
+pub fn to_arrow_rs(array: Box<dyn Array>, field: &ArrowField) -> Result<ArrayRef> {
+    let array = ffi::export_array_to_c(array);
+    let schema = ffi::export_field_to_c(field);
+    // both sides are exactly the C structs, and take ownership of the release callbacks
+    let array = unsafe { transmute::<ffi::ArrowArray, FFI_ArrowArray>(array) };
+    let schema = unsafe { transmute::<ffi::ArrowSchema, FFI_ArrowSchema>(schema) };
+    let data = unsafe { arrow_array::ffi::from_ffi(array, &schema) }?;
+    Ok(make_array(data))
+}
+
+pub fn from_arrow_rs(array: &ArrayRef) -> Result<Box<dyn Array>> {
+    let (array, schema) = arrow_array::ffi::to_ffi(&array.to_data())?;
+    let array = unsafe { transmute::<FFI_ArrowArray, ffi::ArrowArray>(array) };
+    let schema = unsafe { transmute::<FFI_ArrowSchema, ffi::ArrowSchema>(schema) };
+    let field = unsafe { ffi::import_field_from_c(&schema) }?;
+    Ok(unsafe { ffi::import_array_from_c(array, field.data_type) }?)
+}
+
+impl Table {
+    // the rows are only taken if they all convert
+    pub fn take_record_batch(&mut self, fields: &[TableField]) -> Result<RecordBatch> {
+        self.check_consistent()?;
+        ensure!(
+            self.schema().len() == fields.len(),
+            "table has {} columns, but {} fields were provided",
+            self.schema().len(),
+            fields.len()
+        );
+        let batch = self.take_batch();
+        let ret = record_batch(&batch, fields);
+        if ret.is_err() {
+            self.extend_from(&batch)?;
+        }
+        ret
+    }
+}
+
+fn record_batch(batch: &[Arc<dyn Array>], fields: &[TableField]) -> Result<RecordBatch> {
+    let mut columns = Vec::with_capacity(fields.len());
+    let mut schema = Vec::with_capacity(fields.len());
+    for (array, field) in batch.iter().zip(fields) {
+        let field = field.to_arrow_field();
+        let array = to_arrow_rs(array.to_boxed(), &field)?;
+        schema.push(Field::new(
+            field.name,
+            array.data_type().clone(),
+            field.is_nullable,
+        ));
+        columns.push(array);
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(schema)),
+        columns,
+    )?)
+}
+
+impl<W: Write + Send + 'static> Packer<W> {
+    // as submit_dataframe, columns are matched up by name
+    pub fn submit_record_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        let matching = self.name_matching();
+        let schema = batch.schema();
+        let arrays = self
+            .schema()
+            .iter()
+            .map(|field| -> Result<_> {
+                let (i, _) = schema
+                    .fields()
+                    .iter()
+                    .enumerate()
+                    .find(|(_, f)| matching.matches(f.name(), &field.name))
+                    .ok_or_else(|| anyhow!("record batch is missing column {:?}", field.name))?;
+                Ok(from_arrow_rs(batch.column(i))?.into())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.submit_chunk(Chunk::try_new(arrays)?)
+    }
+}
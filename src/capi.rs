@@ -0,0 +1,332 @@
+// C interface; build with e.g. `cargo rustc --lib --release --features capi --crate-type cdylib`,
+// and see include/pack_it.h
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{Kind, Packer, TableField};
+
+pub struct PackItPacker {
+    inner: Packer<fs::File>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(msg: String) {
+    let msg = CString::new(msg.replace('\0', "\\0")).expect("nulls removed");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
+
+// run the closure, turning errors (and panics) into a -1 return and a last_error
+fn guard(f: impl FnOnce() -> Result<()>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) => {
+            set_error(format!("{:?}", e));
+            -1
+        }
+        Err(_) => {
+            set_error("panicked".to_string());
+            -1
+        }
+    }
+}
+
+fn kind_from_c(kind: u32) -> Result<Kind> {
+    Ok(match kind {
+        0 => Kind::Bool,
+        1 => Kind::Uuid,
+        2 => Kind::U8,
+        3 => Kind::I32,
+        4 => Kind::I64,
+        5 => Kind::F64,
+        6 => Kind::String,
+        7 => Kind::TimestampSecsZ,
+        8 => Kind::Geometry,
+        9 => Kind::I8,
+        10 => Kind::I16,
+        11 => Kind::U16,
+        12 => Kind::U32,
+        13 => Kind::U64,
+        14 => Kind::F32,
+        15 => Kind::Date,
+        16 => Kind::DateMillis,
+        17 => Kind::TimeMillis,
+        18 => Kind::TimeMicros,
+        19 => Kind::Binary,
+        20 => Kind::LargeString,
+        21 => Kind::Null,
+        22 => Kind::Json,
+        23 => Kind::Ipv4,
+        24 => Kind::Ipv6,
+        other => bail!("unrecognised kind {}", other),
+    })
+}
+
+unsafe fn packer<'p>(packer: *mut PackItPacker) -> Result<&'p mut Packer<fs::File>> {
+    packer
+        .as_mut()
+        .map(|p| &mut p.inner)
+        .ok_or_else(|| anyhow!("null packer"))
+}
+
+/// # Safety
+/// `path` and the `n_fields` entries of `names` must be nul-terminated strings, and `kinds`
+/// and `nullable` must each point at `n_fields` values.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_packer_create(
+    path: *const c_char,
+    n_fields: usize,
+    names: *const *const c_char,
+    kinds: *const u32,
+    nullable: *const bool,
+) -> *mut PackItPacker {
+    let mut ret = std::ptr::null_mut();
+    guard(|| {
+        if path.is_null() || names.is_null() || kinds.is_null() || nullable.is_null() {
+            bail!("null argument");
+        }
+
+        let schema = (0..n_fields)
+            .map(|i| -> Result<TableField> {
+                let name = CStr::from_ptr(*names.add(i))
+                    .to_str()
+                    .with_context(|| anyhow!("field {} name", i))?;
+                Ok(TableField::new(
+                    name,
+                    kind_from_c(*kinds.add(i))?,
+                    *nullable.add(i),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let path = CStr::from_ptr(path).to_str().context("path")?;
+        let file = fs::File::create(path).with_context(|| anyhow!("creating {:?}", path))?;
+        ret = Box::into_raw(Box::new(PackItPacker {
+            inner: Packer::new(file, &schema)?,
+        }));
+        Ok(())
+    });
+    ret
+}
+
+/// # Safety
+/// `packer` must have come from `pack_it_packer_create`, and not have been finished or freed.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_null(packer: *mut PackItPacker, col: usize) -> c_int {
+    guard(|| self::packer(packer)?.table().push_null(col))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_bool(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: bool,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_bool(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_u8(packer: *mut PackItPacker, col: usize, val: u8) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_u16(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: u16,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_u32(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: u32,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_u64(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: u64,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_i8(packer: *mut PackItPacker, col: usize, val: i8) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_i16(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: i16,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`. Also used for Date and TimeMillis columns.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_i32(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: i32,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`. Also used for TimestampSecsZ, DateMillis and TimeMicros columns.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_i64(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: i64,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_f32(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: f32,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_f64(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: f64,
+) -> c_int {
+    guard(|| self::packer(packer)?.table().push_primitive(col, Some(val)))
+}
+
+/// # Safety
+/// As `pack_it_push_null`, and `val` must point at `len` bytes of utf-8.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_str(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: *const c_char,
+    len: usize,
+) -> c_int {
+    guard(|| {
+        if val.is_null() {
+            bail!("null string");
+        }
+        let val = std::slice::from_raw_parts(val.cast::<u8>(), len);
+        let val = std::str::from_utf8(val).context("pushing a string")?;
+        self::packer(packer)?.table().push_str(col, Some(val))
+    })
+}
+
+/// # Safety
+/// As `pack_it_push_null`, and `val` must point at `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_push_bytes(
+    packer: *mut PackItPacker,
+    col: usize,
+    val: *const u8,
+    len: usize,
+) -> c_int {
+    guard(|| {
+        if val.is_null() {
+            bail!("null bytes");
+        }
+        let val = std::slice::from_raw_parts(val, len);
+        let table = self::packer(packer)?.table();
+        match table.schema().get(col).map(|f| &f.kind) {
+            Some(Kind::Geometry | Kind::Binary) => table.push_bytes(col, Some(val)),
+            _ => table.push_fsb(col, Some(val)),
+        }
+    })
+}
+
+/// Call after every row; flushes a row group if there's enough data buffered.
+///
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_end_row(packer: *mut PackItPacker) -> c_int {
+    guard(|| self::packer(packer)?.consider_flushing())
+}
+
+/// # Safety
+/// As `pack_it_push_null`.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_flush(packer: *mut PackItPacker) -> c_int {
+    guard(|| self::packer(packer)?.flush())
+}
+
+/// Completes the file, and frees the packer, even on failure.
+///
+/// # Safety
+/// As `pack_it_push_null`; `packer` must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_finish(packer: *mut PackItPacker) -> c_int {
+    guard(|| {
+        if packer.is_null() {
+            bail!("null packer");
+        }
+        let packer = Box::from_raw(packer);
+        packer.inner.finish()?.sync_all()?;
+        Ok(())
+    })
+}
+
+/// Abandons the packer; any rows which haven't been flushed are lost.
+///
+/// # Safety
+/// As `pack_it_finish`. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn pack_it_free(packer: *mut PackItPacker) {
+    if !packer.is_null() {
+        drop(Box::from_raw(packer));
+    }
+}
+
+/// The message for the last failure on this thread, or null. Valid until the next call.
+#[no_mangle]
+pub extern "C" fn pack_it_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|e| e.as_ptr())
+            .unwrap_or(std::ptr::null())
+    })
+}
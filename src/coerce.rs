@@ -0,0 +1,156 @@
+// pushing e.g. an i32 into an I64 column; see Table::set_coercion
+
+use std::any::Any;
+
+use anyhow::{anyhow, bail, Result};
+use arrow2::types::NativeType;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Coercion {
+    // the value's type must be the column's
+    #[default]
+    Exact,
+    // any conversion which can't lose information, e.g. i32 into I64, but not i64 into F64
+    Widen,
+    // as Widen, and anything else, as long as this value survives, e.g. u64 into I64, 3.0 into I32
+    Checked,
+}
+
+enum Number {
+    Int { val: i128, min: i128, max: i128 },
+    Float(f64),
+}
+
+// the kinds' storage is only ever (un)signed integers, f32 and f64, but anything may be pushed
+fn number<T: NativeType>(val: T) -> Option<Number> {
+    fn int<V: Into<i128> + Copy>(val: V, min: V, max: V) -> Number {
+        Number::Int {
+            val: val.into(),
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    let val = &val as &dyn Any;
+    Some(if let Some(&v) = val.downcast_ref::<i8>() {
+        int(v, i8::MIN, i8::MAX)
+    } else if let Some(&v) = val.downcast_ref::<i16>() {
+        int(v, i16::MIN, i16::MAX)
+    } else if let Some(&v) = val.downcast_ref::<i32>() {
+        int(v, i32::MIN, i32::MAX)
+    } else if let Some(&v) = val.downcast_ref::<i64>() {
+        int(v, i64::MIN, i64::MAX)
+    } else if let Some(&v) = val.downcast_ref::<u8>() {
+        int(v, u8::MIN, u8::MAX)
+    } else if let Some(&v) = val.downcast_ref::<u16>() {
+        int(v, u16::MIN, u16::MAX)
+    } else if let Some(&v) = val.downcast_ref::<u32>() {
+        int(v, u32::MIN, u32::MAX)
+    } else if let Some(&v) = val.downcast_ref::<u64>() {
+        int(v, u64::MIN, u64::MAX)
+    } else if let Some(&v) = val.downcast_ref::<f32>() {
+        Number::Float(f64::from(v))
+    } else if let Some(&v) = val.downcast_ref::<f64>() {
+        Number::Float(v)
+    } else {
+        return None;
+    })
+}
+
+// f64s hold integers exactly up to here
+const EXACT_FLOAT: i128 = 1 << 53;
+// and f32s up to here
+const EXACT_F32: i128 = 1 << 24;
+
+pub(crate) fn to_int<T: NativeType, U: TryFrom<i128>>(
+    val: T,
+    coercion: Coercion,
+    target: (i128, i128),
+) -> Result<U> {
+    let (target_min, target_max) = target;
+    let val = match number(val).ok_or_else(|| unsupported::<T>())? {
+        Number::Int { val, min, max } => {
+            if coercion == Coercion::Widen && (min < target_min || max > target_max) {
+                bail!(
+                    "{} may not fit, so needs checked coercion",
+                    type_name::<T>()
+                );
+            }
+            val
+        }
+        Number::Float(val) => {
+            if coercion != Coercion::Checked {
+                bail!("floats can only become integers with checked coercion");
+            }
+            if val.fract() != 0.0 || !val.is_finite() {
+                bail!("{} isn't an integer", val);
+            }
+            val as i128
+        }
+    };
+
+    if !(target_min..=target_max).contains(&val) {
+        bail!("{} doesn't fit in {}..={}", val, target_min, target_max);
+    }
+
+    U::try_from(val).map_err(|_| anyhow!("{} doesn't fit", val))
+}
+
+pub(crate) fn to_float<T: NativeType>(val: T, coercion: Coercion) -> Result<f64> {
+    match number(val).ok_or_else(|| unsupported::<T>())? {
+        Number::Float(val) => Ok(val),
+        Number::Int { val, min, max } => {
+            let exact = min >= -EXACT_FLOAT && max <= EXACT_FLOAT;
+            if coercion == Coercion::Widen && !exact {
+                bail!(
+                    "{} may lose precision, so needs checked coercion",
+                    type_name::<T>()
+                );
+            }
+            if !(-EXACT_FLOAT..=EXACT_FLOAT).contains(&val) {
+                bail!("{} can't be represented exactly as a float", val);
+            }
+            Ok(val as f64)
+        }
+    }
+}
+
+// f64s only become f32s with checked coercion, if the value survives
+pub(crate) fn to_f32<T: NativeType>(val: T, coercion: Coercion) -> Result<f32> {
+    match number(val).ok_or_else(|| unsupported::<T>())? {
+        Number::Float(val) => {
+            if coercion != Coercion::Checked {
+                bail!(
+                    "{} may lose precision, so needs checked coercion",
+                    type_name::<T>()
+                );
+            }
+            let narrowed = val as f32;
+            if f64::from(narrowed) != val && !val.is_nan() {
+                bail!("{} can't be represented exactly as an f32", val);
+            }
+            Ok(narrowed)
+        }
+        Number::Int { val, min, max } => {
+            let exact = min >= -EXACT_F32 && max <= EXACT_F32;
+            if coercion == Coercion::Widen && !exact {
+                bail!(
+                    "{} may lose precision, so needs checked coercion",
+                    type_name::<T>()
+                );
+            }
+            if !(-EXACT_F32..=EXACT_F32).contains(&val) {
+                bail!("{} can't be represented exactly as an f32", val);
+            }
+            Ok(val as f32)
+        }
+    }
+}
+
+fn unsupported<T>() -> anyhow::Error {
+    anyhow!("can't coerce a {}", type_name::<T>())
+}
+
+fn type_name<T>() -> &'static str {
+    std::any::type_name::<T>()
+}
@@ -0,0 +1,183 @@
+// per-column data contracts, checked as values are pushed into a Table
+
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{bail, Result};
+use arrow2::types::NativeType;
+use regex::Regex;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Violation {
+    #[default]
+    Error,
+    // the column must be nullable
+    Null,
+    // numbers to the min / max, strings truncated to max_len; anything else is an error
+    Clamp,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Constraint {
+    // compared as f64s, so very large i64s are approximate
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+
+    // in bytes
+    pub max_len: Option<usize>,
+    pub pattern: Option<Regex>,
+    pub allowed: Option<HashSet<String>>,
+
+    pub on_violation: Violation,
+}
+
+// regexes don't compare, so their patterns are
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Self) -> bool {
+        self.min == other.min
+            && self.max == other.max
+            && self.max_len == other.max_len
+            && self.pattern.as_ref().map(Regex::as_str) == other.pattern.as_ref().map(Regex::as_str)
+            && self.allowed == other.allowed
+            && self.on_violation == other.on_violation
+    }
+}
+
+// None means push a null instead
+impl Constraint {
+    pub(crate) fn check_str<'v>(
+        &self,
+        column: &dyn fmt::Display,
+        val: &'v str,
+    ) -> Result<Option<Cow<'v, str>>> {
+        if let Some(allowed) = &self.allowed {
+            if !allowed.contains(val) {
+                return self.reject(column, format!("{:?} isn't an allowed value", val));
+            }
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(val) {
+                return self.reject(column, format!("{:?} doesn't match {:?}", val, pattern));
+            }
+        }
+
+        match self.max_len {
+            Some(max_len) if val.len() > max_len => match self.on_violation {
+                Violation::Clamp => {
+                    let mut end = max_len;
+                    while !val.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    Ok(Some(Cow::Borrowed(&val[..end])))
+                }
+                _ => self.reject(
+                    column,
+                    format!("{} bytes is longer than {}", val.len(), max_len),
+                ),
+            },
+            _ => Ok(Some(Cow::Borrowed(val))),
+        }
+    }
+
+    pub(crate) fn check_number<T: NativeType>(
+        &self,
+        column: &dyn fmt::Display,
+        val: T,
+    ) -> Result<Option<T>> {
+        let number = match as_f64(&val) {
+            Some(number) => number,
+            None => return Ok(Some(val)),
+        };
+
+        let bound = match (self.min, self.max) {
+            (Some(min), _) if number < min => min,
+            (_, Some(max)) if number > max => max,
+            _ => return Ok(Some(val)),
+        };
+
+        match self.on_violation {
+            Violation::Clamp => {
+                let mut val = val;
+                set_f64(&mut val, bound);
+                Ok(Some(val))
+            }
+            _ => self.reject(
+                column,
+                format!(
+                    "{} is outside {:?}..={:?}",
+                    number,
+                    self.min.unwrap_or(f64::NEG_INFINITY),
+                    self.max.unwrap_or(f64::INFINITY)
+                ),
+            ),
+        }
+    }
+
+    fn reject<T>(&self, column: &dyn fmt::Display, problem: String) -> Result<Option<T>> {
+        match self.on_violation {
+            Violation::Null => Ok(None),
+            Violation::Error | Violation::Clamp => {
+                bail!("{} constraint violated: {}", column, problem)
+            }
+        }
+    }
+}
+
+// the kinds' native types; some regrets, again
+fn as_f64<T: NativeType>(val: &T) -> Option<f64> {
+    let val = val as &dyn Any;
+    if let Some(v) = val.downcast_ref::<i64>() {
+        Some(*v as f64)
+    } else if let Some(v) = val.downcast_ref::<i32>() {
+        Some(f64::from(*v))
+    } else if let Some(v) = val.downcast_ref::<i16>() {
+        Some(f64::from(*v))
+    } else if let Some(v) = val.downcast_ref::<i8>() {
+        Some(f64::from(*v))
+    } else if let Some(v) = val.downcast_ref::<u64>() {
+        Some(*v as f64)
+    } else if let Some(v) = val.downcast_ref::<u32>() {
+        Some(f64::from(*v))
+    } else if let Some(v) = val.downcast_ref::<u16>() {
+        Some(f64::from(*v))
+    } else if let Some(v) = val.downcast_ref::<u8>() {
+        Some(f64::from(*v))
+    } else if let Some(v) = val.downcast_ref::<f32>() {
+        Some(f64::from(*v))
+    } else {
+        val.downcast_ref::<f64>().copied()
+    }
+}
+
+fn set_f64<T: NativeType>(val: &mut T, to: f64) {
+    let val = val as &mut dyn Any;
+    if let Some(v) = val.downcast_mut::<i64>() {
+        *v = to as i64;
+    } else if let Some(v) = val.downcast_mut::<i32>() {
+        *v = to as i32;
+    } else if let Some(v) = val.downcast_mut::<i16>() {
+        *v = to as i16;
+    } else if let Some(v) = val.downcast_mut::<i8>() {
+        *v = to as i8;
+    } else if let Some(v) = val.downcast_mut::<u64>() {
+        *v = to as u64;
+    } else if let Some(v) = val.downcast_mut::<u32>() {
+        *v = to as u32;
+    } else if let Some(v) = val.downcast_mut::<u16>() {
+        *v = to as u16;
+    } else if let Some(v) = val.downcast_mut::<u8>() {
+        *v = to as u8;
+    } else if let Some(v) = val.downcast_mut::<f32>() {
+        *v = to as f32;
+    } else if let Some(v) = val.downcast_mut::<f64>() {
+        *v = to;
+    }
+}
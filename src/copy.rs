@@ -0,0 +1,422 @@
+// copying whole arrays into a column's builder, and copying builders, without going through the
+// push_* functions: the values were checked when they were first pushed (or came from a file),
+// so constraints and defaults aren't applied again. Everything is checked before anything is
+// copied, so a column is never left with part of an array in it
+
+use anyhow::{anyhow, ensure, Result};
+use arrow2::array::growable::make_growable;
+use arrow2::array::{
+    Array, BinaryArray, BooleanArray, FixedSizeBinaryArray, ListArray, MapArray, MutableArray,
+    MutableBinaryArray, MutableBooleanArray, MutableFixedSizeBinaryArray, MutableListArray,
+    MutableNullArray, MutablePrimitiveArray, MutableStructArray, MutableUtf8Array, NullArray,
+    PrimitiveArray, StructArray, Utf8Array,
+};
+use arrow2::bitmap::{Bitmap, MutableBitmap};
+use arrow2::datatypes::DataType;
+use arrow2::offset::OffsetsBuffer;
+use arrow2::types::NativeType;
+
+use crate::dictionary::MutableEnumArray;
+use crate::nested::MutableMapArray;
+use crate::table::built;
+use crate::Kind;
+
+impl Kind {
+    // for a builder made by builder_with_capacity for this kind, e.g. to take a batch from
+    // without disturbing the table
+    pub(crate) fn clone_builder(&self, arr: &dyn MutableArray) -> Box<dyn MutableArray> {
+        match self {
+            Kind::Bool => cloned::<MutableBooleanArray>(arr),
+            Kind::Null => Box::new(MutableNullArray::new(DataType::Null, arr.len())),
+            Kind::U8 => cloned::<MutablePrimitiveArray<u8>>(arr),
+            Kind::U16 => cloned::<MutablePrimitiveArray<u16>>(arr),
+            Kind::U32 => cloned::<MutablePrimitiveArray<u32>>(arr),
+            Kind::U64 => cloned::<MutablePrimitiveArray<u64>>(arr),
+            Kind::I8 => cloned::<MutablePrimitiveArray<i8>>(arr),
+            Kind::I16 => cloned::<MutablePrimitiveArray<i16>>(arr),
+            Kind::I32 | Kind::Date | Kind::TimeMillis => cloned::<MutablePrimitiveArray<i32>>(arr),
+            Kind::I64
+            | Kind::TimestampSecsZ
+            | Kind::Timestamp { .. }
+            | Kind::DateMillis
+            | Kind::TimeMicros
+            | Kind::Duration(_) => cloned::<MutablePrimitiveArray<i64>>(arr),
+            Kind::Decimal { .. } => cloned::<MutablePrimitiveArray<i128>>(arr),
+            Kind::F32 => cloned::<MutablePrimitiveArray<f32>>(arr),
+            Kind::F64 => cloned::<MutablePrimitiveArray<f64>>(arr),
+            Kind::String | Kind::Json => cloned::<MutableUtf8Array<i32>>(arr),
+            Kind::LargeString => cloned::<MutableUtf8Array<i64>>(arr),
+            Kind::Enum(_) => cloned::<MutableEnumArray>(arr),
+            Kind::Uuid | Kind::Ipv4 | Kind::Ipv6 | Kind::FixedBytes(_) => {
+                cloned::<MutableFixedSizeBinaryArray>(arr)
+            }
+            Kind::Geometry | Kind::Binary => cloned::<MutableBinaryArray<i32>>(arr),
+            Kind::List(inner) => {
+                Box::new(clone_list(built(arr), |values| inner.clone_builder(values)))
+            }
+            Kind::Struct(fields) => Box::new(clone_struct(
+                built(arr),
+                fields.iter().map(|child| &child.kind),
+            )),
+            Kind::Map(key, value) => {
+                let arr = built::<MutableMapArray>(arr);
+                let entries = clone_list(arr.entries(), |entries| {
+                    Box::new(clone_struct(built(entries), [key.as_ref(), value.as_ref()]))
+                });
+                Box::new(MutableMapArray::from_entries(
+                    arr.data_type().clone(),
+                    entries,
+                ))
+            }
+        }
+    }
+
+    // whether extend can copy all of `arr` to this builder, made by builder_with_capacity for
+    // this kind; nothing is copied
+    pub(crate) fn check_extend(&self, builder: &dyn MutableArray, arr: &dyn Array) -> Result<()> {
+        match self {
+            Kind::Bool => input::<BooleanArray>(self, arr).map(drop),
+            Kind::Null => input::<NullArray>(self, arr).map(drop),
+            Kind::U8 => input::<PrimitiveArray<u8>>(self, arr).map(drop),
+            Kind::U16 => input::<PrimitiveArray<u16>>(self, arr).map(drop),
+            Kind::U32 => input::<PrimitiveArray<u32>>(self, arr).map(drop),
+            Kind::U64 => input::<PrimitiveArray<u64>>(self, arr).map(drop),
+            Kind::I8 => input::<PrimitiveArray<i8>>(self, arr).map(drop),
+            Kind::I16 => input::<PrimitiveArray<i16>>(self, arr).map(drop),
+            Kind::I32 | Kind::Date | Kind::TimeMillis => {
+                input::<PrimitiveArray<i32>>(self, arr).map(drop)
+            }
+            Kind::I64
+            | Kind::TimestampSecsZ
+            | Kind::Timestamp { .. }
+            | Kind::DateMillis
+            | Kind::TimeMicros
+            | Kind::Duration(_) => input::<PrimitiveArray<i64>>(self, arr).map(drop),
+            Kind::Decimal { .. } => input::<PrimitiveArray<i128>>(self, arr).map(drop),
+            Kind::F32 => input::<PrimitiveArray<f32>>(self, arr).map(drop),
+            Kind::F64 => input::<PrimitiveArray<f64>>(self, arr).map(drop),
+            Kind::String | Kind::Json => {
+                let arr = input::<Utf8Array<i32>>(self, arr)?;
+                let builder = built::<MutableUtf8Array<i32>>(builder);
+                fits(builder.values().len(), arr.offsets())
+            }
+            Kind::LargeString => input::<Utf8Array<i64>>(self, arr).map(drop),
+            Kind::Geometry | Kind::Binary => {
+                let arr = input::<BinaryArray<i32>>(self, arr)?;
+                let builder = built::<MutableBinaryArray<i32>>(builder);
+                fits(builder.values().len(), arr.offsets())
+            }
+            Kind::Uuid | Kind::Ipv4 | Kind::Ipv6 | Kind::FixedBytes(_) => {
+                let arr = input::<FixedSizeBinaryArray>(self, arr)?;
+                let size = built::<MutableFixedSizeBinaryArray>(builder).size();
+                ensure!(
+                    arr.size() == size,
+                    "can't copy {}-byte values to a {} column",
+                    arr.size(),
+                    self
+                );
+                Ok(())
+            }
+            Kind::Enum(_) => built::<MutableEnumArray>(builder).keys_for(arr).map(drop),
+            Kind::List(inner) => {
+                let arr = input::<ListArray<i32>>(self, arr)?;
+                let builder = built::<MutableListArray<i32, Box<dyn MutableArray>>>(builder);
+                fits(builder.values().len(), arr.offsets())?;
+                let values = used(arr.offsets(), arr.values().as_ref());
+                inner.check_extend(builder.values().as_ref(), values.as_ref())
+            }
+            Kind::Struct(fields) => {
+                check_struct(
+                    self,
+                    built(builder),
+                    fields.iter().map(|child| &child.kind),
+                    arr,
+                )?;
+                let arr = input::<StructArray>(self, arr)?;
+                for (child, values) in fields.iter().zip(arr.values()) {
+                    ensure!(
+                        child.nullable || !has_nulls_under(values.as_ref(), arr.validity()),
+                        "child {:?} isn't nullable, but has nulls",
+                        child.name
+                    );
+                }
+                Ok(())
+            }
+            Kind::Map(key, value) => {
+                let arr = input::<MapArray>(self, arr)?;
+                let entries = built::<MutableMapArray>(builder).entries();
+                fits(entries.values().len(), arr.offsets())?;
+                check_struct(
+                    self,
+                    built(entries.values().as_ref()),
+                    [key.as_ref(), value.as_ref()],
+                    used(arr.offsets(), arr.field().as_ref()).as_ref(),
+                )
+            }
+        }
+    }
+
+    // copies the rows of `arr`, which check_extend has accepted, after those already in the
+    // builder
+    pub(crate) fn extend(&self, builder: &mut dyn MutableArray, arr: &dyn Array) {
+        match self {
+            Kind::Bool => built_mut::<MutableBooleanArray>(builder)
+                .extend_trusted_len(checked::<BooleanArray>(arr).iter()),
+            Kind::Null => {
+                let len = builder.len() + arr.len();
+                *built_mut::<MutableNullArray>(builder) =
+                    MutableNullArray::new(DataType::Null, len);
+            }
+            Kind::U8 => extend_primitive::<u8>(builder, arr),
+            Kind::U16 => extend_primitive::<u16>(builder, arr),
+            Kind::U32 => extend_primitive::<u32>(builder, arr),
+            Kind::U64 => extend_primitive::<u64>(builder, arr),
+            Kind::I8 => extend_primitive::<i8>(builder, arr),
+            Kind::I16 => extend_primitive::<i16>(builder, arr),
+            Kind::I32 | Kind::Date | Kind::TimeMillis => extend_primitive::<i32>(builder, arr),
+            Kind::I64
+            | Kind::TimestampSecsZ
+            | Kind::Timestamp { .. }
+            | Kind::DateMillis
+            | Kind::TimeMicros
+            | Kind::Duration(_) => extend_primitive::<i64>(builder, arr),
+            Kind::Decimal { .. } => extend_primitive::<i128>(builder, arr),
+            Kind::F32 => extend_primitive::<f32>(builder, arr),
+            Kind::F64 => extend_primitive::<f64>(builder, arr),
+            Kind::String | Kind::Json => built_mut::<MutableUtf8Array<i32>>(builder)
+                .extend_trusted_len(checked::<Utf8Array<i32>>(arr).iter()),
+            Kind::LargeString => built_mut::<MutableUtf8Array<i64>>(builder)
+                .extend_trusted_len(checked::<Utf8Array<i64>>(arr).iter()),
+            Kind::Geometry | Kind::Binary => built_mut::<MutableBinaryArray<i32>>(builder)
+                .extend_trusted_len(checked::<BinaryArray<i32>>(arr).iter()),
+            Kind::Uuid | Kind::Ipv4 | Kind::Ipv6 | Kind::FixedBytes(_) => {
+                let builder = built_mut::<MutableFixedSizeBinaryArray>(builder);
+                for value in checked::<FixedSizeBinaryArray>(arr).iter() {
+                    builder.push(value);
+                }
+            }
+            Kind::Enum(_) => built_mut::<MutableEnumArray>(builder).extend(arr),
+            Kind::List(inner) => {
+                let arr = checked::<ListArray<i32>>(arr);
+                extend_list(
+                    built_mut(builder),
+                    arr.offsets(),
+                    arr.validity(),
+                    arr.values().as_ref(),
+                    |values, arr| inner.extend(values, arr),
+                )
+            }
+            Kind::Struct(fields) => extend_struct(
+                built_mut(builder),
+                fields.iter().map(|child| &child.kind),
+                arr,
+            ),
+            Kind::Map(key, value) => {
+                let arr = checked::<MapArray>(arr);
+                extend_list(
+                    built_mut::<MutableMapArray>(builder).entries_mut(),
+                    arr.offsets(),
+                    arr.validity(),
+                    arr.field().as_ref(),
+                    |entries, arr| {
+                        extend_struct(built_mut(entries), [key.as_ref(), value.as_ref()], arr)
+                    },
+                )
+            }
+        }
+    }
+}
+
+fn cloned<T: MutableArray + Clone + 'static>(arr: &dyn MutableArray) -> Box<dyn MutableArray> {
+    Box::new(built::<T>(arr).clone())
+}
+
+fn clone_list(
+    arr: &MutableListArray<i32, Box<dyn MutableArray>>,
+    values: impl FnOnce(&dyn MutableArray) -> Box<dyn MutableArray>,
+) -> MutableListArray<i32, Box<dyn MutableArray>> {
+    MutableListArray::new_from_mutable(
+        values(arr.values().as_ref()),
+        arr.offsets().clone(),
+        arr.validity().cloned(),
+    )
+}
+
+fn clone_struct<'k>(
+    arr: &MutableStructArray,
+    kinds: impl IntoIterator<Item = &'k Kind>,
+) -> MutableStructArray {
+    let values = arr
+        .values()
+        .iter()
+        .zip(kinds)
+        .map(|(values, kind)| kind.clone_builder(values.as_ref()))
+        .collect();
+    MutableStructArray::try_new(arr.data_type().clone(), values, arr.validity().cloned())
+        .expect("the same as the original")
+}
+
+fn built_mut<T: MutableArray + 'static>(arr: &mut dyn MutableArray) -> &mut T {
+    arr.as_mut_any()
+        .downcast_mut()
+        .expect("builders are built for their kind")
+}
+
+// the array check_extend was given, once it's been checked
+fn checked<T: Array>(arr: &dyn Array) -> &T {
+    arr.as_any().downcast_ref().expect("checked")
+}
+
+fn input<'a, T: Array>(kind: &Kind, arr: &'a dyn Array) -> Result<&'a T> {
+    arr.as_any()
+        .downcast_ref()
+        .ok_or_else(|| anyhow!("can't copy {:?} to a {} column", arr.data_type(), kind))
+}
+
+// the builder's offsets are i32s, so the values it has and those being copied have to fit in one
+fn fits(existing: usize, offsets: &OffsetsBuffer<i32>) -> Result<()> {
+    let copied = offsets.last() - offsets.first();
+    ensure!(
+        i32::try_from(existing + copied as usize).is_ok(),
+        "copying {} bytes (or values) after {} would overflow the offsets",
+        copied,
+        existing
+    );
+    Ok(())
+}
+
+// the part of a list's values its offsets cover
+fn used(offsets: &OffsetsBuffer<i32>, values: &dyn Array) -> Box<dyn Array> {
+    let (start, end) = (*offsets.first() as usize, *offsets.last() as usize);
+    values.sliced(start, end - start)
+}
+
+fn check_struct<'k>(
+    kind: &Kind,
+    builder: &MutableStructArray,
+    kinds: impl IntoIterator<Item = &'k Kind>,
+    arr: &dyn Array,
+) -> Result<()> {
+    let arr = input::<StructArray>(kind, arr)?;
+    ensure!(
+        arr.values().len() == builder.values().len(),
+        "can't copy a struct of {} fields to a {} column",
+        arr.values().len(),
+        kind
+    );
+    for ((builder, kind), arr) in builder.values().iter().zip(kinds).zip(arr.values()) {
+        kind.check_extend(builder.as_ref(), arr.as_ref())?;
+    }
+    Ok(())
+}
+
+// nulls in a struct's null rows don't count
+fn has_nulls_under(values: &dyn Array, parent: Option<&Bitmap>) -> bool {
+    values.null_count() > 0
+        && (0..values.len()).any(|i| values.is_null(i) && parent.is_none_or(|p| p.get_bit(i)))
+}
+
+fn extend_primitive<T: NativeType>(builder: &mut dyn MutableArray, arr: &dyn Array) {
+    built_mut::<MutablePrimitiveArray<T>>(builder)
+        .extend_trusted_len(checked::<PrimitiveArray<T>>(arr).iter())
+}
+
+fn extend_struct<'k>(
+    builder: &mut MutableStructArray,
+    kinds: impl IntoIterator<Item = &'k Kind>,
+    arr: &dyn Array,
+) {
+    let arr = checked::<StructArray>(arr);
+    let len = builder.len();
+    for ((values, kind), arr) in builder.mut_values().iter_mut().zip(kinds).zip(arr.values()) {
+        kind.extend(values.as_mut(), arr.as_ref());
+    }
+    if builder.validity().is_none() && arr.null_count() == 0 {
+        return;
+    }
+    // MutableStructArray::push only works a row at a time, after its values
+    let mut validity = builder.validity().cloned().unwrap_or_else(|| {
+        let mut validity = MutableBitmap::with_capacity(builder.len());
+        validity.extend_constant(len, true);
+        validity
+    });
+    match arr.validity() {
+        Some(bits) => validity.extend_from_bitmap(bits),
+        None => validity.extend_constant(arr.len(), true),
+    }
+    let values = std::mem::take(builder.mut_values());
+    *builder = MutableStructArray::try_new(builder.data_type().clone(), values, Some(validity))
+        .expect("as long as its values");
+}
+
+// the valid rows are copied in runs; nulls are pushed one at a time, as try_extend_from_lengths
+// doesn't start a validity if there isn't one, and can't skip any values under them
+fn extend_list(
+    builder: &mut MutableListArray<i32, Box<dyn MutableArray>>,
+    offsets: &OffsetsBuffer<i32>,
+    validity: Option<&Bitmap>,
+    values: &dyn Array,
+    mut extend_values: impl FnMut(&mut dyn MutableArray, &dyn Array),
+) {
+    let offsets = offsets.buffer();
+    let rows = offsets.len() - 1;
+    let is_valid = |row: usize| validity.is_none_or(|v| v.get_bit(row));
+    let mut row = 0;
+    while row < rows {
+        if !is_valid(row) {
+            MutableArray::push_null(builder);
+            row += 1;
+            continue;
+        }
+        let start = row;
+        while row < rows && is_valid(row) {
+            row += 1;
+        }
+        let (from, to) = (offsets[start] as usize, offsets[row] as usize);
+        if to > from {
+            extend_values(
+                builder.mut_values().as_mut(),
+                &*values.sliced(from, to - from),
+            );
+        }
+        let lengths = offsets[start..=row]
+            .windows(2)
+            .map(|w| Some((w[1] - w[0]) as usize));
+        builder
+            .try_extend_from_lengths(lengths)
+            .expect("checked the offsets fit");
+    }
+}
+
+// the rows of `arr` in this order, as arrow2's take, which can't do maps or fixed-size bytes
+pub(crate) fn gather(arr: &dyn Array, rows: &[usize]) -> Box<dyn Array> {
+    let mut out = make_growable(&[arr], true, rows.len());
+    let mut i = 0;
+    while i < rows.len() {
+        // runs of consecutive rows are copied together
+        let start = i;
+        i += 1;
+        while i < rows.len() && rows[i] == rows[i - 1] + 1 {
+            i += 1;
+        }
+        out.extend(0, rows[start], i - start);
+    }
+    out.as_box()
+}
+
+// rows from several arrays of the same type, each (which array, which row), in this order
+pub(crate) fn interleave(arrs: &[&dyn Array], rows: &[(usize, usize)]) -> Box<dyn Array> {
+    let mut out = make_growable(arrs, true, rows.len());
+    let mut i = 0;
+    while i < rows.len() {
+        // runs of consecutive rows of the same array are copied together
+        let start = i;
+        i += 1;
+        while i < rows.len() && rows[i] == (rows[i - 1].0, rows[i - 1].1 + 1) {
+            i += 1;
+        }
+        let (arr, row) = rows[start];
+        out.extend(arr, row, i - start);
+    }
+    out.as_box()
+}
@@ -0,0 +1,35 @@
+// calendar dates, for the Date and DateMillis kinds, which store days (or millis) since 1970-01-01
+
+use anyhow::{ensure, Result};
+
+pub const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// proleptic Gregorian, as arrow (and parquet) dates are; see
+// https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+pub fn days_from_civil(year: i32, month: u32, day: u32) -> Result<i64> {
+    ensure!((1..=12).contains(&month), "there's no month {}", month);
+    ensure!(
+        day >= 1 && day <= days_in_month(year, month),
+        "there's no day {} in {}-{:02}",
+        day,
+        year,
+        month
+    );
+
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Ok(era * 146_097 + day_of_era - 719_468)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,249 @@
+// plausible random data for a schema, for benchmarks, fuzzers, and demo files
+
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use arrow2::datatypes::TimeUnit;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::constraint::Constraint;
+use crate::date::MILLIS_PER_DAY;
+use crate::{Kind, Table, TableField};
+
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+// the same fields, rows and seed always give the same table
+pub fn generate(fields: &[TableField], rows: usize, seed: u64) -> Result<Table> {
+    let mut table = Table::from_fields(fields, rows);
+    fill(&mut table, rows, seed)?;
+    Ok(table)
+}
+
+// append rows to a table, within its fields' nullability and constraints; patterns can't be
+// generated, so a field with one also needs a list of allowed values. Constant columns are left
+// to their constant
+pub fn fill(table: &mut Table, rows: usize, seed: u64) -> Result<()> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let fields = table.schema().to_vec();
+
+    for _ in 0..rows {
+        for (i, field) in fields.iter().enumerate() {
+            if table.is_constant(i) {
+                continue;
+            }
+            if field.nullable && rng.random_bool(0.1) {
+                table.push_null(i)?;
+                continue;
+            }
+
+            let constraint = field.constraint.as_ref();
+            match field.kind {
+                Kind::Null => table.push_null(i)?,
+                Kind::Bool => table.push_bool(i, Some(rng.random()))?,
+                Kind::U8 => {
+                    let val = integer(&mut rng, field, constraint, (0., u8::MAX as f64))?;
+                    table.push_primitive(i, Some(val as u8))?
+                }
+                Kind::U16 => {
+                    let val = integer(&mut rng, field, constraint, (0., u16::MAX as f64))?;
+                    table.push_primitive(i, Some(val as u16))?
+                }
+                Kind::U32 => {
+                    let val = integer(&mut rng, field, constraint, (0., u32::MAX as f64))?;
+                    table.push_primitive(i, Some(val as u32))?
+                }
+                Kind::U64 => {
+                    let range = (0., 1_000_000_000_000.);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as u64))?
+                }
+                Kind::I8 => {
+                    let range = (i8::MIN as f64, i8::MAX as f64);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i8))?
+                }
+                Kind::I16 => {
+                    let range = (i16::MIN as f64, i16::MAX as f64);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i16))?
+                }
+                Kind::I32 => {
+                    let range = (-1_000_000., 1_000_000.);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i32))?
+                }
+                Kind::I64 => {
+                    let range = (-1_000_000_000_000., 1_000_000_000_000.);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i64))?
+                }
+                Kind::F32 => {
+                    let (lo, hi) = bounds(field, constraint, (-1_000_000., 1_000_000.))?;
+                    table.push_primitive(i, Some(rng.random_range(lo..=hi) as f32))?
+                }
+                Kind::F64 => {
+                    let (lo, hi) = bounds(field, constraint, (-1_000_000., 1_000_000.))?;
+                    table.push_primitive(i, Some(rng.random_range(lo..=hi)))?
+                }
+                Kind::TimestampSecsZ => {
+                    // 2000 to 2030
+                    let range = (946_684_800., 1_893_456_000.);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i64))?
+                }
+                Kind::Timestamp { unit, .. } => {
+                    let per_second = per_second(unit);
+                    let range = (946_684_800. * per_second, 1_893_456_000. * per_second);
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i64))?
+                }
+                Kind::Duration(unit) => {
+                    // up to an hour
+                    let range = (0., 3_600. * per_second(unit));
+                    let val = integer(&mut rng, field, constraint, range)?;
+                    table.push_primitive(i, Some(val as i64))?
+                }
+                Kind::Decimal { precision, .. } => {
+                    // f64s can't go much further
+                    let digits = precision.min(15) as i32;
+                    let limit = 10f64.powi(digits) - 1.;
+                    let val = integer(&mut rng, field, constraint, (-limit, limit))?;
+                    table.push_decimal(i, Some(val as i128))?
+                }
+                Kind::Date => {
+                    // 2000 to 2030
+                    let val = integer(&mut rng, field, constraint, (10_957., 21_915.))?;
+                    table.push_primitive(i, Some(val as i32))?
+                }
+                Kind::DateMillis => {
+                    let val = integer(&mut rng, field, constraint, (10_957., 21_915.))?;
+                    table.push_primitive(i, Some(val as i64 * MILLIS_PER_DAY))?
+                }
+                Kind::TimeMillis => {
+                    let val = integer(&mut rng, field, constraint, (0., 86_399_999.))?;
+                    table.push_primitive(i, Some(val as i32))?
+                }
+                Kind::TimeMicros => {
+                    let val = integer(&mut rng, field, constraint, (0., 86_399_999_999.))?;
+                    table.push_primitive(i, Some(val as i64))?
+                }
+                Kind::Uuid => {
+                    let mut uuid: [u8; 16] = rng.random();
+                    // version 4, variant 1
+                    uuid[6] = (uuid[6] & 0x0f) | 0x40;
+                    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+                    table.push_fsb(i, Some(uuid))?
+                }
+                Kind::Ipv4 => {
+                    let v4: [u8; 4] = rng.random();
+                    table.push_ip(i, Some(IpAddr::from(v4)))?
+                }
+                Kind::Ipv6 => {
+                    let v6: [u8; 16] = rng.random();
+                    table.push_ip(i, Some(IpAddr::from(v6)))?
+                }
+                Kind::Enum(ref values) => {
+                    let val = &values[rng.random_range(0..values.len())];
+                    table.push_str(i, Some(val))?
+                }
+                Kind::String | Kind::LargeString => {
+                    let val = string(&mut rng, field, constraint)?;
+                    table.push_str(i, Some(&val))?
+                }
+                Kind::Json => {
+                    let val = format!("{{\"n\":{}}}", rng.random::<u32>());
+                    table.push_str(i, Some(&val))?
+                }
+                Kind::Geometry => {
+                    let lon = rng.random_range(-180.0..=180.0);
+                    let lat = rng.random_range(-90.0..=90.0);
+                    table.push_point(i, Some((lon, lat)))?
+                }
+                Kind::FixedBytes(size) => {
+                    let val: Vec<u8> = (0..size).map(|_| rng.random()).collect();
+                    table.push_fsb(i, Some(val))?
+                }
+                Kind::List(_) | Kind::Struct(_) | Kind::Map(..) => bail!(
+                    "can't generate {} columns, for {:?}",
+                    field.kind,
+                    field.name
+                ),
+                Kind::Binary => {
+                    let len = rng.random_range(0..32);
+                    let val: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+                    table.push_bytes(i, Some(&val))?
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn per_second(unit: TimeUnit) -> f64 {
+    match unit {
+        TimeUnit::Second => 1.,
+        TimeUnit::Millisecond => 1e3,
+        TimeUnit::Microsecond => 1e6,
+        TimeUnit::Nanosecond => 1e9,
+    }
+}
+
+// the constraint's range, within the default
+fn bounds(
+    field: &TableField,
+    constraint: Option<&Constraint>,
+    (lo, hi): (f64, f64),
+) -> Result<(f64, f64)> {
+    let lo = constraint.and_then(|c| c.min).map_or(lo, |min| min.max(lo));
+    let hi = constraint.and_then(|c| c.max).map_or(hi, |max| max.min(hi));
+    if lo > hi {
+        bail!("no values for {:?} between {} and {}", field.name, lo, hi);
+    }
+    Ok((lo, hi))
+}
+
+fn integer(
+    rng: &mut SmallRng,
+    field: &TableField,
+    constraint: Option<&Constraint>,
+    default: (f64, f64),
+) -> Result<f64> {
+    let (lo, hi) = bounds(field, constraint, default)?;
+    let (lo, hi) = (lo.ceil(), hi.floor());
+    if lo > hi {
+        bail!("no integers for {:?} between {} and {}", field.name, lo, hi);
+    }
+    Ok(rng.random_range(lo..=hi).round())
+}
+
+fn string(
+    rng: &mut SmallRng,
+    field: &TableField,
+    constraint: Option<&Constraint>,
+) -> Result<String> {
+    if let Some(allowed) = constraint.and_then(|c| c.allowed.as_ref()) {
+        // sorted, as the set's order changes between runs
+        let mut allowed = allowed.iter().collect::<Vec<_>>();
+        allowed.sort();
+        if allowed.is_empty() {
+            bail!("no values are allowed for {:?}", field.name);
+        }
+        return Ok(allowed[rng.random_range(0..allowed.len())].to_string());
+    }
+
+    if let Some(pattern) = constraint.and_then(|c| c.pattern.as_ref()) {
+        bail!(
+            "can't generate values matching {:?} for {:?}",
+            pattern,
+            field.name
+        );
+    }
+
+    let max_len = constraint.and_then(|c| c.max_len).unwrap_or(16).min(32);
+    let len = rng.random_range(0..=max_len);
+    Ok((0..len)
+        .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())] as char)
+        .collect())
+}
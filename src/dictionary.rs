@@ -0,0 +1,160 @@
+// the Enum kind: strings from a fixed list, stored as their index in it, so they're written
+// dictionary encoded, with the list as the dictionary
+
+use std::any::Any;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use arrow2::array::{
+    Array, DictionaryArray, DictionaryKey, MutableArray, MutablePrimitiveArray, TryPush, Utf8Array,
+};
+use arrow2::bitmap::MutableBitmap;
+use arrow2::datatypes::{DataType, IntegerType};
+
+use crate::MemUsage;
+
+pub(crate) fn enum_type() -> DataType {
+    DataType::Dictionary(IntegerType::UInt32, Box::new(DataType::Utf8), false)
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MutableEnumArray {
+    data_type: DataType,
+    values: Utf8Array<i32>,
+    index: HashMap<String, u32>,
+    keys: MutablePrimitiveArray<u32>,
+}
+
+impl MutableEnumArray {
+    // the values are unique, and there aren't too many of them; see Kind::check
+    pub(crate) fn new(values: &[String], capacity: usize) -> Self {
+        MutableEnumArray {
+            data_type: enum_type(),
+            values: Utf8Array::from_slice(values),
+            index: values
+                .iter()
+                .zip(0..)
+                .map(|(value, key)| (value.clone(), key))
+                .collect(),
+            keys: MutablePrimitiveArray::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn try_push(&mut self, name: &str, val: Option<&str>) -> Result<()> {
+        let key = val
+            .map(|val| {
+                self.index
+                    .get(val)
+                    .copied()
+                    .ok_or_else(|| anyhow!("{:?} isn't one of column {:?}'s values", val, name))
+            })
+            .transpose()?;
+        self.keys.try_push(key)?;
+        Ok(())
+    }
+
+    // the keys to copy a dictionary array of strings as, e.g. from another file, whose
+    // dictionary can be in any order, or have values that aren't used; every one that is used
+    // has to be one of ours
+    pub(crate) fn keys_for(&self, arr: &dyn Array) -> Result<Vec<Option<u32>>> {
+        macro_rules! keys {
+            ($($k:ty),*) => {
+                $(
+                    if let Some(arr) = arr.as_any().downcast_ref::<DictionaryArray<$k>>() {
+                        return self.remap(arr);
+                    }
+                )*
+            };
+        }
+        keys!(u8, u16, u32, u64, i8, i16, i32, i64);
+        bail!("can't copy {:?} to an enum", arr.data_type())
+    }
+
+    fn remap<K: DictionaryKey>(&self, arr: &DictionaryArray<K>) -> Result<Vec<Option<u32>>> {
+        let values = arr.values().as_any();
+        let values: Vec<Option<&str>> =
+            if let Some(values) = values.downcast_ref::<Utf8Array<i32>>() {
+                values.iter().collect()
+            } else if let Some(values) = values.downcast_ref::<Utf8Array<i64>>() {
+                values.iter().collect()
+            } else {
+                bail!("can't copy {:?} to an enum", arr.data_type())
+            };
+        arr.keys_iter()
+            .map(|key| {
+                key.map(|key| {
+                    values[key]
+                        .and_then(|value| self.index.get(value).copied())
+                        .ok_or_else(|| anyhow!("{:?} isn't one of the enum's values", values[key]))
+                })
+                .transpose()
+            })
+            .collect()
+    }
+
+    // as checked by keys_for
+    pub(crate) fn extend(&mut self, arr: &dyn Array) {
+        let keys = self.keys_for(arr).expect("checked");
+        self.keys.extend_trusted_len(keys.into_iter());
+    }
+}
+
+impl MutableEnumArray {
+    // ignoring the validity
+    pub(crate) fn value(&self, row: usize) -> &str {
+        let key = self.keys.values()[row];
+        self.values.value(usize::try_from(key).expect("u32 fits"))
+    }
+}
+
+impl MutableArray for MutableEnumArray {
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn validity(&self) -> Option<&MutableBitmap> {
+        self.keys.validity()
+    }
+
+    fn as_box(&mut self) -> Box<dyn Array> {
+        let keys = std::mem::take(&mut self.keys);
+        DictionaryArray::try_new(
+            self.data_type.clone(),
+            keys.into(),
+            self.values.clone().boxed(),
+        )
+        .expect("the keys are all from the index")
+        .boxed()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn push_null(&mut self) {
+        self.keys.push_null()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.keys.reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.keys.shrink_to_fit()
+    }
+}
+
+impl MemUsage for MutableEnumArray {
+    // the values don't grow with the rows, so aren't counted
+    fn mem_usage(&self) -> usize {
+        self.keys.mem_usage()
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Seek};
+
+use anyhow::{anyhow, ensure, Result};
+use arrow2::array::{get_display, Array};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Schema};
+use arrow2::io::parquet::read;
+
+use crate::repack::find_field;
+
+pub enum SchemaDifference {
+    OnlyInLeft(String),
+    OnlyInRight(String),
+    TypeChanged {
+        name: String,
+        left: DataType,
+        right: DataType,
+    },
+    NullabilityChanged {
+        name: String,
+        left: bool,
+        right: bool,
+    },
+}
+
+pub enum RowDifference {
+    OnlyInLeft {
+        key: String,
+    },
+    OnlyInRight {
+        key: String,
+    },
+    Changed {
+        key: String,
+        column: String,
+        left: String,
+        right: String,
+    },
+}
+
+pub struct Diff {
+    pub schema: Vec<SchemaDifference>,
+    pub rows: Vec<RowDifference>,
+    // we stopped looking after `limit` row differences
+    pub truncated: bool,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.schema.is_empty() && self.rows.is_empty()
+    }
+}
+
+struct Loaded {
+    schema: Schema,
+    chunks: Vec<Chunk<Box<dyn Array>>>,
+}
+
+// the whole file ends up in memory; fine for validating, not for anything clever
+fn load(mut f: impl Read + Seek) -> Result<Loaded> {
+    let metadata = read::read_metadata(&mut f)?;
+    let schema = read::infer_schema(&metadata)?;
+    let chunks = read::FileReader::new(f, metadata.row_groups, schema.clone(), None, None, None)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Loaded { schema, chunks })
+}
+
+impl Loaded {
+    fn rows(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(|(c, chunk)| (0..chunk.len()).map(move |row| (c, row)))
+    }
+
+    fn render(&self, col: usize, (chunk, row): (usize, usize)) -> String {
+        let arr = self.chunks[chunk].arrays()[col].as_ref();
+        let mut ret = String::new();
+        get_display(arr, "null")(&mut ret, row).expect("writing to a string");
+        ret
+    }
+}
+
+fn diff_schema(left: &Schema, right: &Schema) -> Vec<SchemaDifference> {
+    let mut ret = Vec::new();
+    for l in &left.fields {
+        let r = match find_field(right, &l.name) {
+            Some((_, r)) => r,
+            None => {
+                ret.push(SchemaDifference::OnlyInLeft(l.name.to_string()));
+                continue;
+            }
+        };
+
+        if l.data_type != r.data_type {
+            ret.push(SchemaDifference::TypeChanged {
+                name: l.name.to_string(),
+                left: l.data_type.clone(),
+                right: r.data_type.clone(),
+            });
+        }
+
+        if l.is_nullable != r.is_nullable {
+            ret.push(SchemaDifference::NullabilityChanged {
+                name: l.name.to_string(),
+                left: l.is_nullable,
+                right: r.is_nullable,
+            });
+        }
+    }
+
+    for r in &right.fields {
+        if find_field(left, &r.name).is_none() {
+            ret.push(SchemaDifference::OnlyInRight(r.name.to_string()));
+        }
+    }
+
+    ret
+}
+
+// match rows on the `key` column (or on position, if missing), and give up
+// after `limit` row differences
+pub fn diff(
+    left: impl Read + Seek,
+    right: impl Read + Seek,
+    key: Option<&str>,
+    limit: usize,
+) -> Result<Diff> {
+    let left = load(left)?;
+    let right = load(right)?;
+
+    let schema = diff_schema(&left.schema, &right.schema);
+
+    // (name, left col, right col) for everything we can compare
+    let common = left
+        .schema
+        .fields
+        .iter()
+        .enumerate()
+        .filter_map(|(l, f)| {
+            find_field(&right.schema, &f.name).map(|(r, _)| (f.name.as_str(), l, r))
+        })
+        .collect::<Vec<_>>();
+
+    let key_of = |loaded: &Loaded, col: Option<usize>, pos: usize, addr| match col {
+        Some(col) => loaded.render(col, addr),
+        None => format!("row {}", pos),
+    };
+
+    let (left_key, right_key) = match key {
+        Some(key) => {
+            let (_, l, r) = common
+                .iter()
+                .find(|(name, _, _)| *name == key)
+                .ok_or_else(|| anyhow!("key column {:?} must be present in both files", key))?;
+            (Some(*l), Some(*r))
+        }
+        None => (None, None),
+    };
+
+    let mut remaining = HashMap::new();
+    for (pos, addr) in right.rows().enumerate() {
+        let key = key_of(&right, right_key, pos, addr);
+        ensure!(
+            !remaining.contains_key(&key),
+            "duplicate key in right file: {:?}",
+            key
+        );
+        remaining.insert(key, addr);
+    }
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut seen = HashSet::new();
+
+    'rows: for (pos, addr) in left.rows().enumerate() {
+        let key = key_of(&left, left_key, pos, addr);
+        ensure!(
+            seen.insert(key.clone()),
+            "duplicate key in left file: {:?}",
+            key
+        );
+        let other = match remaining.remove(&key) {
+            Some(other) => other,
+            None => {
+                if rows.len() >= limit {
+                    truncated = true;
+                    break 'rows;
+                }
+                rows.push(RowDifference::OnlyInLeft { key });
+                continue;
+            }
+        };
+
+        for (name, l, r) in &common {
+            let lv = left.render(*l, addr);
+            let rv = right.render(*r, other);
+            if lv == rv {
+                continue;
+            }
+            if rows.len() >= limit {
+                truncated = true;
+                break 'rows;
+            }
+            rows.push(RowDifference::Changed {
+                key: key.clone(),
+                column: name.to_string(),
+                left: lv,
+                right: rv,
+            });
+        }
+    }
+
+    if !truncated {
+        let mut only_right = remaining.into_iter().collect::<Vec<_>>();
+        only_right.sort_unstable_by_key(|(_, addr)| *addr);
+        for (key, _) in only_right {
+            if rows.len() >= limit {
+                truncated = true;
+                break;
+            }
+            rows.push(RowDifference::OnlyInRight { key });
+        }
+    }
+
+    Ok(Diff {
+        schema,
+        rows,
+        truncated,
+    })
+}
+
+impl fmt::Display for SchemaDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaDifference::OnlyInLeft(name) => write!(f, "- column {:?}", name),
+            SchemaDifference::OnlyInRight(name) => write!(f, "+ column {:?}", name),
+            SchemaDifference::TypeChanged { name, left, right } => {
+                write!(f, "~ column {:?}: {:?} -> {:?}", name, left, right)
+            }
+            SchemaDifference::NullabilityChanged { name, left, right } => write!(
+                f,
+                "~ column {:?}: nullable {} -> nullable {}",
+                name, left, right
+            ),
+        }
+    }
+}
+
+impl fmt::Display for RowDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowDifference::OnlyInLeft { key } => write!(f, "- {}", key),
+            RowDifference::OnlyInRight { key } => write!(f, "+ {}", key),
+            RowDifference::Changed {
+                key,
+                column,
+                left,
+                right,
+            } => write!(f, "~ {} {:?}: {} -> {}", key, column, left, right),
+        }
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for d in &self.schema {
+            writeln!(f, "{}", d)?;
+        }
+        for d in &self.rows {
+            writeln!(f, "{}", d)?;
+        }
+        if self.truncated {
+            writeln!(f, "... (more differences not shown)")?;
+        }
+        Ok(())
+    }
+}
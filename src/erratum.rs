@@ -1,4 +1,4 @@
-use std::thread::JoinHandle;
+use std::thread::{JoinHandle, ScopedJoinHandle};
 
 pub fn join<T>(thread: JoinHandle<T>) -> T {
     match thread.join() {
@@ -6,3 +6,10 @@ pub fn join<T>(thread: JoinHandle<T>) -> T {
         Err(payload) => std::panic::resume_unwind(payload),
     }
 }
+
+pub fn join_scoped<T>(thread: ScopedJoinHandle<T>) -> T {
+    match thread.join() {
+        Ok(res) => res,
+        Err(payload) => std::panic::resume_unwind(payload),
+    }
+}
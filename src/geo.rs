@@ -0,0 +1,99 @@
+// geometries, as WKB, with the GeoParquet footer metadata which tells readers which columns
+// they are; see https://geoparquet.org/releases/v1.0.0/
+
+use std::collections::BTreeSet;
+
+use arrow2::array::{Array, BinaryArray};
+use arrow2::io::parquet::write::KeyValue;
+
+// little endian, then the type (1: point), then x and y
+pub fn point_wkb(lon: f64, lat: f64) -> [u8; 21] {
+    let mut wkb = [0u8; 21];
+    wkb[0] = 1;
+    wkb[1..5].copy_from_slice(&1u32.to_le_bytes());
+    wkb[5..13].copy_from_slice(&lon.to_le_bytes());
+    wkb[13..21].copy_from_slice(&lat.to_le_bytes());
+    wkb
+}
+
+// the GeoParquet name for the geometry in the WKB header, if it's one it knows
+pub(crate) fn geometry_type(wkb: &[u8]) -> Option<&'static str> {
+    let header: [u8; 4] = wkb.get(1..5)?.try_into().ok()?;
+    let code = match wkb[0] {
+        0 => u32::from_be_bytes(header),
+        1 => u32::from_le_bytes(header),
+        _ => return None,
+    };
+    Some(match code {
+        1 => "Point",
+        2 => "LineString",
+        3 => "Polygon",
+        4 => "MultiPoint",
+        5 => "MultiLineString",
+        6 => "MultiPolygon",
+        7 => "GeometryCollection",
+        1001 => "Point Z",
+        1002 => "LineString Z",
+        1003 => "Polygon Z",
+        1004 => "MultiPoint Z",
+        1005 => "MultiLineString Z",
+        1006 => "MultiPolygon Z",
+        1007 => "GeometryCollection Z",
+        _ => return None,
+    })
+}
+
+pub(crate) type GeometryTypes = BTreeSet<&'static str>;
+
+pub(crate) fn geometry_types(array: &dyn Array, seen: &mut GeometryTypes) {
+    let array = array
+        .as_any()
+        .downcast_ref::<BinaryArray<i32>>()
+        .expect("geometries are binary");
+    seen.extend(array.iter().flatten().filter_map(geometry_type));
+}
+
+// the first column is the primary one
+pub(crate) fn geo_key_value(columns: &[(&str, &GeometryTypes)]) -> KeyValue {
+    let primary = json_string(columns.first().map(|(name, _)| *name).unwrap_or_default());
+    let columns = columns
+        .iter()
+        .map(|(name, types)| {
+            let types = types
+                .iter()
+                .map(|t| format!("\"{}\"", t))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!(
+                "{}:{{\"encoding\":\"WKB\",\"geometry_types\":[{}]}}",
+                json_string(name),
+                types
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    KeyValue {
+        key: "geo".to_string(),
+        value: Some(format!(
+            "{{\"version\":\"1.0.0\",\"primary_column\":{},\"columns\":{{{}}}}}",
+            primary, columns
+        )),
+    }
+}
+
+// serde_json is optional
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
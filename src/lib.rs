@@ -1,16 +1,58 @@
 #![feature(try_blocks)]
 
+#[cfg(feature = "arrow")]
+pub mod arrow_rs;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod coerce;
+pub mod constraint;
+mod copy;
+pub mod date;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+mod dictionary;
+pub mod diff;
 mod erratum;
+pub mod geo;
 mod mem;
+pub mod metrics;
+mod nested;
 mod packer;
+mod pending;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod profile;
+pub mod progress;
+pub mod record;
 pub mod repack;
+pub mod report;
+pub mod rolling;
+pub mod router;
+pub mod row;
+pub mod scalar;
+pub mod schema;
+pub mod sort;
+mod split;
+mod spool;
 mod table;
 mod write;
 
 pub use crate::mem::MemUsage;
+pub use crate::nested::{ChildValue, StructRow};
+pub use crate::packer::FilePacker;
 pub use crate::packer::Packer;
+pub use crate::packer::PackerOptions;
+pub use crate::pending::PendingFile;
+pub use crate::record::PackRecord;
+pub use crate::scalar::IntoScalar;
+pub use crate::table::ColumnSet;
+pub use crate::table::DefaultValue;
 pub use crate::table::Kind;
 pub use crate::table::Table;
 pub use crate::table::TableField;
 pub use crate::table::VarArray;
+pub use crate::write::Durable;
 pub use crate::write::Writer;
+pub use crate::write::WriterOptions;
+#[cfg(feature = "derive")]
+pub use pack_it_derive::PackRecord;
@@ -0,0 +1,49 @@
+use std::fs;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+const USAGE: &str = "usage: pack-it diff <a.parquet> <b.parquet> [--key <column>] [--limit <rows>]";
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("diff") => diff(args),
+        _ => bail!(USAGE),
+    }
+}
+
+fn diff(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut files = Vec::new();
+    let mut key = None;
+    let mut limit = 100;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => key = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+            "--limit" => {
+                limit = args
+                    .next()
+                    .ok_or_else(|| anyhow!(USAGE))?
+                    .parse()
+                    .context("parsing --limit")?
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let (left, right) = match files.as_slice() {
+        [left, right] => (left, right),
+        _ => bail!(USAGE),
+    };
+
+    let open = |path: &str| fs::File::open(path).with_context(|| anyhow!("opening {:?}", path));
+
+    let diff = pack_it::diff::diff(open(left)?, open(right)?, key.as_deref(), limit)?;
+    print!("{}", diff);
+
+    if !diff.is_empty() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
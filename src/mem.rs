@@ -1,13 +1,19 @@
+use std::any::Any;
+
 use arrow2::array::{
-    MutableArray, MutableBooleanArray, MutableFixedSizeBinaryArray, MutablePrimitiveArray,
-    MutableUtf8Array,
+    Array, BinaryArray, DictionaryArray, FixedSizeBinaryArray, ListArray, MapArray, MutableArray,
+    MutableBinaryArray, MutableBooleanArray, MutableFixedSizeBinaryArray, MutablePrimitiveArray,
+    MutableUtf8Array, StructArray, Utf8Array,
 };
-use arrow2::types::Offset;
 use arrow2::bitmap::MutableBitmap;
+use arrow2::offset::{Offsets, OffsetsBuffer};
 use arrow2::types::NativeType;
+use arrow2::types::Offset;
 use std::mem;
-use arrow2::offset::Offsets;
 
+use crate::Kind;
+
+// for the builders, what's allocated, which is at least what's been pushed
 pub trait MemUsage {
     fn mem_usage(&self) -> usize;
 }
@@ -15,13 +21,14 @@ pub trait MemUsage {
 impl<T: NativeType> MemUsage for Vec<T> {
     #[inline]
     fn mem_usage(&self) -> usize {
-        self.len() * mem::size_of::<T>()
+        self.capacity() * mem::size_of::<T>()
     }
 }
 
+// capacity() is in bits, but always a whole number of bytes
 impl MemUsage for MutableBitmap {
     fn mem_usage(&self) -> usize {
-        self.len() / 8
+        self.capacity() / 8
     }
 }
 
@@ -33,7 +40,13 @@ impl MemUsage for Option<&MutableBitmap> {
 
 impl MemUsage for MutableFixedSizeBinaryArray {
     fn mem_usage(&self) -> usize {
-        self.values().mem_usage()
+        self.validity().mem_usage() + self.values().mem_usage()
+    }
+}
+
+impl<O: Offset> MemUsage for MutableBinaryArray<O> {
+    fn mem_usage(&self) -> usize {
+        self.validity().mem_usage() + self.values().mem_usage() + self.offsets().mem_usage()
     }
 }
 
@@ -43,9 +56,10 @@ impl<O: Offset> MemUsage for MutableUtf8Array<O> {
     }
 }
 
+// there's one more offset than there are elements, which capacity() doesn't count
 impl<O: Offset> MemUsage for Offsets<O> {
     fn mem_usage(&self) -> usize {
-        self.len() * size_of::<O>()
+        (self.capacity() + 1) * size_of::<O>()
     }
 }
 
@@ -60,3 +74,85 @@ impl MemUsage for MutableBooleanArray {
         self.validity().mem_usage() + self.values().mem_usage()
     }
 }
+
+impl Kind {
+    // a finished array of this kind, as submitted to the writer; its buffers can be shared, or
+    // slices, so this is what it holds, not what's allocated
+    pub(crate) fn array_mem_usage(&self, arr: &dyn Array) -> usize {
+        let validity = arr.validity().map(|v| v.len().div_ceil(8)).unwrap_or(0);
+        let values = match self {
+            Kind::Bool => arr.len().div_ceil(8),
+            Kind::Null => 0,
+            Kind::U8 => primitive::<u8>(arr),
+            Kind::U16 => primitive::<u16>(arr),
+            Kind::U32 => primitive::<u32>(arr),
+            Kind::U64 => primitive::<u64>(arr),
+            Kind::I8 => primitive::<i8>(arr),
+            Kind::I16 => primitive::<i16>(arr),
+            Kind::I32 | Kind::Date | Kind::TimeMillis => primitive::<i32>(arr),
+            Kind::I64
+            | Kind::TimestampSecsZ
+            | Kind::Timestamp { .. }
+            | Kind::DateMillis
+            | Kind::TimeMicros
+            | Kind::Duration(_) => primitive::<i64>(arr),
+            Kind::Decimal { .. } => primitive::<i128>(arr),
+            Kind::F32 => primitive::<f32>(arr),
+            Kind::F64 => primitive::<f64>(arr),
+            Kind::String | Kind::Json => {
+                let arr = finished::<Utf8Array<i32>>(arr);
+                arr.values().len() + offsets(arr.offsets())
+            }
+            Kind::LargeString => {
+                let arr = finished::<Utf8Array<i64>>(arr);
+                arr.values().len() + offsets(arr.offsets())
+            }
+            Kind::Enum(_) => {
+                let arr = finished::<DictionaryArray<u32>>(arr);
+                primitive::<u32>(arr.keys()) + Kind::String.array_mem_usage(arr.values().as_ref())
+            }
+            Kind::Uuid | Kind::Ipv4 | Kind::Ipv6 | Kind::FixedBytes(_) => {
+                finished::<FixedSizeBinaryArray>(arr).values().len()
+            }
+            Kind::Geometry | Kind::Binary => {
+                let arr = finished::<BinaryArray<i32>>(arr);
+                arr.values().len() + offsets(arr.offsets())
+            }
+            Kind::List(inner) => {
+                let arr = finished::<ListArray<i32>>(arr);
+                offsets(arr.offsets()) + inner.array_mem_usage(arr.values().as_ref())
+            }
+            Kind::Struct(fields) => struct_mem_usage(arr, fields.iter().map(|child| &child.kind)),
+            Kind::Map(key, value) => {
+                let arr = finished::<MapArray>(arr);
+                offsets(arr.offsets())
+                    + struct_mem_usage(arr.field().as_ref(), [key.as_ref(), value.as_ref()])
+            }
+        };
+        validity + values
+    }
+}
+
+// the array the writer was given, which it's checked is of the column's arrow type
+fn finished<T: Any>(arr: &dyn Array) -> &T {
+    arr.as_any()
+        .downcast_ref()
+        .expect("batches are checked against the schema")
+}
+
+fn primitive<T: NativeType>(arr: &dyn Array) -> usize {
+    arr.len() * size_of::<T>()
+}
+
+fn offsets<O: Offset>(offsets: &OffsetsBuffer<O>) -> usize {
+    offsets.len() * size_of::<O>()
+}
+
+fn struct_mem_usage<'k>(arr: &dyn Array, kinds: impl IntoIterator<Item = &'k Kind>) -> usize {
+    finished::<StructArray>(arr)
+        .values()
+        .iter()
+        .zip(kinds)
+        .map(|(values, kind)| kind.array_mem_usage(values.as_ref()))
+        .sum()
+}
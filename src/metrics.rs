@@ -0,0 +1,46 @@
+// callbacks for wiring into e.g. prometheus; everything defaults to doing nothing
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    // handed to the writer, so not counting what's still buffered in the table
+    RowsFlushed,
+    BatchesFlushed,
+    BytesWritten,
+    // time spent blocked handing batches to the writer threads
+    ChannelWaitMicros,
+    // time the writer threads spent encoding (and writing) batches
+    EncodeMicros,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Gauge {
+    BufferedRows,
+    BufferedBytes,
+}
+
+impl Counter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Counter::RowsFlushed => "pack_it_rows_flushed_total",
+            Counter::BatchesFlushed => "pack_it_batches_flushed_total",
+            Counter::BytesWritten => "pack_it_bytes_written_total",
+            Counter::ChannelWaitMicros => "pack_it_channel_wait_micros_total",
+            Counter::EncodeMicros => "pack_it_encode_micros_total",
+        }
+    }
+}
+
+impl Gauge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Gauge::BufferedRows => "pack_it_buffered_rows",
+            Gauge::BufferedBytes => "pack_it_buffered_bytes",
+        }
+    }
+}
+
+// called from the writer threads, as well as the caller's
+pub trait Metrics: Send + Sync {
+    fn counter(&self, _counter: Counter, _increment: u64) {}
+    fn gauge(&self, _gauge: Gauge, _value: u64) {}
+}
@@ -0,0 +1,321 @@
+// the children of the nested kinds: the values Table::push_list can put in a list, the rows
+// of structs, and the entries of maps; the child builders are decided by the inner kinds
+
+use std::any::Any;
+use std::fmt;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use arrow2::array::{
+    Array, ListArray, MapArray, MutableArray, MutableBinaryArray, MutableBooleanArray,
+    MutableListArray, MutablePrimitiveArray, MutableStructArray, MutableUtf8Array, TryPush,
+};
+use arrow2::bitmap::MutableBitmap;
+use arrow2::datatypes::{DataType, Field};
+
+use crate::{Kind, TableField};
+
+pub trait ChildValue {
+    // whether the child builder takes these, checked before anything is pushed
+    fn fits(values: &dyn MutableArray) -> bool;
+
+    // only called if it fits
+    fn push_to(self, values: &mut dyn MutableArray) -> Result<()>;
+}
+
+macro_rules! primitive {
+    ($($t:ty),*) => {
+        $(
+            impl ChildValue for $t {
+                fn fits(values: &dyn MutableArray) -> bool {
+                    values.as_any().is::<MutablePrimitiveArray<$t>>()
+                }
+
+                fn push_to(self, values: &mut dyn MutableArray) -> Result<()> {
+                    values
+                        .as_mut_any()
+                        .downcast_mut::<MutablePrimitiveArray<$t>>()
+                        .expect("fits")
+                        .push(Some(self));
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+primitive!(u8, u16, u32, u64, i8, i16, i32, i64, i128, f32, f64);
+
+impl ChildValue for bool {
+    fn fits(values: &dyn MutableArray) -> bool {
+        values.as_any().is::<MutableBooleanArray>()
+    }
+
+    fn push_to(self, values: &mut dyn MutableArray) -> Result<()> {
+        values
+            .as_mut_any()
+            .downcast_mut::<MutableBooleanArray>()
+            .expect("fits")
+            .push(Some(self));
+        Ok(())
+    }
+}
+
+// for string and large_string lists
+impl ChildValue for &str {
+    fn fits(values: &dyn MutableArray) -> bool {
+        values.as_any().is::<MutableUtf8Array<i32>>()
+            || values.as_any().is::<MutableUtf8Array<i64>>()
+    }
+
+    fn push_to(self, values: &mut dyn MutableArray) -> Result<()> {
+        let any = values.as_mut_any();
+        if let Some(values) = any.downcast_mut::<MutableUtf8Array<i32>>() {
+            values.try_push(Some(self))?;
+            return Ok(());
+        }
+        any.downcast_mut::<MutableUtf8Array<i64>>()
+            .expect("fits")
+            .try_push(Some(self))?;
+        Ok(())
+    }
+}
+
+// for binary lists; geometry isn't checked here
+impl ChildValue for &[u8] {
+    fn fits(values: &dyn MutableArray) -> bool {
+        values.as_any().is::<MutableBinaryArray<i32>>()
+    }
+
+    fn push_to(self, values: &mut dyn MutableArray) -> Result<()> {
+        values
+            .as_mut_any()
+            .downcast_mut::<MutableBinaryArray<i32>>()
+            .expect("fits")
+            .try_push(Some(self))?;
+        Ok(())
+    }
+}
+
+// a row of a struct column, as passed to the closure given to Table::push_struct
+pub struct StructRow<'t> {
+    arr: &'t mut MutableStructArray,
+    fields: &'t [TableField],
+    set: Vec<bool>,
+}
+
+impl<'t> StructRow<'t> {
+    pub(crate) fn new(arr: &'t mut MutableStructArray, fields: &'t [TableField]) -> Self {
+        StructRow {
+            arr,
+            fields,
+            set: vec![false; fields.len()],
+        }
+    }
+
+    pub fn set<V: ChildValue>(&mut self, child: usize, val: Option<V>) -> Result<&mut Self> {
+        let TableField {
+            name,
+            kind,
+            nullable,
+            ..
+        } = self
+            .fields
+            .get(child)
+            .ok_or_else(|| anyhow!("there's no child {}", child))?;
+        ensure!(!self.set[child], "child {:?} is already set", name);
+        ensure!(
+            val.is_some() || *nullable,
+            "child {:?} isn't nullable",
+            name
+        );
+        let values = self.arr.mut_values()[child].as_mut();
+        match val {
+            Some(val) => {
+                if !V::fits(values) {
+                    bail!(
+                        "child {:?} is a {}, which can't hold {}s",
+                        name,
+                        kind,
+                        std::any::type_name::<V>()
+                    );
+                }
+                val.push_to(values)?
+            }
+            None => values.push_null(),
+        }
+        self.set[child] = true;
+        Ok(self)
+    }
+
+    pub fn child(&self, name: &str) -> Result<usize> {
+        self.fields
+            .iter()
+            .position(|child| child.name == name)
+            .ok_or_else(|| anyhow!("there's no child {:?}", name))
+    }
+
+    // every child that isn't nullable has to be set
+    pub(crate) fn check_set(&self) -> Result<()> {
+        for (child, set) in self.fields.iter().zip(&self.set) {
+            ensure!(
+                *set || child.nullable,
+                "child {:?} isn't nullable, but wasn't set",
+                child.name
+            );
+        }
+        Ok(())
+    }
+
+    // the unset children are null; an invalid row is a null, which hides them
+    pub(crate) fn finish(self, valid: bool) {
+        let values = self.arr.mut_values();
+        for (values, set) in values.iter_mut().zip(self.set) {
+            if !set {
+                values.push_null();
+            }
+        }
+        self.arr.push(valid);
+    }
+}
+
+// arrow2 has no mutable map, but a map is a list of key-value structs, which it only has to
+// be relabelled as when it's finished
+#[derive(Debug)]
+pub(crate) struct MutableMapArray {
+    data_type: DataType,
+    entries: MutableListArray<i32, Box<dyn MutableArray>>,
+}
+
+impl MutableMapArray {
+    pub(crate) fn new(data_type: DataType, key: &Kind, value: &Kind, capacity: usize) -> Self {
+        let entries = match &data_type {
+            DataType::Map(entries, _) => entries.as_ref().clone(),
+            other => unreachable!("maps are maps, not {:?}", other),
+        };
+        let fields = MutableStructArray::new(
+            entries.data_type.clone(),
+            vec![
+                key.builder_with_capacity(capacity),
+                value.builder_with_capacity(capacity),
+            ],
+        );
+        MutableMapArray {
+            entries: MutableListArray::new_from(
+                Box::new(fields),
+                DataType::List(Box::new(entries)),
+                capacity,
+            ),
+            data_type,
+        }
+    }
+
+    // e.g. to copy another map's entries in
+    pub(crate) fn entries_mut(&mut self) -> &mut MutableListArray<i32, Box<dyn MutableArray>> {
+        &mut self.entries
+    }
+
+    // as made by new, e.g. a copy of another map's entries
+    pub(crate) fn from_entries(
+        data_type: DataType,
+        entries: MutableListArray<i32, Box<dyn MutableArray>>,
+    ) -> Self {
+        MutableMapArray { data_type, entries }
+    }
+
+    pub(crate) fn entries(&self) -> &MutableListArray<i32, Box<dyn MutableArray>> {
+        &self.entries
+    }
+
+    // nothing's pushed unless the keys and values fit; the keys can't be null
+    pub(crate) fn try_push<K: ChildValue, V: ChildValue>(
+        &mut self,
+        column: &dyn fmt::Display,
+        entries: impl IntoIterator<Item = (K, Option<V>)>,
+    ) -> Result<()> {
+        let fields = self
+            .entries
+            .mut_values()
+            .as_mut_any()
+            .downcast_mut::<MutableStructArray>()
+            .expect("entries are structs");
+        ensure!(
+            K::fits(fields.values()[0].as_ref()) && V::fits(fields.values()[1].as_ref()),
+            "{} can't hold {}s to {}s",
+            column,
+            std::any::type_name::<K>(),
+            std::any::type_name::<V>()
+        );
+        for (key, value) in entries {
+            let values = fields.mut_values();
+            key.push_to(values[0].as_mut())?;
+            match value {
+                Some(value) => value.push_to(values[1].as_mut())?,
+                None => values[1].push_null(),
+            }
+            fields.push(true);
+        }
+        self.entries.try_push_valid()?;
+        Ok(())
+    }
+}
+
+impl MutableArray for MutableMapArray {
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn validity(&self) -> Option<&MutableBitmap> {
+        self.entries.validity()
+    }
+
+    fn as_box(&mut self) -> Box<dyn Array> {
+        let list = self.entries.as_box();
+        let list = list
+            .as_any()
+            .downcast_ref::<ListArray<i32>>()
+            .expect("entries are lists");
+        MapArray::new(
+            self.data_type.clone(),
+            list.offsets().clone(),
+            list.values().clone(),
+            list.validity().cloned(),
+        )
+        .boxed()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn push_null(&mut self) {
+        self.entries.push_null()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.entries.reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.entries.shrink_to_fit()
+    }
+}
+
+// as arrow2 (and parquet) want them: required keys, nullable values
+pub(crate) fn map_type(key: &Kind, value: &Kind) -> DataType {
+    let fields = vec![
+        Field::new("key", key.to_arrow(), false),
+        Field::new("value", value.to_arrow(), true),
+    ];
+    DataType::Map(
+        Box::new(Field::new("entries", DataType::Struct(fields), false)),
+        false,
+    )
+}
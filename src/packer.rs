@@ -1,20 +1,80 @@
 use std::io::Write;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use arrow2::array::Array;
+use arrow2::chunk::Chunk;
+use crossbeam_channel::Sender;
 use log::{debug, info};
+use tracing::info_span;
 
-use crate::{Table, TableField, Writer};
+use crate::coerce::Coercion;
+use crate::metrics::{Counter, Gauge, Metrics};
+use crate::progress::{emit, Progress};
+use crate::report::ColumnReport;
+use crate::schema::NameMatching;
+use crate::{Durable, PendingFile, Table, TableField, Writer, WriterOptions};
 
-pub struct Packer<W> {
+#[derive(Clone, Default)]
+pub struct PackerOptions {
+    pub writer: WriterOptions,
+    pub coercion: Coercion,
+    // consider_flushing flushes once the buffered rows' builders have allocated this much memory,
+    // as Table::mem_estimate, which it only works out every so often; defaults to 512MB
+    pub max_buffer_bytes: Option<usize>,
+    // ..or once there are this many rows, however small they are
+    pub max_rows_per_group: Option<usize>,
+    // ..but not for the memory until there are this many, so wide rows don't make tiny row groups
+    pub min_rows_per_group: Option<usize>,
+    // ..or once the buffered rows are this old, for slow streams; see flush_if_stale. The table
+    // doesn't timestamp pushes, so their age is from when consider_flushing or flush_if_stale
+    // first noticed rows buffered, not from the first push
+    pub max_buffer_age: Option<Duration>,
+}
+
+const DEFAULT_MAX_BUFFER_BYTES: usize = 512 * 1024 * 1024;
+
+pub struct Packer<W: Write> {
     writer: Writer<W>,
     table: Table,
+    metrics: Option<Arc<dyn Metrics>>,
+    progress: Option<Sender<Progress>>,
+    // for rolling to another writer
+    options: PackerOptions,
+    // when consider_flushing or flush_if_stale first saw rows buffered, since the last flush
+    buffered_since: Option<Instant>,
+    flushed_rows: usize,
 }
 
 impl<W: Write + Send + 'static> Packer<W> {
     pub fn new(inner: W, schema: &[TableField]) -> Result<Self> {
+        Self::with_options(inner, schema, PackerOptions::default())
+    }
+
+    pub fn with_options(inner: W, schema: &[TableField], options: PackerOptions) -> Result<Self> {
+        if let (Some(min), Some(max)) = (options.min_rows_per_group, options.max_rows_per_group) {
+            ensure!(
+                min <= max,
+                "min_rows_per_group ({}) is more than max_rows_per_group ({})",
+                min,
+                max
+            );
+        }
+        let mut table = Table::from_fields(schema, 0);
+        table.set_coercion(options.coercion);
+        table.set_name_matching(options.writer.name_matching);
+
         Ok(Self {
-            writer: Writer::new(vec![inner], schema)?,
-            table: Table::with_capacity(&schema.iter().map(|f| f.kind).collect::<Vec<_>>(), 0),
+            metrics: options.writer.metrics.clone(),
+            progress: options.writer.progress.clone(),
+            writer: Writer::with_options(vec![inner], schema, options.writer.clone())?,
+            table,
+            options,
+            buffered_since: None,
+            flushed_rows: 0,
         })
     }
 
@@ -22,42 +82,105 @@ impl<W: Write + Send + 'static> Packer<W> {
         &mut self.table
     }
 
+    pub fn schema(&self) -> &[TableField] {
+        self.writer.schema()
+    }
+
+    pub fn name_matching(&self) -> NameMatching {
+        self.writer.name_matching()
+    }
+
     pub fn find_field(&self, name: &str) -> Option<(usize, &TableField)> {
         self.writer.find_field(name)
     }
 
+    // handed to the current writer, so not counting what's buffered in the table
+    pub fn flushed_rows(&self) -> usize {
+        self.flushed_rows
+    }
+
+    // see Writer::bytes_written
+    pub fn bytes_written(&self) -> u64 {
+        self.writer.bytes_written()
+    }
+
     pub fn consider_flushing(&mut self) -> Result<()> {
         self.table.check_consistent()?;
 
-        if self.table.mem_estimate() > 512 * 1024 * 1024 {
+        let rows = self.table.rows();
+        let mem_estimate = self.table.recent_mem_estimate();
+        let options = &self.options;
+        let too_big = mem_estimate > options.max_buffer_bytes.unwrap_or(DEFAULT_MAX_BUFFER_BYTES)
+            && rows >= options.min_rows_per_group.unwrap_or(0);
+        let too_long = options.max_rows_per_group.is_some_and(|max| rows >= max);
+        if too_big || too_long || self.is_stale() {
             self.flush()?;
-        } else if self.table.rows() % (64 * 1024) == 0 {
-            let before = self.table.mem_estimate();
-            self.table.finish_bulk_push()?;
-            let mem_estimate = self.table.mem_estimate();
-            let rows = self.table.rows().max(1);
+        } else if rows.is_multiple_of(64 * 1024) {
+            let rows = rows.max(1);
             debug!(
-                "didn't flush ({} rows, ~{}MB (est: ~{}MB), ~{}bytes/row)",
+                "didn't flush ({} rows, ~{}MB, ~{}bytes/row)",
                 rows,
                 mem_estimate / 1024 / 1024,
-                before / 1024 / 1024,
                 mem_estimate / rows
             );
         }
 
+        if let Some(metrics) = &self.metrics {
+            metrics.gauge(Gauge::BufferedRows, self.table.rows() as u64);
+            // the flush emptied it
+            let mem_estimate = if self.table.rows() == 0 {
+                0
+            } else {
+                mem_estimate
+            };
+            metrics.gauge(Gauge::BufferedBytes, mem_estimate as u64);
+        }
+
         Ok(())
     }
 
+    // the buffered rows' age is from when this first noticed them
+    fn is_stale(&mut self) -> bool {
+        if self.table.rows() > 0 && self.buffered_since.is_none() {
+            self.buffered_since = Some(Instant::now());
+        }
+        match (self.options.max_buffer_age, self.buffered_since) {
+            (Some(max), Some(since)) => since.elapsed() >= max,
+            _ => false,
+        }
+    }
+
+    // for a timer, between rows: flushes if the buffered rows are older than max_buffer_age, as
+    // consider_flushing would, if only another row had come along
+    pub fn flush_if_stale(&mut self) -> Result<()> {
+        if self.is_stale() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn record_flush(&mut self, rows: usize) {
+        self.flushed_rows += rows;
+        emit(&self.progress, Progress::BatchFlushed { rows });
+        if let Some(metrics) = &self.metrics {
+            metrics.counter(Counter::RowsFlushed, rows as u64);
+            metrics.counter(Counter::BatchesFlushed, 1);
+            metrics.gauge(Gauge::BufferedRows, 0);
+            metrics.gauge(Gauge::BufferedBytes, 0);
+        }
+    }
+
     pub fn flush(&mut self) -> Result<()> {
         let rows = self.table.rows();
         if 0 == rows {
+            self.buffered_since = None;
             return Ok(());
         }
 
-        // update the memory estimate, and check consistent
-        self.table.finish_bulk_push()?;
+        self.table.check_consistent()?;
         let mem_estimate = self.table.mem_estimate();
 
+        let _span = info_span!("flush", rows, bytes = mem_estimate).entered();
         info!(
             "submitting row group ({} rows, ~{}MB, ~{}bytes/row)",
             rows,
@@ -66,14 +189,144 @@ impl<W: Write + Send + 'static> Packer<W> {
         );
 
         let batch = self.table.take_batch();
+        self.buffered_since = None;
 
         self.writer.submit_batch(batch)?;
+        self.record_flush(rows);
 
         Ok(())
     }
 
+    // already built columns, written as a row group of their own, after anything buffered in the
+    // table; they have to match the schema exactly, as Writer::submit_batch checks, and are kept
+    // for take_failed_batch if they don't
+    pub fn submit_chunk(&mut self, chunk: Chunk<Arc<dyn Array>>) -> Result<()> {
+        self.flush()?;
+        let rows = chunk.len();
+        self.writer.submit_batch(chunk.into_arrays())?;
+        self.record_flush(rows);
+        Ok(())
+    }
+
+    // the rows from the last failed flush, if any; see Writer::take_failed_batch
+    pub fn take_failed_batch(&mut self) -> Option<Vec<Arc<dyn Array>>> {
+        self.writer.take_failed_batch()
+    }
+
+    // a parquet file can't change schema, so this finishes the current file, returning it, and
+    // carries on in `next`; call it between rows
+    pub fn add_column(&mut self, field: TableField, next: W) -> Result<W> {
+        self.flush()?;
+
+        let mut schema = self.schema().to_vec();
+        schema.push(field);
+
+        let writer = Writer::with_options(vec![next], &schema, self.options.writer.clone())?;
+        let previous = std::mem::replace(&mut self.writer, writer);
+        self.flushed_rows = 0;
+        let mut table = Table::from_fields(&schema, 0);
+        table.set_coercion(self.options.coercion);
+        table.set_name_matching(self.options.writer.name_matching);
+        table.copy_constants(&self.table);
+        self.table = table;
+
+        Ok(previous.finish()?.pop().expect("exactly one"))
+    }
+
     pub fn finish(mut self) -> Result<W> {
         self.flush()?;
         Ok(self.writer.finish()?.pop().expect("exactly one"))
     }
+
+    // see Writer::finish_durably
+    pub fn finish_durably(mut self) -> Result<W>
+    where
+        W: Durable,
+    {
+        self.flush()?;
+        Ok(self.writer.finish_durably()?.pop().expect("exactly one"))
+    }
+
+    // see Writer::finish_with_report
+    pub fn finish_with_report(mut self) -> Result<(W, Vec<ColumnReport>)> {
+        self.flush()?;
+        Ok(self
+            .writer
+            .finish_with_report()?
+            .pop()
+            .expect("exactly one"))
+    }
+
+    // see WriterOptions::sha256
+    pub fn finish_with_sha256(mut self) -> Result<(W, [u8; 32])> {
+        self.flush()?;
+        Ok(self
+            .writer
+            .finish_with_sha256()?
+            .pop()
+            .expect("exactly one"))
+    }
+}
+
+impl Packer<PendingFile> {
+    // written next to `path`, and only renamed to it once it's finished; see FilePacker
+    pub fn create(path: impl AsRef<Path>, schema: &[TableField]) -> Result<FilePacker> {
+        Self::create_with_options(path, schema, PackerOptions::default())
+    }
+
+    pub fn create_with_options(
+        path: impl AsRef<Path>,
+        schema: &[TableField],
+        options: PackerOptions,
+    ) -> Result<FilePacker> {
+        Ok(FilePacker {
+            inner: Self::with_options(PendingFile::create(path)?, schema, options)?,
+        })
+    }
+}
+
+// a Packer for a PendingFile, as Packer::create makes, whose finish methods rename the file into
+// place, and return where that is. If it's dropped unfinished, the temporary file's deleted, once
+// the writer's threads let go of it
+pub struct FilePacker {
+    inner: Packer<PendingFile>,
+}
+
+impl Deref for FilePacker {
+    type Target = Packer<PendingFile>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for FilePacker {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl FilePacker {
+    // Packer::finish, then PendingFile::persist; the temporary file's deleted if either fails
+    pub fn finish(self) -> Result<PathBuf> {
+        self.inner.finish()?.persist()
+    }
+
+    // Packer::finish_with_report, then PendingFile::persist
+    pub fn finish_with_report(self) -> Result<(PathBuf, Vec<ColumnReport>)> {
+        let (file, report) = self.inner.finish_with_report()?;
+        Ok((file.persist()?, report))
+    }
+
+    // Packer::finish_with_sha256, then PendingFile::persist
+    pub fn finish_with_sha256(self) -> Result<(PathBuf, [u8; 32])> {
+        let (file, sha256) = self.inner.finish_with_sha256()?;
+        Ok((file.persist()?, sha256))
+    }
+
+    // the finished file, not yet renamed into place, e.g. to only persist it once something else
+    // has worked; it's deleted if it's dropped without being persisted
+    pub fn finish_pending(self) -> Result<PendingFile> {
+        self.inner.finish()
+    }
 }
@@ -0,0 +1,107 @@
+// a file which only appears at its path once it's finished, so anything watching the directory
+// never sees half of one; until then, it's written next to it, with ".tmp" on the end
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::Durable;
+
+#[must_use = "the file's deleted when this is dropped, unless it's persisted"]
+pub struct PendingFile {
+    // only None once it's persisted
+    inner: Option<BufWriter<File>>,
+    tmp: PathBuf,
+    path: PathBuf,
+}
+
+impl PendingFile {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut tmp = OsString::from(path.as_os_str());
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let file = File::create(&tmp).with_context(|| anyhow!("creating {:?}", tmp))?;
+        Ok(PendingFile {
+            inner: Some(BufWriter::new(file)),
+            tmp,
+            path,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // flushes, fsyncs, and renames it into place, then fsyncs the directory, so the rename
+    // survives a crash too, returning where it is; if anything before the rename fails, the
+    // temporary file is removed
+    pub fn persist(mut self) -> Result<PathBuf> {
+        let inner = self.inner.as_mut().expect("only persisted once");
+        inner
+            .flush()
+            .with_context(|| anyhow!("flushing {:?}", self.tmp))?;
+        inner
+            .get_ref()
+            .sync_all()
+            .with_context(|| anyhow!("syncing {:?}", self.tmp))?;
+        // closed before it's renamed, which windows insists on; so Drop won't clean up after this
+        drop(self.inner.take());
+        if let Err(e) = fs::rename(&self.tmp, &self.path) {
+            let _ = fs::remove_file(&self.tmp);
+            return Err(e).with_context(|| anyhow!("renaming {:?} to {:?}", self.tmp, self.path));
+        }
+        sync_dir(&self.path)?;
+        Ok(self.path.clone())
+    }
+
+    fn inner(&mut self) -> &mut BufWriter<File> {
+        self.inner.as_mut().expect("not persisted")
+    }
+}
+
+// a rename is only durable once the directory holding it is synced; windows can't open a
+// directory as a file, and doesn't need to
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| anyhow!("syncing the directory {:?}", dir))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+impl Write for PendingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner().flush()
+    }
+}
+
+// e.g. the write failed, or it was never persisted; there's nobody to tell if this fails
+impl Drop for PendingFile {
+    fn drop(&mut self) {
+        if self.inner.take().is_some() {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+impl Durable for PendingFile {
+    fn sync(&mut self) -> io::Result<()> {
+        self.inner().sync()
+    }
+}
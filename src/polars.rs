@@ -0,0 +1,97 @@
+// like arrow_rs, this goes through the C data interface, as polars has its own fork of arrow2
+
+use std::io::{Read, Seek, Write};
+use std::mem::transmute;
+
+use anyhow::{anyhow, Context, Result};
+use arrow2::array::{Array, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field as ArrowField};
+use arrow2::ffi;
+use arrow2::io::parquet::read;
+use polars_arrow::ffi as pl_ffi;
+use polars_core::frame::column::Column;
+use polars_core::prelude::{CompatLevel, DataFrame, Series};
+
+use crate::{Kind, Packer};
+
+fn from_polars(column: &Column, kind: &Kind) -> Result<Box<dyn Array>> {
+    let field = column.field().to_arrow(CompatLevel::oldest());
+    let array = column.clone().rechunk_to_arrow(CompatLevel::oldest());
+
+    let array = pl_ffi::export_array_to_c(array);
+    let schema = pl_ffi::export_field_to_c(&field);
+    let array = unsafe { transmute::<pl_ffi::ArrowArray, ffi::ArrowArray>(array) };
+    let schema = unsafe { transmute::<pl_ffi::ArrowSchema, ffi::ArrowSchema>(schema) };
+    let field = unsafe { ffi::import_field_from_c(&schema) }?;
+    let array = unsafe { ffi::import_array_from_c(array, field.data_type) }?;
+
+    // polars hands out large strings, even at the oldest compat level; they're kept for a
+    // LargeString column
+    Ok(match array.data_type() {
+        DataType::LargeUtf8 if *kind != Kind::LargeString => {
+            let large = array
+                .as_any()
+                .downcast_ref::<Utf8Array<i64>>()
+                .expect("LargeUtf8 is a Utf8Array<i64>");
+            let offsets = large
+                .offsets()
+                .try_into()
+                .with_context(|| anyhow!("column {:?} has over 2GB of strings", column.name()))?;
+            Box::new(Utf8Array::<i32>::try_new(
+                DataType::Utf8,
+                offsets,
+                large.values().clone(),
+                large.validity().cloned(),
+            )?)
+        }
+        _ => array,
+    })
+}
+
+fn to_polars(array: Box<dyn Array>, field: &ArrowField) -> Result<Column> {
+    let array = ffi::export_array_to_c(array);
+    let schema = ffi::export_field_to_c(field);
+    let array = unsafe { transmute::<ffi::ArrowArray, pl_ffi::ArrowArray>(array) };
+    let schema = unsafe { transmute::<ffi::ArrowSchema, pl_ffi::ArrowSchema>(schema) };
+    let pl_field = unsafe { pl_ffi::import_field_from_c(&schema) }?;
+    let array = unsafe { pl_ffi::import_array_from_c(array, pl_field.dtype) }?;
+    Ok(Series::from_arrow(field.name.as_str().into(), array)?.into())
+}
+
+impl<W: Write + Send + 'static> Packer<W> {
+    // columns are matched up by name, and the whole frame becomes one row group
+    pub fn submit_dataframe(&mut self, df: &DataFrame) -> Result<()> {
+        let matching = self.name_matching();
+        let arrays = self
+            .schema()
+            .iter()
+            .map(|field| -> Result<_> {
+                let column = df
+                    .get_columns()
+                    .iter()
+                    .find(|c| matching.matches(c.name(), &field.name))
+                    .ok_or_else(|| anyhow!("dataframe is missing column {:?}", field.name))?;
+                Ok(from_polars(column, &field.kind)?.into())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.submit_chunk(Chunk::try_new(arrays)?)
+    }
+}
+
+pub fn read_dataframes(mut f: impl Read + Seek) -> Result<impl Iterator<Item = Result<DataFrame>>> {
+    let metadata = read::read_metadata(&mut f)?;
+    let schema = read::infer_schema(&metadata)?;
+    let fields = schema.fields.clone();
+    let reader = read::FileReader::new(f, metadata.row_groups, schema, None, None, None);
+
+    Ok(reader.map(move |chunk| -> Result<DataFrame> {
+        let columns = chunk?
+            .into_arrays()
+            .into_iter()
+            .zip(&fields)
+            .map(|(array, field)| to_polars(array, field))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DataFrame::new(columns)?)
+    }))
+}
@@ -0,0 +1,148 @@
+// presets restricting what we write to what each reader reliably handles; see the TODOs on
+// Kind::default_encoding for how we found out
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use arrow2::array::{Array, PrimitiveArray};
+use arrow2::datatypes::{DataType, TimeUnit};
+use arrow2::io::parquet::write::{can_encode, Encoding, Version};
+
+use crate::{Kind, TableField};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WriteProfile {
+    // parquet-mr: v1 pages, plain, and real (millisecond) timestamps
+    SparkSafe,
+    // presto/trino, as SparkSafe, but only with names Athena's DDL takes unquoted: lowercase
+    // letters, digits and underscores, starting with a letter, and at most 255 long
+    AthenaSafe,
+    // arrow-rs: no DELTA_LENGTH_BYTE_ARRAY
+    DataFusionSafe,
+    // v2 pages, and the delta encodings wherever arrow2 can write them
+    Maximal,
+}
+
+impl WriteProfile {
+    pub(crate) fn version(self) -> Version {
+        match self {
+            WriteProfile::SparkSafe | WriteProfile::AthenaSafe => Version::V1,
+            WriteProfile::DataFusionSafe | WriteProfile::Maximal => Version::V2,
+        }
+    }
+
+    pub(crate) fn encoding(self, data_type: &DataType, requested: Encoding) -> Encoding {
+        // arrow2 can't write them any other way, and everyone reads it, as parquet-mr's default
+        if let DataType::Dictionary(..) = data_type {
+            return Encoding::RleDictionary;
+        }
+        match self {
+            WriteProfile::SparkSafe | WriteProfile::AthenaSafe => Encoding::Plain,
+            WriteProfile::DataFusionSafe => match requested {
+                Encoding::DeltaLengthByteArray => Encoding::Plain,
+                other => other,
+            },
+            WriteProfile::Maximal if requested == Encoding::Plain => {
+                [Encoding::DeltaBinaryPacked, Encoding::DeltaLengthByteArray]
+                    .into_iter()
+                    .find(|&encoding| can_encode(data_type, encoding))
+                    .unwrap_or(Encoding::Plain)
+            }
+            WriteProfile::Maximal => requested,
+        }
+    }
+
+    pub(crate) fn check_names(self, schema: &[TableField]) -> Result<()> {
+        if self != WriteProfile::AthenaSafe {
+            return Ok(());
+        }
+        for field in schema {
+            athena_name(&field.name)?;
+            athena_children(&field.kind).with_context(|| anyhow!("in column {:?}", field.name))?;
+        }
+        Ok(())
+    }
+
+    // parquet has no seconds timestamp, so arrow2 writes them as bare integers
+    pub(crate) fn written_type(self, kind: &Kind) -> DataType {
+        match (self, kind) {
+            (WriteProfile::SparkSafe | WriteProfile::AthenaSafe, Kind::TimestampSecsZ) => {
+                DataType::Timestamp(TimeUnit::Millisecond, Some("+00:00".to_string()))
+            }
+            (
+                WriteProfile::SparkSafe | WriteProfile::AthenaSafe,
+                Kind::Timestamp {
+                    unit: TimeUnit::Second,
+                    tz,
+                },
+            ) => DataType::Timestamp(TimeUnit::Millisecond, tz.clone()),
+            (_, kind) => kind.to_arrow(),
+        }
+    }
+}
+
+fn athena_children(kind: &Kind) -> Result<()> {
+    match kind {
+        Kind::List(item) => athena_children(item),
+        Kind::Map(key, value) => {
+            athena_children(key)?;
+            athena_children(value)
+        }
+        Kind::Struct(fields) => {
+            for child in fields {
+                athena_name(&child.name)?;
+                athena_children(&child.kind)
+                    .with_context(|| anyhow!("in field {:?}", child.name))?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn athena_name(name: &str) -> Result<()> {
+    ensure!(
+        name.len() <= 255,
+        "{:?} is longer than the 255 bytes athena allows",
+        name
+    );
+    ensure!(
+        name.starts_with(|c: char| c.is_ascii_lowercase()),
+        "athena needs {:?} to start with a lowercase letter",
+        name
+    );
+    ensure!(
+        name.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+        "athena needs {:?} to be only lowercase letters, digits and underscores",
+        name
+    );
+    Ok(())
+}
+
+// bring an array from the kind's type to the written_type
+pub(crate) fn convert(array: Arc<dyn Array>, to: &DataType) -> Result<Arc<dyn Array>> {
+    match (array.data_type(), to) {
+        // the table builds timestamps as plain i64s
+        (
+            DataType::Int64 | DataType::Timestamp(TimeUnit::Second, _),
+            DataType::Timestamp(TimeUnit::Millisecond, _),
+        ) => {
+            let secs = array
+                .as_any()
+                .downcast_ref::<PrimitiveArray<i64>>()
+                .expect("timestamps are i64s");
+            let millis = secs
+                .values()
+                .iter()
+                .map(|v| v.saturating_mul(1000))
+                .collect::<Vec<_>>();
+            Ok(Arc::new(PrimitiveArray::new(
+                to.clone(),
+                millis.into(),
+                secs.validity().cloned(),
+            )))
+        }
+        (from, to) => bail!("can't write {:?} as {:?}", from, to),
+    }
+}
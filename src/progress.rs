@@ -0,0 +1,27 @@
+// events for anyone watching a long job; see WriterOptions::progress
+use crossbeam_channel::Sender;
+
+#[derive(Clone, Debug)]
+pub enum Progress {
+    // the packer has handed a batch of rows to the writer
+    BatchFlushed {
+        rows: usize,
+    },
+    // a sink has encoded and written a row group
+    RowGroupWritten {
+        sink: usize,
+        rows: usize,
+        bytes: u64,
+    },
+    Finished,
+    Failed {
+        error: String,
+    },
+}
+
+// nobody listening isn't our problem
+pub(crate) fn emit(progress: &Option<Sender<Progress>>, event: Progress) {
+    if let Some(tx) = progress {
+        let _ = tx.send(event);
+    }
+}
@@ -0,0 +1,100 @@
+// structs as rows; see #[derive(PackRecord)], with the derive feature
+
+use crate::row::Value;
+use crate::{Kind, Table, TableField};
+
+// for the derived impls
+pub use anyhow::Result;
+
+pub trait PackRecord {
+    // a column per field, in order
+    fn schema() -> Vec<TableField>;
+
+    // to a table with that schema; nothing is pushed unless the whole row is acceptable
+    fn push_into(&self, table: &mut Table) -> Result<()>;
+}
+
+// the types a record's fields can have
+pub trait RecordValue {
+    fn kind() -> Kind;
+
+    fn nullable() -> bool {
+        false
+    }
+
+    fn value(&self) -> Value<'_>;
+}
+
+macro_rules! record_value {
+    ($ty:ty, $kind:ident, $variant:ident) => {
+        impl RecordValue for $ty {
+            fn kind() -> Kind {
+                Kind::$kind
+            }
+
+            fn value(&self) -> Value<'_> {
+                Value::$variant(*self)
+            }
+        }
+    };
+}
+
+record_value!(bool, Bool, Bool);
+record_value!(u8, U8, U8);
+record_value!(u16, U16, U16);
+record_value!(u32, U32, U32);
+record_value!(u64, U64, U64);
+record_value!(i8, I8, I8);
+record_value!(i16, I16, I16);
+record_value!(i32, I32, I32);
+record_value!(i64, I64, I64);
+record_value!(f32, F32, F32);
+record_value!(f64, F64, F64);
+
+impl RecordValue for String {
+    fn kind() -> Kind {
+        Kind::String
+    }
+
+    fn value(&self) -> Value<'_> {
+        Value::Str(self)
+    }
+}
+
+impl RecordValue for Vec<u8> {
+    fn kind() -> Kind {
+        Kind::Binary
+    }
+
+    fn value(&self) -> Value<'_> {
+        Value::Bytes(self)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl RecordValue for uuid::Uuid {
+    fn kind() -> Kind {
+        Kind::Uuid
+    }
+
+    fn value(&self) -> Value<'_> {
+        Value::Bytes(self.as_bytes())
+    }
+}
+
+impl<T: RecordValue> RecordValue for Option<T> {
+    fn kind() -> Kind {
+        T::kind()
+    }
+
+    fn nullable() -> bool {
+        true
+    }
+
+    fn value(&self) -> Value<'_> {
+        match self {
+            Some(v) => v.value(),
+            None => Value::Null,
+        }
+    }
+}
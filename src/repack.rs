@@ -1,23 +1,40 @@
-use std::io::{Read, Seek, Write};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use arrow2::array::{
-    Array, BooleanArray, MutableBooleanArray, MutablePrimitiveArray, MutableUtf8Array,
-    PrimitiveArray, TryExtend, Utf8Array,
-};
+use arrow2::array::{Array, MutableFixedSizeBinaryArray, PrimitiveArray, Utf8Array};
+use arrow2::compute::concatenate::concatenate;
+use arrow2::compute::take::take;
 use arrow2::datatypes::{DataType, Field, Schema};
 use arrow2::io::parquet::read;
-use arrow2::io::parquet::read::RowGroupMetaData;
+use arrow2::io::parquet::read::{
+    decompress, ColumnChunkMetaData, FileMetaData, Page, PageReader, PhysicalType, RowGroupMetaData,
+};
+use arrow2::io::parquet::write::CompressedPage;
 use arrow2::io::parquet::write::Encoding;
+use arrow2::io::parquet::write::FallibleStreamingIterator;
 use log::info;
+use parquet2::error::Error as ParquetError;
+use parquet2::page::{CompressedDictPage, DictPage};
+use parquet2::read::{Decompressor, PageIterator};
+use parquet_format_safe::thrift::protocol::TCompactInputProtocol;
+use parquet_format_safe::{Encoding as ThriftEncoding, PageHeader, PageType as ThriftPageType};
+use tracing::info_span;
 
-use crate::table::VarArray;
-use crate::{Kind, Packer, TableField};
+use crate::erratum::join_scoped;
+use crate::schema::{annotated_kind, NameMatching};
+use crate::split::Unsplit;
+use crate::spool::Spooled;
+use crate::table::{ip_bytes, VarArray};
+use crate::{Kind, Packer, PackerOptions, TableField};
 
 #[derive(Clone)]
 pub struct OutField {
     pub name: String,
-    pub data_type: DataType,
+    pub kind: Kind,
     pub nullable: bool,
 
     pub encoding: Encoding,
@@ -29,9 +46,11 @@ pub struct OutField {
 //     func: Box<dyn FnMut(Box<dyn Array>, &mut Table, usize) -> Result<()>>,
 // }
 
+pub type SplitFn = Box<dyn Send + FnMut(Box<dyn Array>, &mut [&mut VarArray]) -> Result<()>>;
+
 pub struct Split {
     pub output: Vec<OutField>,
-    pub func: Box<dyn Send + FnMut(Box<dyn Array>, &mut [&mut VarArray]) -> Result<()>>,
+    pub func: SplitFn,
 }
 
 pub enum Action {
@@ -53,36 +72,136 @@ pub struct Repack {
 
 #[inline]
 pub fn find_field<'f>(schema: &'f Schema, name: &str) -> Option<(usize, &'f Field)> {
+    find_field_matching(schema, name, NameMatching::default())
+}
+
+pub fn find_field_matching<'f>(
+    schema: &'f Schema,
+    name: &str,
+    matching: NameMatching,
+) -> Option<(usize, &'f Field)> {
     schema
         .fields
         .iter()
         .enumerate()
-        .find(|(_, f)| f.name == name)
+        .find(|(_, f)| matching.matches(&f.name, name))
 }
 
 pub enum LoopDecision {
     Include,
     Skip,
     Break,
+    // included unless the column's dictionary shows that no row can be the (string) value; much
+    // stronger than the statistics' min and max for categorical columns
+    IncludeIfMayEqual { column: String, value: String },
 }
 
+type RawColumn<'m, 'b> = Vec<(&'m ColumnChunkMetaData, &'b [u8])>;
+
 pub fn read_single_column(
     mut f: impl Read + Seek,
     rg_meta: &RowGroupMetaData,
     field_meta: Field,
 ) -> Result<Box<dyn Array>> {
-    let name = field_meta.name.to_string();
-    let col = read::read_columns(&mut f, rg_meta.columns(), &name)?;
-    let mut des = read::to_deserializer(
-        col,
-        field_meta,
-        rg_meta
-            .num_rows()
-            .try_into()
-            .expect("row count fits in memory"),
-        None,
-        None,
-    )?;
+    let col = read::read_columns(&mut f, rg_meta.columns(), &field_meta.name)?;
+    let col = col.iter().map(|(meta, chunk)| (*meta, chunk.as_slice()));
+    decode_column(col.collect(), rg_meta, field_meta, &Scratch::default())
+}
+
+// as read::read_columns, but into a buffer we get to keep
+fn read_chunk(
+    f: &mut (impl Read + Seek),
+    meta: &ColumnChunkMetaData,
+    buffer: &mut Vec<u8>,
+) -> Result<()> {
+    let (start, length) = meta.byte_range();
+    f.seek(SeekFrom::Start(start))?;
+    buffer.clear();
+    buffer.try_reserve(usize::try_from(length)?)?;
+    f.take(length).read_to_end(buffer)?;
+    Ok(())
+}
+
+// the page readers' and decompressors' buffers, given back as each column's decoded, so the next
+// row group's can reuse them
+#[derive(Clone, Default)]
+struct Scratch(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl Scratch {
+    fn take(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .expect("not poisoned")
+            .pop()
+            .unwrap_or_default()
+    }
+
+    fn give(&self, buffer: Vec<u8>) {
+        if buffer.capacity() > 0 {
+            self.0.lock().expect("not poisoned").push(buffer);
+        }
+    }
+}
+
+// a Decompressor, which gives its buffers back when it's dropped, after it's been decoded
+struct Recycled<P: PageIterator> {
+    inner: Option<Decompressor<P>>,
+    scratch: Scratch,
+}
+
+impl<P: PageIterator> FallibleStreamingIterator for Recycled<P> {
+    type Item = Page;
+    type Error = ParquetError;
+
+    fn advance(&mut self) -> Result<(), ParquetError> {
+        self.inner.as_mut().expect("until dropped").advance()
+    }
+
+    fn get(&self) -> Option<&Page> {
+        self.inner.as_ref().expect("until dropped").get()
+    }
+}
+
+impl<P: PageIterator> Drop for Recycled<P> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let (pages, decompressed) = inner.into_buffers();
+            self.scratch.give(pages);
+            self.scratch.give(decompressed);
+        }
+    }
+}
+
+// as read::to_deserializer, but borrowing the chunks
+fn decode_column(
+    col: RawColumn,
+    rg_meta: &RowGroupMetaData,
+    field_meta: Field,
+    scratch: &Scratch,
+) -> Result<Box<dyn Array>> {
+    let _span = info_span!("decode", column = field_meta.name.as_str()).entered();
+    let (columns, types): (Vec<_>, Vec<_>) = col
+        .into_iter()
+        .map(|(meta, chunk)| {
+            let pages = PageReader::new(
+                Cursor::new(chunk),
+                meta,
+                Arc::new(|_, _| true),
+                scratch.take(),
+                chunk.len() * 2 + 1024,
+            );
+            let pages = Recycled {
+                inner: Some(Decompressor::new(pages, scratch.take())),
+                scratch: scratch.clone(),
+            };
+            (
+                Unsplit::new(pages),
+                &meta.descriptor().descriptor.primitive_type,
+            )
+        })
+        .unzip();
+    let mut des =
+        read::column_iter_to_arrays(columns, types, field_meta, None, rg_meta.num_rows())?;
 
     let ret = des
         .next()
@@ -92,14 +211,308 @@ pub fn read_single_column(
     Ok(ret)
 }
 
+// for LoopDecision::IncludeIfMayEqual: whether any row of the group could be `value`. Only false
+// if every page of the column is dictionary encoded, and the dictionary doesn't have the value
+fn dictionary_may_contain(
+    mut f: impl Read + Seek,
+    rg_meta: &RowGroupMetaData,
+    column: &str,
+    value: &str,
+) -> Result<bool> {
+    let meta = match read::get_field_columns(rg_meta.columns(), column).as_slice() {
+        [meta] if meta.physical_type() == PhysicalType::ByteArray => *meta,
+        _ => return Ok(true),
+    };
+
+    Ok(match dictionary_entries(&mut f, meta, column)? {
+        Some(entries) => entries.iter().any(|entry| entry == value.as_bytes()),
+        None => true,
+    })
+}
+
+// the values of an enum (as written from a dictionary array) in the order they're first seen in
+// the row groups' dictionaries; every page has to be dictionary encoded
+fn enum_values(
+    mut f: impl Read + Seek,
+    metadata: &FileMetaData,
+    column: &str,
+) -> Result<Vec<String>> {
+    let mut values = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, rg_meta) in metadata.row_groups.iter().enumerate() {
+        let meta = match read::get_field_columns(rg_meta.columns(), column).as_slice() {
+            [meta] if meta.physical_type() == PhysicalType::ByteArray => *meta,
+            _ => bail!("{:?} isn't a column of strings", column),
+        };
+        let entries = dictionary_entries(&mut f, meta, column)?.ok_or_else(|| {
+            anyhow!(
+                "{:?} isn't entirely dictionary encoded in row group {}",
+                column,
+                i
+            )
+        })?;
+        for entry in entries {
+            let value = String::from_utf8(entry)
+                .with_context(|| anyhow!("in the dictionary for {:?}", column))?;
+            if seen.insert(value.clone()) {
+                values.push(value);
+            }
+        }
+    }
+    Ok(values)
+}
+
+// as Kind::from_arrow, for a column of the file, keeping its uuid or json annotation; an enum's
+// values aren't in the schema, only in the dictionaries
+fn column_kind(f: impl Read + Seek, metadata: &FileMetaData, field: &Field) -> Result<Kind> {
+    if let Some(kind) = annotated_kind(metadata.schema(), &field.name, &field.data_type) {
+        return Ok(kind);
+    }
+    Ok(match &field.data_type {
+        DataType::Dictionary(..) => Kind::Enum(enum_values(f, metadata, &field.name)?),
+        data_type => Kind::from_arrow(data_type)
+            .with_context(|| anyhow!("converting {:?} to a Kind", field.name))?,
+    })
+}
+
+// a byte array column chunk's dictionary, or None if any of its pages aren't dictionary encoded;
+// only the dictionary page is read, and maybe the other pages' headers
+fn dictionary_entries(
+    mut f: impl Read + Seek,
+    meta: &ColumnChunkMetaData,
+    column: &str,
+) -> Result<Option<Vec<Vec<u8>>>> {
+    if !all_dictionary_encoded(&mut f, meta)? {
+        return Ok(None);
+    }
+    let dictionary = match dictionary_page(&mut f, meta)? {
+        Some(dictionary) => dictionary,
+        None => return Ok(None),
+    };
+
+    // plain encoded: a u32 length before each value
+    let mut entries = Vec::new();
+    let mut rest = dictionary.buffer.as_slice();
+    while rest.len() >= 4 {
+        let (len, after) = rest.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().expect("split at 4")) as usize;
+        ensure!(
+            after.len() >= len,
+            "dictionary for {:?} is truncated",
+            column
+        );
+        let (entry, after) = after.split_at(len);
+        entries.push(entry.to_vec());
+        rest = after;
+    }
+
+    Ok(Some(entries))
+}
+
+// whether every data page of the chunk is dictionary encoded, from its encoding stats, or its
+// encodings, where they're enough; a plain dictionary page can't be told from a data page which
+// fell back to plain, so then the page headers are read, skipping over the pages
+fn all_dictionary_encoded(mut f: impl Read + Seek, meta: &ColumnChunkMetaData) -> Result<bool> {
+    let encoding = |e: ThriftEncoding| Encoding::try_from(e).map_err(anyhow::Error::from);
+    if let Some(stats) = &meta.metadata().encoding_stats {
+        for stat in stats {
+            if stat.page_type != ThriftPageType::DICTIONARY_PAGE
+                && !is_dictionary(encoding(stat.encoding)?)
+            {
+                return Ok(false);
+            }
+        }
+        return Ok(true);
+    }
+
+    let encodings = meta
+        .column_encoding()
+        .iter()
+        .map(|&e| encoding(e))
+        .collect::<Result<Vec<_>>>()?;
+    // the levels are rle, or bit packed
+    let levels = |e: Encoding| matches!(e, Encoding::Rle | Encoding::BitPacked);
+    if !encodings.iter().any(|&e| is_dictionary(e)) {
+        return Ok(false);
+    }
+    if encodings.iter().all(|&e| is_dictionary(e) || levels(e)) {
+        return Ok(true);
+    }
+    if encodings
+        .iter()
+        .any(|&e| !is_dictionary(e) && !levels(e) && e != Encoding::Plain)
+    {
+        return Ok(false);
+    }
+
+    let (start, length) = meta.byte_range();
+    let end = start + length;
+    let mut at = start;
+    while at < end {
+        f.seek(SeekFrom::Start(at))?;
+        let header = read_page_header(&mut f, length)?;
+        let data = match (&header.data_page_header, &header.data_page_header_v2) {
+            (Some(v1), _) => Some(v1.encoding),
+            (_, Some(v2)) => Some(v2.encoding),
+            _ => None,
+        };
+        if let Some(data) = data {
+            if !is_dictionary(encoding(data)?) {
+                return Ok(false);
+            }
+        }
+        at = f.stream_position()? + u64::try_from(header.compressed_page_size)?;
+    }
+    Ok(true)
+}
+
+// the chunk's dictionary page, which comes first, if it has one; parquet2 doesn't set the
+// dictionary_page_offset, so that's the data_page_offset
+fn dictionary_page(
+    mut f: impl Read + Seek,
+    meta: &ColumnChunkMetaData,
+) -> Result<Option<DictPage>> {
+    let (start, length) = meta.byte_range();
+    f.seek(SeekFrom::Start(start))?;
+    let header = read_page_header(&mut f, length)?;
+    let dictionary = match &header.dictionary_page_header {
+        Some(dictionary) => dictionary,
+        None => return Ok(None),
+    };
+
+    let size = u64::try_from(header.compressed_page_size)?;
+    ensure!(
+        size <= length,
+        "dictionary page is bigger than its column chunk"
+    );
+    let mut buffer = Vec::new();
+    f.take(size).read_to_end(&mut buffer)?;
+    ensure!(buffer.len() as u64 == size, "dictionary page is truncated");
+
+    let page = CompressedDictPage::new(
+        buffer,
+        meta.compression(),
+        usize::try_from(header.uncompressed_page_size)?,
+        usize::try_from(dictionary.num_values)?,
+        dictionary.is_sorted.unwrap_or(false),
+    );
+    Ok(
+        match decompress(CompressedPage::Dict(page), &mut Vec::new())? {
+            Page::Dict(page) => Some(page),
+            Page::Data(_) => bail!("a dictionary page decompressed into a data page"),
+        },
+    )
+}
+
+fn is_dictionary(encoding: Encoding) -> bool {
+    matches!(
+        encoding,
+        Encoding::RleDictionary | Encoding::PlainDictionary
+    )
+}
+
+// the thrift header before each page, at most as big as the chunk it's in
+fn read_page_header(f: impl Read, max_size: u64) -> Result<PageHeader> {
+    let mut protocol = TCompactInputProtocol::new(f, usize::try_from(max_size)?);
+    Ok(PageHeader::read_from_in_protocol(&mut protocol)?)
+}
+
+// there's no std::thread on wasm32
+fn decode_columns<'f>(
+    raw: Vec<(RawColumn, &'f Field)>,
+    rg_meta: &RowGroupMetaData,
+    scratch: &Scratch,
+) -> Result<Vec<(Box<dyn Array>, &'f Field)>> {
+    if cfg!(target_arch = "wasm32") || raw.len() < 2 {
+        return raw
+            .into_iter()
+            .map(|(col, field_meta)| {
+                let arr = decode_column(col, rg_meta, field_meta.clone(), scratch)?;
+                Ok((arr, field_meta))
+            })
+            .collect();
+    }
+
+    // a thread per core, each taking the next column until they're all done
+    let threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(raw.len());
+    let columns = Mutex::new(raw.into_iter().enumerate());
+    let mut decoded = thread::scope(|s| -> Result<Vec<_>> {
+        let handles = (0..threads)
+            .map(|_| {
+                s.spawn(|| -> Result<Vec<_>> {
+                    let mut decoded = Vec::new();
+                    loop {
+                        let next = columns.lock().expect("not poisoned").next();
+                        let Some((i, (col, field_meta))) = next else {
+                            return Ok(decoded);
+                        };
+                        let arr = decode_column(col, rg_meta, field_meta.clone(), scratch)?;
+                        decoded.push((i, (arr, field_meta)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut decoded = Vec::new();
+        for handle in handles {
+            decoded.extend(join_scoped(handle)?);
+        }
+        Ok(decoded)
+    })?;
+    decoded.sort_unstable_by_key(|(i, _)| *i);
+    Ok(decoded.into_iter().map(|(_, column)| column).collect())
+}
+
 pub fn transform<W: Write + Send + 'static>(
+    f: impl Read + Seek,
+    out: W,
+    repack: &mut Repack,
+    rg_filter: impl FnMut(usize, &RowGroupMetaData) -> LoopDecision,
+) -> Result<W> {
+    transform_with_options(f, out, repack, rg_filter, PackerOptions::default())
+}
+
+// for stdin, or a network stream: parquet's footer is at the end, so the whole file is read
+// first, into memory if it's at most `memory_limit` bytes, otherwise into a temporary file
+pub fn transform_stream<W: Write + Send + 'static>(
+    mut f: impl Read,
+    out: W,
+    repack: &mut Repack,
+    rg_filter: impl FnMut(usize, &RowGroupMetaData) -> LoopDecision,
+    options: PackerOptions,
+    memory_limit: usize,
+) -> Result<W> {
+    let mut buffer = Vec::new();
+    f.by_ref()
+        .take(u64::try_from(memory_limit)?.saturating_add(1))
+        .read_to_end(&mut buffer)?;
+    if buffer.len() <= memory_limit {
+        return transform_with_options(Cursor::new(buffer), out, repack, rg_filter, options);
+    }
+
+    let mut spooled = Spooled::create(None, "stream")?;
+    spooled.write_all(&buffer)?;
+    let copied = io::copy(&mut f, &mut spooled)?;
+    info!(
+        "spooled {} bytes of input to a temporary file",
+        buffer.len() as u64 + copied
+    );
+    drop(buffer);
+    spooled.seek(SeekFrom::Start(0))?;
+    transform_with_options(BufReader::new(spooled), out, repack, rg_filter, options)
+}
+
+pub fn transform_with_options<W: Write + Send + 'static>(
     mut f: impl Read + Seek,
     out: W,
     repack: &mut Repack,
     mut rg_filter: impl FnMut(usize, &RowGroupMetaData) -> LoopDecision,
+    options: PackerOptions,
 ) -> Result<W> {
     let metadata = read::read_metadata(&mut f)?;
     let in_schema = read::infer_schema(&metadata)?;
+    let matching = options.writer.name_matching;
 
     let out_schema = repack
         .ops
@@ -107,18 +520,16 @@ pub fn transform<W: Write + Send + 'static>(
         .flat_map(|op| -> Vec<Result<OutField>> {
             match &op.action {
                 Action::Drop | Action::ErrorOut => Vec::new(),
-                Action::Copy => vec![
-                    try {
-                        let (_, x) = find_field(&in_schema, &op.input)
-                            .ok_or_else(|| anyhow!("field has gone missing?"))?;
-                        OutField {
-                            name: x.name.to_string(),
-                            data_type: x.data_type.clone(),
-                            nullable: x.is_nullable,
-                            encoding: Encoding::Plain,
-                        }
-                    },
-                ],
+                Action::Copy => vec![try {
+                    let (_, x) = find_field_matching(&in_schema, &op.input, matching)
+                        .ok_or_else(|| anyhow!("field has gone missing?"))?;
+                    OutField {
+                        name: x.name.to_string(),
+                        kind: column_kind(&mut f, &metadata, x)?,
+                        nullable: x.is_nullable,
+                        encoding: Encoding::Plain,
+                    }
+                }],
 
                 Action::Split(split) => split.output.iter().cloned().map(Ok).collect(),
             }
@@ -130,16 +541,24 @@ pub fn transform<W: Write + Send + 'static>(
         .map(|v| -> Result<TableField> {
             Ok(TableField {
                 name: v.name.to_string(),
-                kind: Kind::from_arrow(&v.data_type)
-                    .with_context(|| anyhow!("converting {:?} to a Kind", v.name))?,
-                nullable: false,
+                kind: v.kind.clone(),
+                nullable: v.nullable,
                 encoding: Encoding::Plain,
+                metadata: Default::default(),
+                constraint: None,
+                compression: None,
+                statistics: true,
+                default: None,
             })
         })
         .collect::<Result<Vec<_>>>()
         .with_context(|| anyhow!("generating an internal schema for the output"))?;
 
-    let mut writer = Packer::new(out, &table_schema)?;
+    let mut writer = Packer::with_options(out, &table_schema, options)?;
+
+    // the column chunks are read into these, which are then reused for later row groups
+    let mut buffers: Vec<Vec<u8>> = Vec::new();
+    let scratch = Scratch::default();
 
     for (rg, rg_meta) in metadata.row_groups.iter().enumerate() {
         info!(
@@ -153,74 +572,61 @@ pub fn transform<W: Write + Send + 'static>(
             LoopDecision::Include => (),
             LoopDecision::Skip => continue,
             LoopDecision::Break => break,
+            LoopDecision::IncludeIfMayEqual { column, value } => {
+                let (_, field) = find_field_matching(&in_schema, &column, matching)
+                    .ok_or_else(|| anyhow!("filtering on missing column {:?}", column))?;
+                if !dictionary_may_contain(&mut f, rg_meta, &field.name, &value)? {
+                    info!("rg {} can't have {:?} in {:?}", rg, value, column);
+                    continue;
+                }
+            }
         };
 
-        for op in &mut repack.ops {
-            let (_field, field_meta) = find_field(&in_schema, &op.input)
+        let _span = info_span!("row_group", rg, rows = rg_meta.num_rows()).entered();
+
+        // the reads have to take turns on the file, but the columns decode independently
+        let mut chunks = Vec::with_capacity(repack.ops.len());
+        for op in &repack.ops {
+            let (_field, field_meta) = find_field_matching(&in_schema, &op.input, matching)
                 .ok_or_else(|| anyhow!("looking up input field {:?}", op.input))?;
+            let metas = read::get_field_columns(rg_meta.columns(), &field_meta.name);
+            chunks.push((metas, field_meta));
+        }
 
-            let arr = read_single_column(&mut f, rg_meta, field_meta.clone())?;
+        let needed = chunks.iter().map(|(metas, _)| metas.len()).sum();
+        if buffers.len() < needed {
+            buffers.resize_with(needed, Vec::new);
+        }
+        for (meta, buffer) in chunks.iter().flat_map(|(metas, _)| metas).zip(&mut buffers) {
+            read_chunk(&mut f, meta, buffer)?;
+        }
 
+        let mut remaining = buffers.as_slice();
+        let raw = chunks
+            .into_iter()
+            .map(|(metas, field_meta)| {
+                let (these, rest) = remaining.split_at(metas.len());
+                remaining = rest;
+                let col = metas.into_iter().zip(these.iter().map(|b| b.as_slice()));
+                (col.collect(), field_meta)
+            })
+            .collect();
+        let arrays = decode_columns(raw, rg_meta, &scratch)?;
+
+        for (op, (arr, field_meta)) in repack.ops.iter_mut().zip(arrays) {
             match &mut op.action {
                 Action::ErrorOut => bail!("asked to error out after loading {:?}", field_meta.name),
                 Action::Drop => unimplemented!("drop"),
                 Action::Copy => {
                     let (output, _) = writer.find_field(&op.input).expect("created above");
-
-                    let output = writer.table().get(output);
-
-                    if let Some(output) = output.downcast_mut::<MutableUtf8Array<i32>>() {
-                        output
-                            .try_extend(
-                                arr.as_any()
-                                    .downcast_ref::<Utf8Array<i32>>()
-                                    .expect("input=output")
-                                    .iter(),
-                            )
-                            .with_context(|| {
-                                anyhow!("copying {} rows of {:?}", metadata.num_rows, op.input)
-                            })?;
-                    } else if let Some(output) = output.downcast_mut::<MutablePrimitiveArray<i64>>()
-                    {
-                        output.extend(
-                            arr.as_any()
-                                .downcast_ref::<PrimitiveArray<i64>>()
-                                .expect("input=output")
-                                .iter()
-                                .map(|v| v.map(|x| *x)),
-                        );
-                    } else if let Some(output) = output.downcast_mut::<MutablePrimitiveArray<i32>>()
-                    {
-                        output.extend(
-                            arr.as_any()
-                                .downcast_ref::<PrimitiveArray<i32>>()
-                                .expect("input=output")
-                                .iter()
-                                .map(|v| v.map(|x| *x)),
-                        );
-                    } else if let Some(output) = output.downcast_mut::<MutableBooleanArray>() {
-                        output.extend(
-                            arr.as_any()
-                                .downcast_ref::<BooleanArray>()
-                                .expect("input=output")
-                                .iter(),
-                        );
-                    } else if let Some(output) = output.downcast_mut::<MutablePrimitiveArray<f64>>()
-                    {
-                        output.extend(
-                            arr.as_any()
-                                .downcast_ref::<PrimitiveArray<f64>>()
-                                .expect("input=output")
-                                .iter()
-                                .map(|v| v.map(|x| *x)),
-                        );
-                    } else {
-                        bail!(
-                            "copy for {:?} columns ({:?})",
-                            field_meta.data_type,
-                            field_meta.name
-                        )
+                    let table = writer.table();
+                    // as in Table::push_null, null columns are constants; there's nothing to copy
+                    if table.schema()[output].kind == Kind::Null {
+                        continue;
                     }
+                    table.get(output).copy_from(arr.as_ref()).with_context(|| {
+                        anyhow!("copying {} rows of {:?}", metadata.num_rows, op.input)
+                    })?;
                 }
                 Action::Split(s) => {
                     let fields: Vec<usize> = s
@@ -233,7 +639,7 @@ pub fn transform<W: Write + Send + 'static>(
                                 .0
                         })
                         .collect();
-                    (s.func)(arr, &mut writer.table().get_many(&fields))?;
+                    (s.func)(arr, &mut writer.table().columns(&fields)?)?;
                 }
             }
         }
@@ -244,3 +650,203 @@ pub fn transform<W: Write + Send + 'static>(
 
     writer.finish()
 }
+
+// the rows of a lookup file, by their (string or integer) key
+enum Lookup {
+    Strs(HashMap<String, u32>),
+    Ints(HashMap<i64, u32>),
+}
+
+// a key column's values, for either side of the join
+enum Keys<'a> {
+    Strs(Box<dyn Iterator<Item = Option<&'a str>> + 'a>),
+    Ints(Box<dyn Iterator<Item = Option<i64>> + 'a>),
+}
+
+impl<'a> Keys<'a> {
+    fn of(key: &'a dyn Array) -> Result<Self> {
+        let any = key.as_any();
+        Ok(if let Some(arr) = any.downcast_ref::<Utf8Array<i32>>() {
+            Keys::Strs(Box::new(arr.iter()))
+        } else if let Some(arr) = any.downcast_ref::<Utf8Array<i64>>() {
+            Keys::Strs(Box::new(arr.iter()))
+        } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i64>>() {
+            Keys::Ints(Box::new(arr.iter().map(|v| v.copied())))
+        } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i32>>() {
+            Keys::Ints(Box::new(arr.iter().map(|v| v.copied().map(i64::from))))
+        } else {
+            bail!("can't join on {:?} columns", key.data_type());
+        })
+    }
+}
+
+impl Lookup {
+    // the first row wins, if a key is repeated
+    fn index(key: &dyn Array) -> Result<Self> {
+        fn first<K: Hash + Eq>(keys: impl Iterator<Item = Option<K>>) -> Result<HashMap<K, u32>> {
+            let mut index = HashMap::new();
+            for (row, v) in keys.enumerate() {
+                if let Some(v) = v {
+                    index.entry(v).or_insert(u32::try_from(row)?);
+                }
+            }
+            Ok(index)
+        }
+        Ok(match Keys::of(key)? {
+            Keys::Strs(keys) => Lookup::Strs(first(keys.map(|v| v.map(str::to_string)))?),
+            Keys::Ints(keys) => Lookup::Ints(first(keys)?),
+        })
+    }
+
+    // the matching row of the lookup file, for each row of `key`; null if there isn't one
+    fn rows(&self, key: &dyn Array) -> Result<PrimitiveArray<u32>> {
+        Ok(match (self, Keys::of(key)?) {
+            (Lookup::Strs(index), Keys::Strs(keys)) => keys
+                .map(|v| v.and_then(|v| index.get(v).copied()))
+                .collect(),
+            (Lookup::Ints(index), Keys::Ints(keys)) => keys
+                .map(|v| v.and_then(|v| index.get(&v).copied()))
+                .collect(),
+            (Lookup::Strs(_), Keys::Ints(_)) => {
+                bail!("can't look {:?} keys up by string", key.data_type())
+            }
+            (Lookup::Ints(_), Keys::Strs(_)) => {
+                bail!("can't look {:?} keys up by integer", key.data_type())
+            }
+        })
+    }
+}
+
+// denormalising: the input column is looked up in the `key` column of a (small) lookup file,
+// which is loaded into memory, and the `values` columns of the matching row are output; all
+// nulls if nothing matches. String keys only match strings, and integers integers. Use a Copy op
+// as well to keep the input column
+pub fn lookup_join(lookup: impl Read + Seek, key: &str, values: &[&str]) -> Result<Split> {
+    lookup_join_matching(lookup, key, values, NameMatching::default())
+}
+
+// as lookup_join, finding the lookup file's columns as e.g. the writer's name_matching
+pub fn lookup_join_matching(
+    mut lookup: impl Read + Seek,
+    key: &str,
+    values: &[&str],
+    matching: NameMatching,
+) -> Result<Split> {
+    let metadata = read::read_metadata(&mut lookup)?;
+    let schema = read::infer_schema(&metadata)?;
+
+    let mut wanted = Vec::with_capacity(values.len() + 1);
+    for name in std::iter::once(&key).chain(values) {
+        let (i, _) = find_field_matching(&schema, name, matching)
+            .ok_or_else(|| anyhow!("lookup file has no column {:?}", name))?;
+        wanted.push(i);
+    }
+    let schema = schema.filter(|i, _| wanted.contains(&i));
+    let position = |name: &str| {
+        find_field_matching(&schema, name, matching)
+            .map(|(i, _)| i)
+            .expect("just checked")
+    };
+
+    // the kinds come from the lookup file, before the reader takes it
+    let output = values
+        .iter()
+        .map(|name| -> Result<OutField> {
+            let field = &schema.fields[position(name)];
+            Ok(OutField {
+                name: field.name.to_string(),
+                kind: column_kind(&mut lookup, &metadata, field)?,
+                nullable: true,
+                encoding: Encoding::Plain,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let reader = read::FileReader::new(
+        lookup,
+        metadata.row_groups,
+        schema.clone(),
+        None,
+        None,
+        None,
+    );
+    let chunks = reader.collect::<Result<Vec<_>, _>>()?;
+    let column = |i: usize| -> Result<Box<dyn Array>> {
+        let parts = chunks
+            .iter()
+            .map(|chunk| chunk.arrays()[i].as_ref())
+            .collect::<Vec<_>>();
+        Ok(match parts.as_slice() {
+            [] => arrow2::array::new_empty_array(schema.fields[i].data_type.clone()),
+            parts => concatenate(parts)?,
+        })
+    };
+
+    let index = Lookup::index(column(position(key))?.as_ref())?;
+    let columns = values
+        .iter()
+        .map(|name| column(position(name)))
+        .collect::<Result<Vec<_>>>()?;
+
+    info!(
+        "loaded {} lookup rows",
+        columns.first().map(|c| c.len()).unwrap_or_default()
+    );
+
+    Ok(Split {
+        output,
+        func: Box::new(move |input, outputs| {
+            let rows = index.rows(input.as_ref())?;
+            for (values, output) in columns.iter().zip(outputs.iter_mut()) {
+                output.copy_from(take(values.as_ref(), &rows)?.as_ref())?;
+            }
+            Ok(())
+        }),
+    })
+}
+
+// addresses, as strings, to an Ipv4 or Ipv6 column called `output`; v4 addresses are mapped into
+// Ipv6 columns, and anything unparseable is an error. Use a Copy op as well to keep the strings
+pub fn parse_ips(output: &str, kind: Kind) -> Result<Split> {
+    ensure!(
+        matches!(kind, Kind::Ipv4 | Kind::Ipv6),
+        "{} columns don't hold addresses",
+        kind
+    );
+    let name = output.to_string();
+    Ok(Split {
+        output: vec![OutField {
+            name: name.clone(),
+            kind: kind.clone(),
+            nullable: true,
+            encoding: Encoding::Plain,
+        }],
+        func: Box::new(move |input, outputs| {
+            let any = input.as_any();
+            let strings: Box<dyn Iterator<Item = Option<&str>>> =
+                if let Some(arr) = any.downcast_ref::<Utf8Array<i32>>() {
+                    Box::new(arr.iter())
+                } else if let Some(arr) = any.downcast_ref::<Utf8Array<i64>>() {
+                    Box::new(arr.iter())
+                } else {
+                    bail!("can't parse addresses from {:?} columns", input.data_type());
+                };
+            let output = outputs[0]
+                .downcast_mut::<MutableFixedSizeBinaryArray>()
+                .expect("addresses are fixed size");
+            for v in strings {
+                let bytes = v
+                    .map(|v| -> Result<Vec<u8>> {
+                        let addr = v
+                            .trim()
+                            .parse()
+                            .with_context(|| anyhow!("parsing {:?} for {:?}", v, name))?;
+                        ip_bytes(&kind, addr)
+                    })
+                    .transpose()?;
+                output.try_push(bytes)?;
+            }
+            Ok(())
+        }),
+    })
+}
@@ -0,0 +1,152 @@
+// how each column came out, from the footer we just wrote; see Writer::finish_with_report
+
+use std::fmt;
+
+use arrow2::io::parquet::write::Encoding;
+use parquet2::metadata::ThriftFileMetaData;
+use parquet2::page::PageType;
+
+use crate::schema::encoding_name;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnReport {
+    // dotted, for nested columns
+    pub name: String,
+    // as listed in the column chunks, including the levels' rle
+    pub encodings: Vec<Encoding>,
+    pub compressed_bytes: u64,
+    pub uncompressed_bytes: u64,
+    pub values: u64,
+    // only known if statistics were written for every row group, and never for a column with
+    // any dictionary encoded chunk (enums, or an `auto_encoding` choice), whose statistics are
+    // the dictionary's
+    pub nulls: Option<u64>,
+    // row groups which started with a dictionary, then had to switch to something else; from the
+    // data pages' encoding stats, where the file has them (parquet2 doesn't write them). Otherwise
+    // the dictionary page is plain, so a switch to plain can't be told apart, and isn't counted
+    pub dictionary_fallbacks: usize,
+}
+
+impl ColumnReport {
+    pub fn null_ratio(&self) -> Option<f64> {
+        let nulls = self.nulls?;
+        Some(match self.values {
+            0 => 0.,
+            values => nulls as f64 / values as f64,
+        })
+    }
+
+    // above one is good
+    pub fn compression_ratio(&self) -> f64 {
+        match self.compressed_bytes {
+            0 => 1.,
+            compressed => self.uncompressed_bytes as f64 / compressed as f64,
+        }
+    }
+}
+
+impl fmt::Display for ColumnReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encodings = self
+            .encodings
+            .iter()
+            .map(|e| encoding_name(*e))
+            .collect::<Vec<_>>()
+            .join("+");
+        write!(
+            f,
+            "{}: {}, {} -> {} bytes ({:.1}x)",
+            self.name,
+            encodings,
+            self.uncompressed_bytes,
+            self.compressed_bytes,
+            self.compression_ratio()
+        )?;
+        if let Some(ratio) = self.null_ratio() {
+            write!(f, ", {:.1}% null", ratio * 100.)?;
+        }
+        if self.dictionary_fallbacks > 0 {
+            write!(f, ", {} dictionary fallbacks", self.dictionary_fallbacks)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_dictionary(encoding: Encoding) -> bool {
+    matches!(
+        encoding,
+        Encoding::RleDictionary | Encoding::PlainDictionary
+    )
+}
+
+// `data_pages` are the data pages' encodings, if the chunk lists them; `encodings` are the chunk's
+fn fell_back(data_pages: Option<&[Encoding]>, encodings: &[Encoding]) -> bool {
+    if let Some(data) = data_pages {
+        return data.iter().any(|&e| is_dictionary(e)) && data.iter().any(|&e| !is_dictionary(e));
+    }
+    // the levels are always rle, and the dictionary page plain
+    encodings.iter().any(|&e| is_dictionary(e))
+        && encodings.iter().any(|&e| {
+            !is_dictionary(e) && !matches!(e, Encoding::Plain | Encoding::Rle | Encoding::BitPacked)
+        })
+}
+
+pub(crate) fn column_reports(metadata: &ThriftFileMetaData) -> Vec<ColumnReport> {
+    let mut reports: Vec<ColumnReport> = Vec::new();
+
+    for row_group in &metadata.row_groups {
+        for (i, chunk) in row_group.columns.iter().enumerate() {
+            let meta = match &chunk.meta_data {
+                Some(meta) => meta,
+                None => continue,
+            };
+            if reports.len() <= i {
+                reports.push(ColumnReport {
+                    name: meta.path_in_schema.join("."),
+                    nulls: Some(0),
+                    ..Default::default()
+                });
+            }
+            let report = &mut reports[i];
+
+            let encodings = meta
+                .encodings
+                .iter()
+                .filter_map(|e| Encoding::try_from(*e).ok())
+                .collect::<Vec<_>>();
+            let data_pages = meta.encoding_stats.as_ref().map(|stats| {
+                stats
+                    .iter()
+                    .filter(|s| {
+                        PageType::try_from(s.page_type).ok() != Some(PageType::DictionaryPage)
+                    })
+                    .filter_map(|s| Encoding::try_from(s.encoding).ok())
+                    .collect::<Vec<_>>()
+            });
+            if fell_back(data_pages.as_deref(), &encodings) {
+                report.dictionary_fallbacks += 1;
+            }
+            for &encoding in &encodings {
+                if !report.encodings.contains(&encoding) {
+                    report.encodings.push(encoding);
+                }
+            }
+
+            report.compressed_bytes += u64::try_from(meta.total_compressed_size).unwrap_or(0);
+            report.uncompressed_bytes += u64::try_from(meta.total_uncompressed_size).unwrap_or(0);
+            report.values += u64::try_from(meta.num_values).unwrap_or(0);
+            // arrow2 takes a dictionary column's statistics from the dictionary, so they don't
+            // count the rows' nulls
+            let nulls = match encodings.iter().any(|&e| is_dictionary(e)) {
+                true => None,
+                false => meta.statistics.as_ref().and_then(|s| s.null_count),
+            };
+            report.nulls = match (report.nulls, nulls) {
+                (Some(total), Some(nulls)) => Some(total + u64::try_from(nulls).unwrap_or(0)),
+                _ => None,
+            };
+        }
+    }
+
+    reports
+}
@@ -0,0 +1,127 @@
+// one file after another, each finished and the next started once it's big enough; they're
+// named from a template, with "{seq}" counting from 0, and each only appears once it's finished
+
+use std::path::PathBuf;
+
+use anyhow::{ensure, Result};
+
+use crate::{FilePacker, Packer, PackerOptions, Table, TableField};
+
+#[derive(Clone, Default)]
+pub struct RollingOptions {
+    // checked by consider_flushing; batches the writer hasn't got to aren't counted, and nor is
+    // the footer, so a file can end up a row group or so bigger than this
+    pub max_file_bytes: Option<u64>,
+    // including the buffered rows, so consider_flushing after every row makes this exact
+    pub max_file_rows: Option<usize>,
+    pub packer: PackerOptions,
+}
+
+pub struct RollingPacker {
+    template: String,
+    schema: Box<[TableField]>,
+    options: RollingOptions,
+    current: FilePacker,
+    // the finished files, in order
+    files: Vec<PathBuf>,
+}
+
+impl RollingPacker {
+    pub fn new(
+        template: impl Into<String>,
+        schema: &[TableField],
+        max_file_rows: usize,
+    ) -> Result<Self> {
+        let options = RollingOptions {
+            max_file_rows: Some(max_file_rows),
+            ..Default::default()
+        };
+        Self::with_options(template, schema, options)
+    }
+
+    pub fn with_options(
+        template: impl Into<String>,
+        schema: &[TableField],
+        options: RollingOptions,
+    ) -> Result<Self> {
+        let template = template.into();
+        ensure!(
+            template.contains("{seq}"),
+            "the file name template {:?} needs a {{seq}}, or every file would have the same name",
+            template
+        );
+        let current = create(&template, 0, schema, &options)?;
+        Ok(RollingPacker {
+            template,
+            schema: schema.to_vec().into_boxed_slice(),
+            options,
+            current,
+            files: Vec::new(),
+        })
+    }
+
+    pub fn table(&mut self) -> &mut Table {
+        self.current.table()
+    }
+
+    // as Packer::consider_flushing, then finishes the file if it's big enough
+    pub fn consider_flushing(&mut self) -> Result<()> {
+        self.current.consider_flushing()?;
+        let rows = self.current.flushed_rows() + self.current.table().rows();
+        let too_long = self.options.max_file_rows.is_some_and(|max| rows >= max);
+        let too_big = self
+            .options
+            .max_file_bytes
+            .is_some_and(|max| self.current.bytes_written() >= max);
+        if too_long || too_big {
+            self.roll()?;
+        }
+        Ok(())
+    }
+
+    // ends the row group; see Packer::flush
+    pub fn flush(&mut self) -> Result<()> {
+        self.current.flush()
+    }
+
+    // finishes the current file, even if it's small, and starts the next, with the same constants
+    pub fn roll(&mut self) -> Result<()> {
+        let mut next = create(
+            &self.template,
+            self.files.len() + 1,
+            &self.schema,
+            &self.options,
+        )?;
+        next.table().copy_constants(self.current.table());
+        let finished = std::mem::replace(&mut self.current, next);
+        self.files.push(finished.finish()?);
+        Ok(())
+    }
+
+    // the files finished so far
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    // all of the files; the last is dropped if nothing was pushed to it, unless it's the only one
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        let empty = self.current.flushed_rows() == 0 && self.current.table().rows() == 0;
+        if !empty || self.files.is_empty() {
+            self.files.push(self.current.finish()?);
+        } else {
+            // waits for the writer, so the unpersisted file's gone by the time this returns
+            drop(self.current.finish_pending()?);
+        }
+        Ok(self.files)
+    }
+}
+
+fn create(
+    template: &str,
+    seq: usize,
+    schema: &[TableField],
+    options: &RollingOptions,
+) -> Result<FilePacker> {
+    let path = template.replace("{seq}", &seq.to_string());
+    Packer::create_with_options(path, schema, options.packer.clone())
+}
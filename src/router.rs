@@ -0,0 +1,96 @@
+// one Packer per tag, for streams which mix records of different types; each is created the
+// first time its tag is seen
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::Write;
+
+use anyhow::{Context, Result};
+
+use crate::row::RowBuilder;
+use crate::{Packer, PackerOptions, TableField};
+
+pub struct Router<K, W: Write, F> {
+    // called with a new tag, to find where its rows go, and what they look like
+    registry: F,
+    options: PackerOptions,
+    index: HashMap<K, usize>,
+    // in the order the tags were first seen
+    packers: Vec<(K, Packer<W>)>,
+}
+
+impl<K, W, F> Router<K, W, F>
+where
+    K: Hash + Eq + Clone + Debug,
+    W: Write + Send + 'static,
+    F: FnMut(&K) -> Result<(W, Vec<TableField>)>,
+{
+    pub fn new(registry: F) -> Self {
+        Self::with_options(registry, PackerOptions::default())
+    }
+
+    pub fn with_options(registry: F, options: PackerOptions) -> Self {
+        Router {
+            registry,
+            options,
+            index: HashMap::new(),
+            packers: Vec::new(),
+        }
+    }
+
+    pub fn packer(&mut self, tag: &K) -> Result<&mut Packer<W>> {
+        let i = match self.index.get(tag) {
+            Some(&i) => i,
+            None => {
+                let (inner, schema) = (self.registry)(tag)
+                    .with_context(|| format!("looking up the schema for {:?}", tag))?;
+                let packer = Packer::with_options(inner, &schema, self.options.clone())
+                    .with_context(|| format!("creating the packer for {:?}", tag))?;
+                self.packers.push((tag.clone(), packer));
+                self.index.insert(tag.clone(), self.packers.len() - 1);
+                self.packers.len() - 1
+            }
+        };
+        Ok(&mut self.packers[i].1)
+    }
+
+    // the row is committed to its tag's table; see consider_flushing
+    pub fn row<'r>(&mut self, tag: &K) -> Result<RowBuilder<'_, 'r>> {
+        Ok(self.packer(tag)?.table().row())
+    }
+
+    pub fn consider_flushing(&mut self) -> Result<()> {
+        for (tag, packer) in &mut self.packers {
+            packer
+                .consider_flushing()
+                .with_context(|| format!("flushing {:?}", tag))?;
+        }
+        Ok(())
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &K> {
+        self.packers.iter().map(|(tag, _)| tag)
+    }
+
+    // every packer is finished, in the order the tags were first seen, even if an earlier one
+    // failed; the first failure is returned
+    pub fn finish(self) -> Result<Vec<(K, W)>> {
+        let mut finished = Vec::with_capacity(self.packers.len());
+        let mut first_err = None;
+        for (tag, packer) in self.packers {
+            match packer.finish() {
+                Ok(inner) => finished.push((tag, inner)),
+                Err(e) => {
+                    let e = e.context(format!("finishing {:?}", tag));
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_err {
+            Some(e) => Err(e),
+            None => Ok(finished),
+        }
+    }
+}
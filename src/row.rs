@@ -0,0 +1,262 @@
+// building a whole row, then committing it at once; see Table::row
+
+use std::borrow::Cow;
+
+use anyhow::{anyhow, ensure, Result};
+
+use crate::geo::geometry_type;
+use crate::table::{decimal_fits, ColumnAt};
+use crate::{Kind, Table};
+
+// one per column, for Table::push_row; the nested kinds (lists, structs and maps) can only be
+// null here, and are pushed with Table::push_list and friends
+#[derive(Clone, Copy, Debug)]
+pub enum Value<'r> {
+    Null,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    Str(&'r str),
+    Bytes(&'r [u8]),
+}
+
+impl Value<'_> {
+    // the integers are also for the date and time kinds, as the setters say
+    pub(crate) fn check(&self, column: &ColumnAt) -> Result<()> {
+        let field = column.field;
+        let fits = match (self, &field.kind) {
+            (Value::Null, _) => true,
+            (Value::Bool(_), Kind::Bool) => true,
+            (Value::U8(_), Kind::U8) => true,
+            (Value::U16(_), Kind::U16) => true,
+            (Value::U32(_), Kind::U32) => true,
+            (Value::U64(_), Kind::U64) => true,
+            (Value::I8(_), Kind::I8) => true,
+            (Value::I16(_), Kind::I16) => true,
+            (Value::I32(_), Kind::I32 | Kind::Date | Kind::TimeMillis) => true,
+            (
+                Value::I64(_),
+                Kind::I64
+                | Kind::TimestampSecsZ
+                | Kind::Timestamp { .. }
+                | Kind::DateMillis
+                | Kind::TimeMicros
+                | Kind::Duration(_),
+            ) => true,
+            (Value::I128(unscaled), Kind::Decimal { precision, .. }) => {
+                ensure!(
+                    decimal_fits(*unscaled, *precision),
+                    "{} has more than {} digits, for {}",
+                    unscaled,
+                    precision,
+                    column
+                );
+                true
+            }
+            (Value::F32(_), Kind::F32) => true,
+            (Value::F64(_), Kind::F64) => true,
+            (Value::Str(_), Kind::String | Kind::LargeString | Kind::Json | Kind::Enum(_)) => true,
+            (Value::Bytes(_), Kind::Binary) => true,
+            (Value::Bytes(val), Kind::Geometry) => {
+                ensure!(
+                    geometry_type(val).is_some(),
+                    "{} expects WKB, which this doesn't start like",
+                    column
+                );
+                true
+            }
+            (Value::Bytes(val), Kind::Uuid | Kind::FixedBytes(_) | Kind::Ipv4 | Kind::Ipv6) => {
+                let size = match field.kind {
+                    Kind::FixedBytes(size) => size,
+                    Kind::Ipv4 => 4,
+                    _ => 16,
+                };
+                ensure!(
+                    val.len() == size,
+                    "{} expects {} bytes, got {}",
+                    column,
+                    size,
+                    val.len()
+                );
+                true
+            }
+            _ => false,
+        };
+        ensure!(fits, "{} can't hold {:?}", column, self);
+        Ok(())
+    }
+}
+
+// after the constraints have been applied
+pub(crate) enum Checked<'r> {
+    Null,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    Str(Cow<'r, str>),
+    Bytes(Cow<'r, [u8]>),
+    Binary(Cow<'r, [u8]>),
+}
+
+pub trait Column {
+    fn index(&self, table: &Table) -> Result<usize>;
+}
+
+impl Column for usize {
+    fn index(&self, table: &Table) -> Result<usize> {
+        ensure!(*self < table.schema().len(), "there's no column {}", self);
+        Ok(*self)
+    }
+}
+
+// as the table's NameMatching; see Table::column_index, to look the names up only once
+impl Column for &str {
+    fn index(&self, table: &Table) -> Result<usize> {
+        table
+            .column_index(self)
+            .ok_or_else(|| anyhow!("there's no column {:?}", self))
+    }
+}
+
+// a buffered row, as Table::iter_rows gives them; it's read as Table::value_at reads
+#[derive(Clone, Copy)]
+pub struct RowView<'t> {
+    table: &'t Table,
+    row: usize,
+}
+
+impl<'t> RowView<'t> {
+    pub(crate) fn new(table: &'t Table, row: usize) -> Self {
+        RowView { table, row }
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn value(&self, col: impl Column) -> Result<Value<'t>> {
+        let i = col.index(self.table)?;
+        self.table.value_at(i, self.row)
+    }
+}
+
+// types are checked against the schema as values are set, so committing doesn't have to look
+// at the builders; unset columns are null
+pub struct RowBuilder<'t, 'r> {
+    table: &'t mut Table,
+    values: Vec<Value<'r>>,
+}
+
+impl<'t, 'r> RowBuilder<'t, 'r> {
+    pub(crate) fn new(table: &'t mut Table) -> Self {
+        let columns = table.schema().len();
+        RowBuilder {
+            table,
+            values: vec![Value::Null; columns],
+        }
+    }
+
+    fn set(&mut self, col: impl Column, val: Value<'r>) -> Result<&mut Self> {
+        let i = col.index(self.table)?;
+        val.check(&self.table.column_at(i))?;
+        self.values[i] = val;
+        Ok(self)
+    }
+
+    pub fn set_null(&mut self, col: impl Column) -> Result<&mut Self> {
+        self.set(col, Value::Null)
+    }
+
+    pub fn set_bool(&mut self, col: impl Column, val: bool) -> Result<&mut Self> {
+        self.set(col, Value::Bool(val))
+    }
+
+    pub fn set_u8(&mut self, col: impl Column, val: u8) -> Result<&mut Self> {
+        self.set(col, Value::U8(val))
+    }
+
+    pub fn set_u16(&mut self, col: impl Column, val: u16) -> Result<&mut Self> {
+        self.set(col, Value::U16(val))
+    }
+
+    pub fn set_u32(&mut self, col: impl Column, val: u32) -> Result<&mut Self> {
+        self.set(col, Value::U32(val))
+    }
+
+    pub fn set_u64(&mut self, col: impl Column, val: u64) -> Result<&mut Self> {
+        self.set(col, Value::U64(val))
+    }
+
+    pub fn set_i8(&mut self, col: impl Column, val: i8) -> Result<&mut Self> {
+        self.set(col, Value::I8(val))
+    }
+
+    pub fn set_i16(&mut self, col: impl Column, val: i16) -> Result<&mut Self> {
+        self.set(col, Value::I16(val))
+    }
+
+    // also for dates, and millis times
+    pub fn set_i32(&mut self, col: impl Column, val: i32) -> Result<&mut Self> {
+        self.set(col, Value::I32(val))
+    }
+
+    // also for timestamps, millis dates, micros times, and durations
+    pub fn set_i64(&mut self, col: impl Column, val: i64) -> Result<&mut Self> {
+        self.set(col, Value::I64(val))
+    }
+
+    pub fn set_f32(&mut self, col: impl Column, val: f32) -> Result<&mut Self> {
+        self.set(col, Value::F32(val))
+    }
+
+    pub fn set_f64(&mut self, col: impl Column, val: f64) -> Result<&mut Self> {
+        self.set(col, Value::F64(val))
+    }
+
+    // the unscaled value, as Table::push_decimal
+    pub fn set_decimal(&mut self, col: impl Column, unscaled: i128) -> Result<&mut Self> {
+        self.set(col, Value::I128(unscaled))
+    }
+
+    pub fn set_str(&mut self, col: impl Column, val: &'r str) -> Result<&mut Self> {
+        self.set(col, Value::Str(val))
+    }
+
+    pub fn set_uuid(&mut self, col: impl Column, val: &'r [u8]) -> Result<&mut Self> {
+        ensure!(val.len() == 16, "uuids are 16 bytes, not {}", val.len());
+        self.set(col, Value::Bytes(val))
+    }
+
+    // also for addresses, in network order
+    pub fn set_fixed_bytes(&mut self, col: impl Column, val: &'r [u8]) -> Result<&mut Self> {
+        self.set(col, Value::Bytes(val))
+    }
+
+    // also for geometries, as WKB
+    pub fn set_bytes(&mut self, col: impl Column, val: &'r [u8]) -> Result<&mut Self> {
+        self.set(col, Value::Bytes(val))
+    }
+
+    // nothing is pushed unless the whole row is acceptable
+    pub fn commit(self) -> Result<()> {
+        self.table.commit_row(self.values)
+    }
+}
@@ -0,0 +1,64 @@
+// single values, pushed to whichever typed push_* suits them; see Table::push
+
+use anyhow::Result;
+
+use crate::{Kind, Table};
+
+pub trait IntoScalar {
+    fn push_to(self, table: &mut Table, i: usize) -> Result<()>;
+}
+
+macro_rules! primitive {
+    ($($t:ty),*) => {
+        $(
+            impl IntoScalar for $t {
+                fn push_to(self, table: &mut Table, i: usize) -> Result<()> {
+                    table.push_primitive(i, Some(self))
+                }
+            }
+        )*
+    };
+}
+
+primitive!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+// the unscaled value, for decimal columns
+impl IntoScalar for i128 {
+    fn push_to(self, table: &mut Table, i: usize) -> Result<()> {
+        match table.schema()[i].kind {
+            Kind::Decimal { .. } => table.push_decimal(i, Some(self)),
+            _ => table.push_primitive(i, Some(self)),
+        }
+    }
+}
+
+impl IntoScalar for bool {
+    fn push_to(self, table: &mut Table, i: usize) -> Result<()> {
+        table.push_bool(i, Some(self))
+    }
+}
+
+impl IntoScalar for &str {
+    fn push_to(self, table: &mut Table, i: usize) -> Result<()> {
+        table.push_str(i, Some(self))
+    }
+}
+
+// to the fixed-size kinds too, which check the length
+impl IntoScalar for &[u8] {
+    fn push_to(self, table: &mut Table, i: usize) -> Result<()> {
+        match table.schema()[i].kind {
+            Kind::Uuid | Kind::Ipv4 | Kind::Ipv6 | Kind::FixedBytes(_) => {
+                table.push_fsb(i, Some(self))
+            }
+            _ => table.push_bytes(i, Some(self)),
+        }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl IntoScalar for uuid::Uuid {
+    fn push_to(self, table: &mut Table, i: usize) -> Result<()> {
+        table.push_uuid(i, Some(self))
+    }
+}
@@ -0,0 +1,475 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use arrow2::datatypes::{DataType, Schema};
+use arrow2::io::ipc::write::{default_ipc_fields, schema_to_bytes};
+use arrow2::io::parquet::write::{
+    BrotliLevel, CompressionOptions, Encoding, GzipLevel, KeyValue, ParquetType, SchemaDescriptor,
+    ZstdLevel,
+};
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use parquet2::schema::types::PrimitiveLogicalType;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{Kind, TableField};
+
+pub fn to_arrow_schema(fields: &[TableField]) -> Schema {
+    Schema::from(
+        fields
+            .iter()
+            .map(|f| f.to_arrow_field())
+            .collect::<Vec<_>>(),
+    )
+}
+
+// as arrow2's, plus the logical types which it can't know our kinds have, however deeply nested
+pub(crate) fn to_parquet_schema(fields: &[TableField], arrow: &Schema) -> Result<SchemaDescriptor> {
+    let schema = arrow2::io::parquet::write::to_parquet_schema(arrow)?;
+    let columns = schema
+        .fields()
+        .iter()
+        .zip(fields)
+        .map(|(column, field)| {
+            let mut column = column.clone();
+            annotate(&mut column, &field.kind);
+            column
+        })
+        .collect();
+    Ok(SchemaDescriptor::new(schema.name().to_string(), columns))
+}
+
+// arrow2 writes a list as list.list.element, and a map as map.map.entries.(key, value)
+fn annotate(column: &mut ParquetType, kind: &Kind) {
+    let fields = match column {
+        ParquetType::PrimitiveType(primitive) => {
+            match kind {
+                Kind::Uuid => primitive.logical_type = Some(PrimitiveLogicalType::Uuid),
+                Kind::Json => primitive.logical_type = Some(PrimitiveLogicalType::Json),
+                _ => (),
+            }
+            return;
+        }
+        ParquetType::GroupType { fields, .. } => fields,
+    };
+    match (kind, fields.as_mut_slice()) {
+        (Kind::Struct(children), fields) => {
+            for (column, child) in fields.iter_mut().zip(children) {
+                annotate(column, &child.kind);
+            }
+        }
+        (Kind::List(item), [ParquetType::GroupType { fields, .. }]) => {
+            if let [element] = fields.as_mut_slice() {
+                annotate(element, item);
+            }
+        }
+        (Kind::Map(key, value), [ParquetType::GroupType { fields, .. }]) => {
+            if let [ParquetType::GroupType { fields, .. }] = fields.as_mut_slice() {
+                if let [key_column, value_column] = fields.as_mut_slice() {
+                    annotate(key_column, key);
+                    annotate(value_column, value);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+// the other way: the kinds which only the parquet schema marks, so arrow2 reads as their storage,
+// including those inside a nested column
+pub(crate) fn annotated_kind(
+    schema: &SchemaDescriptor,
+    name: &str,
+    data_type: &DataType,
+) -> Option<Kind> {
+    let column = schema
+        .fields()
+        .iter()
+        .find(|column| column.name() == name)?;
+    let mut kind = Kind::from_arrow(data_type).ok()?;
+    annotated(column, &mut kind).then_some(kind)
+}
+
+// as annotate, in reverse; whether anything was marked
+fn annotated(column: &ParquetType, kind: &mut Kind) -> bool {
+    let fields = match column {
+        ParquetType::PrimitiveType(primitive) => {
+            let marked = match (primitive.logical_type, &*kind) {
+                (Some(PrimitiveLogicalType::Uuid), Kind::FixedBytes(16)) => Kind::Uuid,
+                (Some(PrimitiveLogicalType::Json), Kind::String) => Kind::Json,
+                _ => return false,
+            };
+            *kind = marked;
+            return true;
+        }
+        ParquetType::GroupType { fields, .. } => fields,
+    };
+    match (kind, fields.as_slice()) {
+        (Kind::Struct(children), fields) => fields
+            .iter()
+            .zip(children)
+            .fold(false, |any, (column, child)| {
+                annotated(column, &mut child.kind) | any
+            }),
+        (Kind::List(item), [ParquetType::GroupType { fields, .. }]) => match fields.as_slice() {
+            [element] => annotated(element, item),
+            _ => false,
+        },
+        (Kind::Map(key, value), [ParquetType::GroupType { fields, .. }]) => {
+            match fields.as_slice() {
+                [ParquetType::GroupType { fields, .. }] => match fields.as_slice() {
+                    [key_column, value_column] => {
+                        annotated(key_column, key) | annotated(value_column, value)
+                    }
+                    _ => false,
+                },
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+pub fn from_arrow_schema(schema: &Schema) -> Result<Vec<TableField>> {
+    schema
+        .fields
+        .iter()
+        .map(|f| {
+            TableField::from_arrow_field(f)
+                .with_context(|| anyhow!("converting field {:?}", f.name))
+        })
+        .collect()
+}
+
+// e.g. "id:i64, name:string?, created:timestamp_s"; a trailing ? makes the field nullable
+pub fn parse_schema(schema: &str) -> Result<Vec<TableField>> {
+    // commas inside brackets, as in timestamp[s,UTC], don't separate fields
+    let mut depth = 0i32;
+    schema
+        .split(|c| {
+            match c {
+                '[' | '(' | '<' => depth += 1,
+                ']' | ')' | '>' => depth -= 1,
+                _ => (),
+            }
+            c == ',' && depth == 0
+        })
+        .filter(|field| !field.trim().is_empty())
+        .map(|field| -> Result<TableField> {
+            let (name, kind) = field
+                .split_once(':')
+                .ok_or_else(|| anyhow!("expected name:kind, not {:?}", field.trim()))?;
+            let kind = kind.trim();
+            let (kind, nullable) = match kind.strip_suffix('?') {
+                Some(kind) => (kind, true),
+                None => (kind, false),
+            };
+            Ok(TableField::new(name.trim(), kind.parse()?, nullable))
+        })
+        .collect()
+}
+
+// how names are compared when looking up fields; the default is exact
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NameMatching {
+    pub case_insensitive: bool,
+    pub trim: bool,
+    // unicode normalisation form C, so composed and decomposed accents match
+    pub nfc: bool,
+}
+
+impl NameMatching {
+    pub fn normalize<'s>(&self, name: &'s str) -> Cow<'s, str> {
+        let mut name = Cow::Borrowed(name);
+        if self.trim {
+            name = match name {
+                Cow::Borrowed(name) => Cow::Borrowed(name.trim()),
+                Cow::Owned(name) => Cow::Owned(name.trim().to_string()),
+            };
+        }
+        if self.nfc {
+            name = Cow::Owned(name.nfc().collect());
+        }
+        if self.case_insensitive {
+            name = Cow::Owned(name.to_lowercase());
+        }
+        name
+    }
+
+    pub fn matches(&self, left: &str, right: &str) -> bool {
+        self.normalize(left) == self.normalize(right)
+    }
+}
+
+// find_field would otherwise quietly pick the first of any duplicates
+pub(crate) fn check_names(fields: &[TableField], matching: NameMatching) -> Result<()> {
+    let mut seen = HashSet::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        ensure!(!field.name.is_empty(), "field {} has an empty name", i);
+        ensure!(
+            seen.insert(matching.normalize(&field.name)),
+            "field {} has the same name as an earlier field: {:?}",
+            i,
+            field.name
+        );
+    }
+    Ok(())
+}
+
+// the footer entry arrow readers use to recover the exact types, as arrow2's FileWriter writes it
+pub(crate) fn arrow_schema_key_value(schema: &Schema) -> KeyValue {
+    let serialized = schema_to_bytes(schema, &default_ipc_fields(&schema.fields));
+
+    // the legacy ipc framing: continuation marker, then length
+    let mut framed = Vec::with_capacity(serialized.len() + 8);
+    framed.extend_from_slice(&[255u8, 255, 255, 255]);
+    framed.extend_from_slice(&(serialized.len() as u32).to_le_bytes());
+    framed.extend_from_slice(&serialized);
+
+    KeyValue {
+        key: "ARROW:schema".to_string(),
+        value: Some(general_purpose::STANDARD.encode(&framed)),
+    }
+}
+
+pub fn encoding_name(encoding: Encoding) -> &'static str {
+    match encoding {
+        Encoding::Plain => "plain",
+        Encoding::PlainDictionary => "plain_dictionary",
+        Encoding::Rle => "rle",
+        Encoding::BitPacked => "bit_packed",
+        Encoding::DeltaBinaryPacked => "delta_binary_packed",
+        Encoding::DeltaLengthByteArray => "delta_length_byte_array",
+        Encoding::DeltaByteArray => "delta_byte_array",
+        Encoding::RleDictionary => "rle_dictionary",
+        Encoding::ByteStreamSplit => "byte_stream_split",
+    }
+}
+
+pub fn encoding_from_name(name: &str) -> Result<Encoding> {
+    Ok(match name {
+        "plain" => Encoding::Plain,
+        "plain_dictionary" => Encoding::PlainDictionary,
+        "rle" => Encoding::Rle,
+        "bit_packed" => Encoding::BitPacked,
+        "delta_binary_packed" => Encoding::DeltaBinaryPacked,
+        "delta_length_byte_array" => Encoding::DeltaLengthByteArray,
+        "delta_byte_array" => Encoding::DeltaByteArray,
+        "rle_dictionary" => Encoding::RleDictionary,
+        "byte_stream_split" => Encoding::ByteStreamSplit,
+        other => bail!("unrecognised encoding {:?}", other),
+    })
+}
+
+// e.g. snappy, or zstd(3) with a level
+pub fn compression_name(compression: CompressionOptions) -> String {
+    match compression {
+        CompressionOptions::Uncompressed => "uncompressed".to_string(),
+        CompressionOptions::Snappy => "snappy".to_string(),
+        CompressionOptions::Gzip(None) => "gzip".to_string(),
+        CompressionOptions::Gzip(Some(level)) => format!("gzip({})", level.compression_level()),
+        CompressionOptions::Lzo => "lzo".to_string(),
+        CompressionOptions::Brotli(None) => "brotli".to_string(),
+        CompressionOptions::Brotli(Some(level)) => {
+            format!("brotli({})", level.compression_level())
+        }
+        CompressionOptions::Lz4 => "lz4".to_string(),
+        CompressionOptions::Zstd(None) => "zstd".to_string(),
+        CompressionOptions::Zstd(Some(level)) => format!("zstd({})", level.compression_level()),
+        CompressionOptions::Lz4Raw => "lz4_raw".to_string(),
+    }
+}
+
+pub fn compression_from_name(name: &str) -> Result<CompressionOptions> {
+    let (codec, level) = match name.split_once('(') {
+        Some((codec, level)) => {
+            let level = level
+                .strip_suffix(')')
+                .ok_or_else(|| anyhow!("unclosed level in compression {:?}", name))?;
+            (codec.trim(), Some(level.trim()))
+        }
+        None => (name.trim(), None),
+    };
+    let bad_level = || anyhow!("level of compression {:?}", name);
+    Ok(match (codec, level) {
+        ("uncompressed", None) => CompressionOptions::Uncompressed,
+        ("snappy", None) => CompressionOptions::Snappy,
+        ("lzo", None) => CompressionOptions::Lzo,
+        ("lz4", None) => CompressionOptions::Lz4,
+        ("lz4_raw", None) => CompressionOptions::Lz4Raw,
+        ("gzip", level) => CompressionOptions::Gzip(
+            parse_level(level, GzipLevel::try_new).with_context(bad_level)?,
+        ),
+        ("brotli", level) => CompressionOptions::Brotli(
+            parse_level(level, BrotliLevel::try_new).with_context(bad_level)?,
+        ),
+        ("zstd", level) => CompressionOptions::Zstd(
+            parse_level(level, ZstdLevel::try_new).with_context(bad_level)?,
+        ),
+        _ => bail!("unrecognised compression {:?}", name),
+    })
+}
+
+fn parse_level<T: FromStr, L>(
+    level: Option<&str>,
+    try_new: impl FnOnce(T) -> parquet2::error::Result<L>,
+) -> Result<Option<L>> {
+    level
+        .map(|level| -> Result<L> {
+            let level = level
+                .parse()
+                .map_err(|_| anyhow!("{:?} isn't a number", level))?;
+            Ok(try_new(level)?)
+        })
+        .transpose()
+}
+
+#[cfg(feature = "serde")]
+pub use self::serde_impl::{load_schema, schema_from_json, schema_from_yaml};
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::Path;
+
+    use anyhow::{anyhow, bail, Context, Result};
+    use arrow2::datatypes::Metadata;
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{compression_from_name, compression_name, encoding_from_name, encoding_name};
+    use crate::constraint::{Constraint, Violation};
+    use crate::{DefaultValue, Kind, TableField};
+
+    // the encoding is optional in the file, defaulting based on the kind
+    #[derive(Serialize, Deserialize)]
+    struct RawField {
+        name: String,
+        kind: Kind,
+        #[serde(default)]
+        nullable: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encoding: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<String>,
+        #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+        metadata: Metadata,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        constraint: Option<RawConstraint>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        statistics: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<DefaultValue>,
+    }
+
+    // as Constraint, but with the regex as a string
+    #[derive(Serialize, Deserialize)]
+    struct RawConstraint {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_len: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        allowed: Option<HashSet<String>>,
+        #[serde(default)]
+        on_violation: Violation,
+    }
+
+    impl From<&Constraint> for RawConstraint {
+        fn from(c: &Constraint) -> Self {
+            RawConstraint {
+                min: c.min,
+                max: c.max,
+                max_len: c.max_len,
+                pattern: c.pattern.as_ref().map(|p| p.as_str().to_string()),
+                allowed: c.allowed.clone(),
+                on_violation: c.on_violation,
+            }
+        }
+    }
+
+    impl TryFrom<RawConstraint> for Constraint {
+        type Error = regex::Error;
+
+        fn try_from(c: RawConstraint) -> Result<Self, Self::Error> {
+            Ok(Constraint {
+                min: c.min,
+                max: c.max,
+                max_len: c.max_len,
+                pattern: c.pattern.as_deref().map(Regex::new).transpose()?,
+                allowed: c.allowed,
+                on_violation: c.on_violation,
+            })
+        }
+    }
+
+    impl Serialize for TableField {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            RawField {
+                name: self.name.to_string(),
+                kind: self.kind.clone(),
+                nullable: self.nullable,
+                encoding: Some(encoding_name(self.encoding).to_string()),
+                compression: self.compression.map(compression_name),
+                metadata: self.metadata.clone(),
+                constraint: self.constraint.as_ref().map(RawConstraint::from),
+                statistics: (!self.statistics).then_some(false),
+                default: self.default.clone(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TableField {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let raw = RawField::deserialize(deserializer)?;
+            let mut field = TableField::new(raw.name, raw.kind, raw.nullable);
+            field.metadata = raw.metadata;
+            field.constraint = raw
+                .constraint
+                .map(Constraint::try_from)
+                .transpose()
+                .map_err(serde::de::Error::custom)?;
+            field.statistics = raw.statistics.unwrap_or(true);
+            field.default = raw.default;
+            if let Some(encoding) = raw.encoding {
+                field.encoding = encoding_from_name(&encoding).map_err(serde::de::Error::custom)?;
+            }
+            field.compression = raw
+                .compression
+                .as_deref()
+                .map(compression_from_name)
+                .transpose()
+                .map_err(serde::de::Error::custom)?;
+            Ok(field)
+        }
+    }
+
+    pub fn schema_from_json(json: &str) -> Result<Vec<TableField>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn schema_from_yaml(yaml: &str) -> Result<Vec<TableField>> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    // a list of fields, in json or yaml, based on the extension
+    pub fn load_schema(path: impl AsRef<Path>) -> Result<Vec<TableField>> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).with_context(|| anyhow!("reading schema {:?}", path))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => schema_from_json(&contents),
+            Some("yaml") | Some("yml") => schema_from_yaml(&contents),
+            _ => bail!("expected a .json or .yaml schema, not {:?}", path),
+        }
+        .with_context(|| anyhow!("loading schema {:?}", path))
+    }
+}
@@ -0,0 +1,438 @@
+// a Packer which writes its rows sorted by some key columns, however many there are: batches
+// which get too big are sorted and spilled to temporary files, which are then merged
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use arrow2::array::{
+    Array, BinaryArray, BooleanArray, DictionaryArray, FixedSizeBinaryArray, PrimitiveArray,
+    Utf8Array,
+};
+use arrow2::chunk::Chunk;
+use arrow2::io::parquet::read;
+use arrow2::io::parquet::write::Encoding;
+use log::info;
+
+use crate::copy::{gather, interleave};
+use crate::spool::temp_path;
+use crate::{Kind, Packer, PackerOptions, Table, TableField, WriterOptions};
+
+// the merge holds a chunk of every run at once, so the runs are written, and read back, in bits
+const RUN_GROUP_ROWS: usize = 64 * 1024;
+const RUN_CHUNK_ROWS: usize = 4 * 1024;
+// the sorted rows are copied to the output this many at a time, whole columns at once
+const COPY_ROWS: usize = 4 * 1024;
+
+#[derive(Clone)]
+pub struct SortOptions {
+    // ascending, nulls first
+    pub keys: Vec<String>,
+    // spill once the buffered rows are estimated to be this big
+    pub memory_limit: usize,
+    // defaults to the system's temporary directory
+    pub spill_dir: Option<PathBuf>,
+    pub packer: PackerOptions,
+}
+
+pub struct SortingPacker<W: Write> {
+    out: Option<Packer<W>>,
+    table: Table,
+    schema: Box<[TableField]>,
+    keys: Vec<usize>,
+    runs: Vec<PathBuf>,
+    options: SortOptions,
+}
+
+impl<W: Write + Send + 'static> SortingPacker<W> {
+    pub fn new(inner: W, schema: &[TableField], keys: &[&str]) -> Result<Self> {
+        let options = SortOptions {
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            memory_limit: 512 * 1024 * 1024,
+            spill_dir: None,
+            packer: PackerOptions::default(),
+        };
+        Self::with_options(inner, schema, options)
+    }
+
+    pub fn with_options(inner: W, schema: &[TableField], options: SortOptions) -> Result<Self> {
+        let out = Packer::with_options(inner, schema, options.packer.clone())?;
+        let keys = options
+            .keys
+            .iter()
+            .map(|key| {
+                let (i, field) = out
+                    .find_field(key)
+                    .ok_or_else(|| anyhow!("sort key {:?} isn't in the schema", key))?;
+                if matches!(field.kind, Kind::List(_) | Kind::Struct(_) | Kind::Map(..)) {
+                    bail!("can't sort by {:?}, a {}", key, field.kind);
+                }
+                Ok(i)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut table = Table::from_fields(schema, 0);
+        table.set_coercion(options.packer.coercion);
+        table.set_name_matching(options.packer.writer.name_matching);
+
+        Ok(Self {
+            out: Some(out),
+            table,
+            schema: schema.to_vec().into_boxed_slice(),
+            keys,
+            runs: Vec::new(),
+            options,
+        })
+    }
+
+    pub fn table(&mut self) -> &mut Table {
+        &mut self.table
+    }
+
+    // as Packer::consider_flushing, but spills instead
+    pub fn consider_spilling(&mut self) -> Result<()> {
+        self.table.check_consistent()?;
+        if self.table.recent_mem_estimate() > self.options.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        if self.table.rows() == 0 {
+            return Ok(());
+        }
+
+        let path = temp_path(self.options.spill_dir.as_deref(), "sort");
+        info!("spilling {} rows to {:?}", self.table.rows(), path);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| anyhow!("creating {:?}", path))?;
+        // tracked before writing, so it's cleaned up if this fails
+        self.runs.push(path);
+
+        let options = PackerOptions {
+            writer: WriterOptions {
+                single_threaded: true,
+                ..Default::default()
+            },
+            coercion: self.options.packer.coercion,
+            max_rows_per_group: Some(RUN_GROUP_ROWS),
+            ..Default::default()
+        };
+        // the runs are read back by arrow2, which can't read byte_stream_split
+        let schema = self
+            .schema
+            .iter()
+            .map(|field| field.clone().encoding(Encoding::Plain))
+            .collect::<Vec<_>>();
+        let mut run = Packer::with_options(BufWriter::new(file), &schema, options)?;
+        let batch = take_sorted(&mut self.table, &self.keys)?;
+        copy_rows(&mut run, &batch, Some(RUN_GROUP_ROWS))?;
+        run.finish()?.flush()?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        let mut out = self.out.take().expect("only taken by finish");
+
+        if self.runs.is_empty() {
+            let batch = take_sorted(&mut self.table, &self.keys)?;
+            copy_rows(&mut out, &batch, self.options.packer.max_rows_per_group)?;
+            return out.finish();
+        }
+
+        self.spill()?;
+        info!("merging {} runs", self.runs.len());
+
+        let mut runs = self
+            .runs
+            .iter()
+            .map(|path| Run::open(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        // the run index breaks ties, so equal keys stay in the order they were pushed
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (i, run) in runs.iter().enumerate() {
+            if let Some(chunk) = &run.chunk {
+                heap.push(Reverse((row_key(chunk.arrays(), &self.keys, run.row)?, i)));
+            }
+        }
+
+        let max_rows_per_group = self.options.packer.max_rows_per_group;
+        let mut merged = Merged::default();
+        // which of merged's chunks each run's current chunk is, once a row's been taken from it
+        let mut taken = vec![None; runs.len()];
+        while let Some(Reverse((_, i))) = heap.pop() {
+            let run = &mut runs[i];
+            let chunk = run.chunk.as_ref().expect("only queued with a chunk");
+            let from = *taken[i].get_or_insert_with(|| {
+                merged.chunks.push(chunk.clone());
+                merged.chunks.len() - 1
+            });
+            merged.rows.push((from, run.row));
+
+            run.advance()?;
+            if run.row == 0 {
+                taken[i] = None;
+            }
+            if let Some(chunk) = &run.chunk {
+                heap.push(Reverse((row_key(chunk.arrays(), &self.keys, run.row)?, i)));
+            }
+
+            if merged.rows.len() >= COPY_ROWS {
+                merged.copy_to(&mut out, max_rows_per_group)?;
+                taken.fill(None);
+            }
+        }
+        merged.copy_to(&mut out, max_rows_per_group)?;
+
+        out.finish()
+    }
+}
+
+impl<W: Write> Drop for SortingPacker<W> {
+    fn drop(&mut self) {
+        for path in &self.runs {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// the buffered rows, taken from the table, sorted
+fn take_sorted(table: &mut Table, keys: &[usize]) -> Result<Vec<Box<dyn Array>>> {
+    table.finish_bulk_push()?;
+    let arrays = table
+        .take_batch()
+        .into_iter()
+        .map(|arr| arr.to_boxed())
+        .collect::<Vec<_>>();
+    let order = sort_order(&arrays, keys)?;
+    Ok(arrays
+        .iter()
+        .map(|arr| gather(arr.as_ref(), &order))
+        .collect())
+}
+
+// the rows, by the key columns
+pub(crate) fn sort_order(arrays: &[Box<dyn Array>], keys: &[usize]) -> Result<Vec<usize>> {
+    let rows = arrays.first().map(|arr| arr.len()).unwrap_or_default();
+
+    let mut keyed = (0..rows)
+        .map(|row| Ok((row_key(arrays, keys, row)?, row)))
+        .collect::<Result<Vec<_>>>()?;
+    // stable, so equal keys stay in the order they were pushed
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(keyed.into_iter().map(|(_, row)| row).collect())
+}
+
+// a slice at a time, so the row groups are cut where consider_flushing would have cut them, had
+// the rows been pushed one by one
+fn copy_rows<W: Write + Send + 'static>(
+    out: &mut Packer<W>,
+    arrays: &[Box<dyn Array>],
+    max_rows_per_group: Option<usize>,
+) -> Result<()> {
+    let rows = arrays.first().map(|arr| arr.len()).unwrap_or_default();
+    let mut at = 0;
+    while at < rows {
+        let room = max_rows_per_group.map_or(COPY_ROWS, |max| {
+            max.saturating_sub(out.table().rows()).max(1)
+        });
+        let len = room.min(COPY_ROWS).min(rows - at);
+        let slice = arrays
+            .iter()
+            .map(|arr| arr.sliced(at, len))
+            .collect::<Vec<_>>();
+        out.table().extend_from(&slice)?;
+        out.consider_flushing()?;
+        at += len;
+    }
+    Ok(())
+}
+
+// rows popped off the merge, waiting to be copied to the output together
+#[derive(Default)]
+struct Merged {
+    // every chunk the rows came from; they share the runs' buffers
+    chunks: Vec<Chunk<Box<dyn Array>>>,
+    // (which chunk, which row)
+    rows: Vec<(usize, usize)>,
+}
+
+impl Merged {
+    fn copy_to<W: Write + Send + 'static>(
+        &mut self,
+        out: &mut Packer<W>,
+        max_rows_per_group: Option<usize>,
+    ) -> Result<()> {
+        let Some(first) = self.chunks.first() else {
+            return Ok(());
+        };
+        let arrays = (0..first.arrays().len())
+            .map(|col| {
+                let arrs = self
+                    .chunks
+                    .iter()
+                    .map(|chunk| chunk.arrays()[col].as_ref())
+                    .collect::<Vec<_>>();
+                interleave(&arrs, &self.rows)
+            })
+            .collect::<Vec<_>>();
+        self.chunks.clear();
+        self.rows.clear();
+        copy_rows(out, &arrays, max_rows_per_group)
+    }
+}
+
+// a spilled, sorted file, being read back a row group at a time
+struct Run {
+    reader: read::FileReader<File>,
+    chunk: Option<Chunk<Box<dyn Array>>>,
+    row: usize,
+}
+
+impl Run {
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).with_context(|| anyhow!("opening {:?}", path))?;
+        let metadata = read::read_metadata(&mut file)?;
+        let schema = read::infer_schema(&metadata)?;
+        let reader = read::FileReader::new(
+            file,
+            metadata.row_groups,
+            schema,
+            Some(RUN_CHUNK_ROWS),
+            None,
+            None,
+        );
+        let mut run = Run {
+            reader,
+            chunk: None,
+            row: 0,
+        };
+        run.next_chunk()?;
+        Ok(run)
+    }
+
+    fn next_chunk(&mut self) -> Result<()> {
+        self.row = 0;
+        self.chunk = None;
+        for chunk in self.reader.by_ref() {
+            let chunk = chunk?;
+            if !chunk.is_empty() {
+                self.chunk = Some(chunk);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        self.row += 1;
+        match &self.chunk {
+            Some(chunk) if self.row < chunk.len() => Ok(()),
+            _ => self.next_chunk(),
+        }
+    }
+}
+
+// the value of a key column, owned, so rows from different runs can be compared
+#[derive(Debug)]
+enum Key {
+    Null,
+    Bool(bool),
+    // every integer, including u64s, and decimals (unscaled, as a column has one scale)
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Key::Null, Key::Null) => Ordering::Equal,
+            (Key::Null, _) => Ordering::Less,
+            (_, Key::Null) => Ordering::Greater,
+            (Key::Bool(a), Key::Bool(b)) => a.cmp(b),
+            (Key::Int(a), Key::Int(b)) => a.cmp(b),
+            (Key::Float(a), Key::Float(b)) => a.total_cmp(b),
+            (Key::Str(a), Key::Str(b)) => a.cmp(b),
+            (Key::Bytes(a), Key::Bytes(b)) => a.cmp(b),
+            (a, b) => unreachable!("a column has one type: {:?} vs. {:?}", a, b),
+        }
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Key {}
+
+fn row_key(arrays: &[Box<dyn Array>], keys: &[usize], row: usize) -> Result<Vec<Key>> {
+    keys.iter()
+        .map(|&col| key(arrays[col].as_ref(), row))
+        .collect()
+}
+
+fn key(array: &dyn Array, row: usize) -> Result<Key> {
+    if array.is_null(row) {
+        return Ok(Key::Null);
+    }
+
+    let any = array.as_any();
+    Ok(if let Some(arr) = any.downcast_ref::<BooleanArray>() {
+        Key::Bool(arr.value(row))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<u8>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<u16>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<u32>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<u64>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i8>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i16>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i32>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i64>>() {
+        Key::Int(i128::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<i128>>() {
+        Key::Int(arr.value(row))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<f32>>() {
+        Key::Float(f64::from(arr.value(row)))
+    } else if let Some(arr) = any.downcast_ref::<PrimitiveArray<f64>>() {
+        Key::Float(arr.value(row))
+    } else if let Some(arr) = any.downcast_ref::<Utf8Array<i32>>() {
+        Key::Str(arr.value(row).to_string())
+    } else if let Some(arr) = any.downcast_ref::<Utf8Array<i64>>() {
+        Key::Str(arr.value(row).to_string())
+    } else if let Some(arr) = any.downcast_ref::<DictionaryArray<u32>>() {
+        // by the value, not the key, which is only its position in the enum
+        return key(arr.values().as_ref(), arr.key_value(row));
+    } else if let Some(arr) = any.downcast_ref::<FixedSizeBinaryArray>() {
+        Key::Bytes(arr.value(row).to_vec())
+    } else if let Some(arr) = any.downcast_ref::<BinaryArray<i32>>() {
+        Key::Bytes(arr.value(row).to_vec())
+    } else {
+        bail!("can't sort by {:?}", array.data_type())
+    })
+}
@@ -0,0 +1,125 @@
+// byte_stream_split, which arrow2 can neither write nor read: it's plain, but with the values'
+// bytes transposed, so all the first bytes come first, then all the second bytes, and so on.
+// Columns are encoded as plain, then split; and unsplit before arrow2 decodes them
+
+use anyhow::{bail, ensure, Result};
+use arrow2::datatypes::DataType;
+use arrow2::io::parquet::read::{DataPageHeader, PhysicalType};
+use arrow2::io::parquet::write::{Encoding, FallibleStreamingIterator, Page};
+use parquet2::error::Error as ParquetError;
+use parquet2::page::DataPage;
+
+pub(crate) fn can_split(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Float32 | DataType::Float64)
+}
+
+pub(crate) fn split(page: Page) -> Result<Page> {
+    match page {
+        Page::Data(page) => Ok(Page::Data(transpose(&page, Encoding::ByteStreamSplit)?)),
+        dict => Ok(dict),
+    }
+}
+
+fn transpose(page: &DataPage, to: Encoding) -> Result<DataPage> {
+    let width = match page.descriptor.primitive_type.physical_type {
+        PhysicalType::Float => 4,
+        PhysicalType::Double => 8,
+        other => bail!("can't byte_stream_split {:?}", other),
+    };
+    let levels = levels_len(page)?;
+    let buffer = page.buffer();
+    ensure!(buffer.len() >= levels, "levels run past the page");
+    let values = &buffer[levels..];
+    ensure!(
+        values.len().is_multiple_of(width),
+        "values aren't a whole number of floats"
+    );
+    let n = values.len() / width;
+
+    let mut out = Vec::with_capacity(buffer.len());
+    out.extend_from_slice(&buffer[..levels]);
+    out.resize(buffer.len(), 0);
+    let transposed = &mut out[levels..];
+    for i in 0..n {
+        for b in 0..width {
+            match to {
+                Encoding::ByteStreamSplit => transposed[b * n + i] = values[i * width + b],
+                _ => transposed[i * width + b] = values[b * n + i],
+            }
+        }
+    }
+
+    let mut header = page.header().clone();
+    match &mut header {
+        DataPageHeader::V1(header) => header.encoding = to.into(),
+        DataPageHeader::V2(header) => header.encoding = to.into(),
+    }
+    let mut transposed = DataPage::new(header, out, page.descriptor.clone(), None);
+    transposed.selected_rows = page.selected_rows.clone();
+    Ok(transposed)
+}
+
+// v2 pages have the lengths in the header; v1 pages prefix each of the levels with theirs
+fn levels_len(page: &DataPage) -> Result<usize> {
+    Ok(match page.header() {
+        DataPageHeader::V2(header) => usize::try_from(
+            header.repetition_levels_byte_length + header.definition_levels_byte_length,
+        )?,
+        DataPageHeader::V1(_) => {
+            let buffer = page.buffer();
+            let mut at = 0;
+            for max_level in [page.descriptor.max_rep_level, page.descriptor.max_def_level] {
+                if max_level == 0 {
+                    continue;
+                }
+                let len: [u8; 4] = match buffer.get(at..at + 4) {
+                    Some(len) => len.try_into()?,
+                    None => bail!("levels run past the page"),
+                };
+                at += 4 + usize::try_from(u32::from_le_bytes(len))?;
+            }
+            at
+        }
+    })
+}
+
+// wraps the (decompressed) pages of a column, to undo the split
+pub(crate) struct Unsplit<I> {
+    inner: I,
+    current: Option<Page>,
+}
+
+impl<I> Unsplit<I> {
+    pub(crate) fn new(inner: I) -> Self {
+        Unsplit {
+            inner,
+            current: None,
+        }
+    }
+}
+
+impl<I> FallibleStreamingIterator for Unsplit<I>
+where
+    I: FallibleStreamingIterator<Item = Page, Error = ParquetError>,
+{
+    type Item = Page;
+    type Error = ParquetError;
+
+    fn advance(&mut self) -> Result<(), ParquetError> {
+        self.inner.advance()?;
+        self.current = match self.inner.get() {
+            Some(Page::Data(page)) if page.encoding() == Encoding::ByteStreamSplit => {
+                Some(Page::Data(
+                    transpose(page, Encoding::Plain)
+                        .map_err(|e| ParquetError::OutOfSpec(e.to_string()))?,
+                ))
+            }
+            _ => None,
+        };
+        Ok(())
+    }
+
+    fn get(&self) -> Option<&Page> {
+        self.current.as_ref().or_else(|| self.inner.get())
+    }
+}
@@ -0,0 +1,70 @@
+// temporary files, which are removed when they're dropped
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{anyhow, Context, Result};
+
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+// unique within the process, and between processes (unless pids are shared, e.g. in containers
+// writing to the same directory, in which case pass a different dir); defaults to the system's
+pub(crate) fn temp_path(dir: Option<&Path>, purpose: &str) -> PathBuf {
+    let dir = dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join(format!(
+        "pack-it-{}-{}-{}.parquet",
+        purpose,
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+pub(crate) struct Spooled {
+    path: PathBuf,
+    file: File,
+}
+
+impl Spooled {
+    pub(crate) fn create(dir: Option<&Path>, purpose: &str) -> Result<Self> {
+        let path = temp_path(dir, purpose);
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| anyhow!("creating {:?}", path))?;
+        Ok(Spooled { path, file })
+    }
+}
+
+impl Read for Spooled {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for Spooled {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for Spooled {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for Spooled {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -1,119 +1,869 @@
 use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::IpAddr;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::coerce::{to_f32, to_float, to_int, Coercion};
+use crate::constraint::Constraint;
+use crate::copy::gather;
+use crate::date::{days_from_civil, MILLIS_PER_DAY};
+use crate::dictionary::{enum_type, MutableEnumArray};
+use crate::geo::{geometry_type, point_wkb};
+use crate::nested::{map_type, ChildValue, MutableMapArray, StructRow};
+use crate::row::{Checked, RowBuilder, RowView, Value};
+use crate::scalar::IntoScalar;
+use crate::schema::{parse_schema, NameMatching};
+use crate::sort::sort_order;
 use crate::MemUsage;
-use anyhow::{anyhow, bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use arrow2::array::new_null_array;
 use arrow2::array::{
-    Array, MutableArray, MutableBooleanArray, MutableFixedSizeBinaryArray, MutablePrimitiveArray,
+    Array, MutableArray, MutableBinaryArray, MutableBooleanArray, MutableFixedSizeBinaryArray,
+    MutableListArray, MutableNullArray, MutablePrimitiveArray, MutableStructArray,
     MutableUtf8Array, TryPush,
 };
-use arrow2::datatypes::{DataType, TimeUnit};
-use arrow2::io::parquet::write::Encoding;
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field as ArrowField, Metadata, Schema, TimeUnit};
+use arrow2::io::ipc::read::{read_stream_metadata, StreamReader, StreamState};
+use arrow2::io::ipc::write::{StreamWriter, WriteOptions as IpcWriteOptions};
+use arrow2::io::parquet::write::{CompressionOptions, Encoding};
 use arrow2::types::NativeType;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TableField {
     pub name: String,
     pub kind: Kind,
     pub nullable: bool,
 
     pub encoding: Encoding,
+    pub metadata: Metadata,
+    pub constraint: Option<Constraint>,
+    // instead of the writer's default
+    pub compression: Option<CompressionOptions>,
+    pub statistics: bool,
+    // pushed instead of a null; not checked against the constraint
+    pub default: Option<DefaultValue>,
+}
+
+// so Kind, which has them as struct children, can be Eq; only a NaN in a constraint or default
+// would make a field unequal to itself
+impl Eq for TableField {}
+
+// integers are for any of the integer kinds (and timestamps), bytes for uuids and geometries
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum DefaultValue {
+    Bool(bool),
+    Int(i64),
+    F64(f64),
+    Str(String),
+    Bytes(Vec<u8>),
 }
 
 impl TableField {
     pub fn new(name: impl ToString, kind: Kind, nullable: bool) -> Self {
         TableField {
             name: name.to_string(),
+            encoding: kind.default_encoding(),
             kind,
             nullable,
-            encoding: kind.default_encoding(),
+            metadata: Metadata::default(),
+            constraint: None,
+            compression: None,
+            statistics: true,
+            default: None,
         }
     }
+
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn compression(mut self, compression: CompressionOptions) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn statistics(mut self, statistics: bool) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    pub fn metadata(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn constraint(mut self, constraint: Constraint) -> Self {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    pub fn default_value(mut self, default: DefaultValue) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    // the default, as the builder for this kind takes it
+    pub(crate) fn checked_default(&self) -> Result<Option<Checked<'static>>> {
+        let default = match &self.default {
+            Some(default) => default,
+            None => return Ok(None),
+        };
+        let range = || {
+            anyhow!(
+                "column {:?} is a {}, so {:?} is out of range",
+                self.name,
+                self.kind,
+                default
+            )
+        };
+        Ok(Some(match (&self.kind, default) {
+            (Kind::Bool, DefaultValue::Bool(v)) => Checked::Bool(*v),
+            (Kind::U8, DefaultValue::Int(v)) => Checked::U8(u8::try_from(*v).with_context(range)?),
+            (Kind::U16, DefaultValue::Int(v)) => {
+                Checked::U16(u16::try_from(*v).with_context(range)?)
+            }
+            (Kind::U32, DefaultValue::Int(v)) => {
+                Checked::U32(u32::try_from(*v).with_context(range)?)
+            }
+            (Kind::U64, DefaultValue::Int(v)) => {
+                Checked::U64(u64::try_from(*v).with_context(range)?)
+            }
+            (Kind::I8, DefaultValue::Int(v)) => Checked::I8(i8::try_from(*v).with_context(range)?),
+            (Kind::I16, DefaultValue::Int(v)) => {
+                Checked::I16(i16::try_from(*v).with_context(range)?)
+            }
+            (Kind::I32 | Kind::Date | Kind::TimeMillis, DefaultValue::Int(v)) => {
+                Checked::I32(i32::try_from(*v).with_context(range)?)
+            }
+            (
+                Kind::I64
+                | Kind::TimestampSecsZ
+                | Kind::Timestamp { .. }
+                | Kind::DateMillis
+                | Kind::TimeMicros
+                | Kind::Duration(_),
+                DefaultValue::Int(v),
+            ) => Checked::I64(*v),
+            (Kind::F32, DefaultValue::F64(v)) => Checked::F32(*v as f32),
+            (Kind::F32, DefaultValue::Int(v)) => Checked::F32(*v as f32),
+            (Kind::F64, DefaultValue::F64(v)) => Checked::F64(*v),
+            (Kind::F64, DefaultValue::Int(v)) => Checked::F64(*v as f64),
+            (Kind::Decimal { precision, scale }, DefaultValue::Int(v)) => {
+                let unscaled = 10i128
+                    .checked_pow(u32::try_from(*scale)?)
+                    .and_then(|factor| i128::from(*v).checked_mul(factor))
+                    .filter(|&unscaled| decimal_fits(unscaled, *precision))
+                    .ok_or_else(range)?;
+                Checked::I128(unscaled)
+            }
+            (Kind::Decimal { precision, scale }, DefaultValue::F64(v)) => {
+                let unscaled = (v * 10f64.powi(i32::try_from(*scale)?)).round();
+                let unscaled = unscaled as i128;
+                ensure!(decimal_fits(unscaled, *precision), range());
+                Checked::I128(unscaled)
+            }
+            (Kind::String | Kind::LargeString, DefaultValue::Str(v)) => {
+                Checked::Str(Cow::Owned(v.clone()))
+            }
+            (Kind::Json, DefaultValue::Str(v)) if is_json(v) => Checked::Str(Cow::Owned(v.clone())),
+            (Kind::Enum(values), DefaultValue::Str(v)) if values.contains(v) => {
+                Checked::Str(Cow::Owned(v.clone()))
+            }
+            (Kind::Uuid, DefaultValue::Bytes(v)) if v.len() == 16 => {
+                Checked::Bytes(Cow::Owned(v.clone()))
+            }
+            (Kind::FixedBytes(size), DefaultValue::Bytes(v)) if v.len() == *size => {
+                Checked::Bytes(Cow::Owned(v.clone()))
+            }
+            (Kind::Ipv4 | Kind::Ipv6, DefaultValue::Str(v)) => {
+                let addr = v
+                    .parse()
+                    .with_context(|| anyhow!("default {:?} for {:?}", v, self.name))?;
+                Checked::Bytes(Cow::Owned(ip_bytes(&self.kind, addr)?))
+            }
+            (Kind::Geometry, DefaultValue::Bytes(v)) if geometry_type(v).is_some() => {
+                Checked::Binary(Cow::Owned(v.clone()))
+            }
+            (Kind::Binary, DefaultValue::Bytes(v)) => Checked::Binary(Cow::Owned(v.clone())),
+            (kind, default) => bail!(
+                "column {:?} is a {}, which can't default to {:?}",
+                self.name,
+                kind,
+                default
+            ),
+        }))
+    }
+
+    pub fn from_arrow_field(field: &ArrowField) -> Result<Self> {
+        let mut ret = TableField::new(
+            &field.name,
+            Kind::from_arrow(&field.data_type)?,
+            field.is_nullable,
+        );
+        ret.metadata = field.metadata.clone();
+        Ok(ret)
+    }
+
+    pub fn to_arrow_field(&self) -> ArrowField {
+        ArrowField::new(&self.name, self.kind.to_arrow(), self.nullable)
+            .with_metadata(self.metadata.clone())
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Kind {
     Bool,
     Uuid,
     U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
     I32,
     I64,
+    F32,
     F64,
     String,
+    // i64 offsets, for when a row group's strings add up to more than 2GB
+    LargeString,
+    // strings, annotated as json for the readers; with the json feature, checked they parse
+    Json,
+    // only these strings, which are written as a dictionary; see push_str
+    Enum(Vec<String>),
 
     // do we want multiple types here?
     TimestampSecsZ,
+    // as written by everyone else; the timezone is as arrow has it, e.g. "+00:00" or "Europe/London"
+    Timestamp { unit: TimeUnit, tz: Option<String> },
+
+    // days since the epoch, or the same in millis (whole days); see push_date
+    Date,
+    DateMillis,
+
+    // time of day, since midnight
+    TimeMillis,
+    TimeMicros,
+
+    // elapsed time; see push_duration
+    Duration(TimeUnit),
+
+    // exact, as the unscaled value, e.g. 12345 with scale 2 is 123.45; see push_decimal
+    Decimal { precision: usize, scale: usize },
+
+    // WKB, marked as geometry for GeoParquet readers; see push_point
+    Geometry,
+
+    // arbitrary bytes, of any length; see push_bytes
+    Binary,
+    // exactly this many bytes, e.g. hashes; see push_fsb
+    FixedBytes(usize),
+    // network order, four and sixteen bytes; v4 addresses are mapped into Ipv6 columns, see push_ip
+    Ipv4,
+    Ipv6,
+    // of any other kind (the values are nullable); see push_list
+    List(Box<Kind>),
+    // named children; only their names, kinds and nullability are used; see push_struct
+    Struct(Vec<TableField>),
+    // keys (which can't be null, or nested) to values; see push_map
+    Map(Box<Kind>, Box<Kind>),
+    // always null, e.g. for a column a later schema has; nothing's pushed to it, see push_null
+    Null,
 }
 
 impl Kind {
-    pub fn array_with_capacity(self, capacity: usize) -> VarArray {
+    pub fn array_with_capacity(&self, capacity: usize) -> VarArray {
+        VarArray {
+            inner: self.builder_with_capacity(capacity),
+            kind: self.clone(),
+        }
+    }
+
+    pub(crate) fn builder_with_capacity(&self, capacity: usize) -> Box<dyn MutableArray> {
         match self {
-            Kind::Bool => VarArray::new(MutableBooleanArray::with_capacity(capacity)),
-            Kind::U8 => VarArray::new(MutablePrimitiveArray::<u8>::with_capacity(capacity)),
-            Kind::I32 => VarArray::new(MutablePrimitiveArray::<i32>::with_capacity(capacity)),
-            Kind::I64 => VarArray::new(MutablePrimitiveArray::<i64>::with_capacity(capacity)),
-            Kind::F64 => VarArray::new(MutablePrimitiveArray::<f64>::with_capacity(capacity)),
-            Kind::String => VarArray::new(MutableUtf8Array::<i32>::with_capacity(capacity)),
-            Kind::Uuid => VarArray::new(MutableFixedSizeBinaryArray::with_capacity(16, capacity)),
-            Kind::TimestampSecsZ => {
-                VarArray::new(MutablePrimitiveArray::<i64>::with_capacity(capacity))
+            Kind::Bool => Box::new(MutableBooleanArray::with_capacity(capacity)),
+            Kind::Null => Box::new(MutableNullArray::new(DataType::Null, 0)),
+            Kind::U8 => Box::new(MutablePrimitiveArray::<u8>::with_capacity(capacity)),
+            Kind::U16 => Box::new(MutablePrimitiveArray::<u16>::with_capacity(capacity)),
+            Kind::U32 => Box::new(MutablePrimitiveArray::<u32>::with_capacity(capacity)),
+            Kind::U64 => Box::new(MutablePrimitiveArray::<u64>::with_capacity(capacity)),
+            Kind::I8 => Box::new(MutablePrimitiveArray::<i8>::with_capacity(capacity)),
+            Kind::I16 => Box::new(MutablePrimitiveArray::<i16>::with_capacity(capacity)),
+            Kind::I32 => Box::new(MutablePrimitiveArray::<i32>::with_capacity(capacity)),
+            Kind::I64 => Box::new(MutablePrimitiveArray::<i64>::with_capacity(capacity)),
+            Kind::F32 => Box::new(MutablePrimitiveArray::<f32>::with_capacity(capacity)),
+            Kind::F64 => Box::new(MutablePrimitiveArray::<f64>::with_capacity(capacity)),
+            Kind::String | Kind::Json => Box::new(MutableUtf8Array::<i32>::with_capacity(capacity)),
+            Kind::LargeString => Box::new(MutableUtf8Array::<i64>::with_capacity(capacity)),
+            Kind::Enum(values) => Box::new(MutableEnumArray::new(values, capacity)),
+            Kind::Uuid | Kind::Ipv6 => {
+                Box::new(MutableFixedSizeBinaryArray::with_capacity(16, capacity))
+            }
+            Kind::Ipv4 => Box::new(MutableFixedSizeBinaryArray::with_capacity(4, capacity)),
+            Kind::FixedBytes(size) => {
+                Box::new(MutableFixedSizeBinaryArray::with_capacity(*size, capacity))
+            }
+            Kind::List(inner) => Box::new(MutableListArray::<i32, _>::new_from(
+                inner.builder_with_capacity(capacity),
+                self.to_arrow(),
+                capacity,
+            )),
+            Kind::Struct(fields) => Box::new(MutableStructArray::new(
+                self.to_arrow(),
+                fields
+                    .iter()
+                    .map(|child| child.kind.builder_with_capacity(capacity))
+                    .collect(),
+            )),
+            Kind::Map(key, value) => {
+                Box::new(MutableMapArray::new(self.to_arrow(), key, value, capacity))
+            }
+            Kind::TimestampSecsZ => Box::new(MutablePrimitiveArray::<i64>::with_capacity(capacity)),
+            Kind::Date => {
+                Box::new(MutablePrimitiveArray::<i32>::with_capacity(capacity).to(DataType::Date32))
+            }
+            Kind::DateMillis => {
+                Box::new(MutablePrimitiveArray::<i64>::with_capacity(capacity).to(DataType::Date64))
+            }
+            Kind::Timestamp { .. } => {
+                Box::new(MutablePrimitiveArray::<i64>::with_capacity(capacity).to(self.to_arrow()))
+            }
+            Kind::Duration(_) => {
+                Box::new(MutablePrimitiveArray::<i64>::with_capacity(capacity).to(self.to_arrow()))
+            }
+            Kind::Decimal { .. } => {
+                Box::new(MutablePrimitiveArray::<i128>::with_capacity(capacity).to(self.to_arrow()))
+            }
+            Kind::TimeMillis => Box::new(
+                MutablePrimitiveArray::<i32>::with_capacity(capacity)
+                    .to(DataType::Time32(TimeUnit::Millisecond)),
+            ),
+            Kind::TimeMicros => Box::new(
+                MutablePrimitiveArray::<i64>::with_capacity(capacity)
+                    .to(DataType::Time64(TimeUnit::Microsecond)),
+            ),
+            Kind::Geometry | Kind::Binary => {
+                Box::new(MutableBinaryArray::<i32>::with_capacity(capacity))
             }
         }
     }
 
-    pub fn to_arrow(self) -> DataType {
+    // exact, for a builder made by builder_with_capacity for this kind
+    pub(crate) fn mem_usage(&self, arr: &dyn MutableArray) -> usize {
+        match self {
+            Kind::Bool => exact::<MutableBooleanArray>(arr),
+            Kind::Null => 0,
+            Kind::U8 => exact::<MutablePrimitiveArray<u8>>(arr),
+            Kind::U16 => exact::<MutablePrimitiveArray<u16>>(arr),
+            Kind::U32 => exact::<MutablePrimitiveArray<u32>>(arr),
+            Kind::U64 => exact::<MutablePrimitiveArray<u64>>(arr),
+            Kind::I8 => exact::<MutablePrimitiveArray<i8>>(arr),
+            Kind::I16 => exact::<MutablePrimitiveArray<i16>>(arr),
+            Kind::I32 | Kind::Date | Kind::TimeMillis => exact::<MutablePrimitiveArray<i32>>(arr),
+            Kind::I64
+            | Kind::TimestampSecsZ
+            | Kind::Timestamp { .. }
+            | Kind::DateMillis
+            | Kind::TimeMicros
+            | Kind::Duration(_) => exact::<MutablePrimitiveArray<i64>>(arr),
+            Kind::Decimal { .. } => exact::<MutablePrimitiveArray<i128>>(arr),
+            Kind::F32 => exact::<MutablePrimitiveArray<f32>>(arr),
+            Kind::F64 => exact::<MutablePrimitiveArray<f64>>(arr),
+            Kind::String | Kind::Json => exact::<MutableUtf8Array<i32>>(arr),
+            Kind::LargeString => exact::<MutableUtf8Array<i64>>(arr),
+            Kind::Enum(_) => exact::<MutableEnumArray>(arr),
+            Kind::Uuid | Kind::Ipv4 | Kind::Ipv6 | Kind::FixedBytes(_) => {
+                exact::<MutableFixedSizeBinaryArray>(arr)
+            }
+            Kind::Geometry | Kind::Binary => exact::<MutableBinaryArray<i32>>(arr),
+            Kind::List(inner) => list_mem_usage(built(arr), |values| inner.mem_usage(values)),
+            Kind::Struct(fields) => {
+                struct_mem_usage(built(arr), fields.iter().map(|child| &child.kind))
+            }
+            Kind::Map(key, value) => {
+                list_mem_usage(built::<MutableMapArray>(arr).entries(), |entries| {
+                    struct_mem_usage(built(entries), [key.as_ref(), value.as_ref()])
+                })
+            }
+        }
+    }
+
+    pub fn to_arrow(&self) -> DataType {
         match self {
             Kind::Bool => DataType::Boolean,
+            Kind::Null => DataType::Null,
             Kind::U8 => DataType::UInt8,
+            Kind::U16 => DataType::UInt16,
+            Kind::U32 => DataType::UInt32,
+            Kind::U64 => DataType::UInt64,
+            Kind::I8 => DataType::Int8,
+            Kind::I16 => DataType::Int16,
             Kind::I32 => DataType::Int32,
             Kind::I64 => DataType::Int64,
+            Kind::F32 => DataType::Float32,
             Kind::F64 => DataType::Float64,
-            Kind::String => DataType::Utf8,
-            Kind::Uuid => DataType::FixedSizeBinary(16),
+            Kind::String | Kind::Json => DataType::Utf8,
+            Kind::LargeString => DataType::LargeUtf8,
+            Kind::Enum(_) => enum_type(),
+            Kind::Uuid | Kind::Ipv6 => DataType::FixedSizeBinary(16),
+            Kind::Ipv4 => DataType::FixedSizeBinary(4),
+            Kind::FixedBytes(size) => DataType::FixedSizeBinary(*size),
+            Kind::List(inner) => {
+                DataType::List(Box::new(ArrowField::new("item", inner.to_arrow(), true)))
+            }
+            Kind::Struct(fields) => DataType::Struct(
+                fields
+                    .iter()
+                    .map(|child| {
+                        ArrowField::new(&child.name, child.kind.to_arrow(), child.nullable)
+                    })
+                    .collect(),
+            ),
+            Kind::Map(key, value) => map_type(key, value),
             Kind::TimestampSecsZ => DataType::Timestamp(TimeUnit::Second, None),
+            Kind::Timestamp { unit, tz } => DataType::Timestamp(*unit, tz.clone()),
+            Kind::Date => DataType::Date32,
+            Kind::DateMillis => DataType::Date64,
+            Kind::TimeMillis => DataType::Time32(TimeUnit::Millisecond),
+            Kind::TimeMicros => DataType::Time64(TimeUnit::Microsecond),
+            Kind::Duration(unit) => DataType::Duration(*unit),
+            Kind::Decimal { precision, scale } => DataType::Decimal(*precision, *scale),
+            Kind::Geometry | Kind::Binary => DataType::Binary,
         }
     }
 
     pub fn from_arrow(arrow: &DataType) -> Result<Self> {
         Ok(match arrow {
             DataType::Utf8 => Kind::String,
+            DataType::LargeUtf8 => Kind::LargeString,
             DataType::Boolean => Kind::Bool,
+            DataType::Null => Kind::Null,
             DataType::Int64 => Kind::I64,
             DataType::Int32 => Kind::I32,
             DataType::UInt8 => Kind::U8,
+            DataType::UInt16 => Kind::U16,
+            DataType::UInt32 => Kind::U32,
+            DataType::UInt64 => Kind::U64,
+            DataType::Int8 => Kind::I8,
+            DataType::Int16 => Kind::I16,
+            DataType::Float32 => Kind::F32,
             DataType::Float64 => Kind::F64,
+            // geometry is only in the file's "geo" metadata, which isn't available here
+            DataType::Binary => Kind::Binary,
+            // similarly, uuids are only marked in the parquet schema
+            DataType::FixedSizeBinary(size) => Kind::FixedBytes(*size),
+            DataType::List(field) => Kind::List(Box::new(Kind::from_arrow(&field.data_type)?)),
+            DataType::Struct(fields) => Kind::Struct(
+                fields
+                    .iter()
+                    .map(|f| {
+                        Ok(TableField::new(
+                            &f.name,
+                            Kind::from_arrow(&f.data_type)?,
+                            f.is_nullable,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            DataType::Map(entries, _) => match &entries.data_type {
+                DataType::Struct(fields) if fields.len() == 2 => Kind::Map(
+                    Box::new(Kind::from_arrow(&fields[0].data_type)?),
+                    Box::new(Kind::from_arrow(&fields[1].data_type)?),
+                ),
+                other => bail!("unsupported map entries {:?}", other),
+            },
             DataType::Timestamp(TimeUnit::Second, None) => Kind::TimestampSecsZ,
+            DataType::Date32 => Kind::Date,
+            DataType::Date64 => Kind::DateMillis,
+            DataType::Time32(TimeUnit::Millisecond) => Kind::TimeMillis,
+            DataType::Time64(TimeUnit::Microsecond) => Kind::TimeMicros,
+            DataType::Duration(unit) => Kind::Duration(*unit),
+            DataType::Decimal(precision, scale) => Kind::Decimal {
+                precision: *precision,
+                scale: *scale,
+            },
+            DataType::Timestamp(unit, tz) => Kind::Timestamp {
+                unit: *unit,
+                tz: tz.clone(),
+            },
             other => bail!("unsupported type {:?}", other),
         })
     }
 
+    // things to_arrow can't refuse
+    pub(crate) fn check(&self) -> Result<()> {
+        if let Kind::Enum(values) = self {
+            ensure!(!values.is_empty(), "enums need at least one value");
+            ensure!(
+                u32::try_from(values.len()).is_ok(),
+                "too many values for an enum"
+            );
+            for (i, value) in values.iter().enumerate() {
+                ensure!(
+                    !values[..i].contains(value),
+                    "{:?} is in the enum twice",
+                    value
+                );
+            }
+        }
+        if let Kind::FixedBytes(size) = self {
+            ensure!(*size > 0, "fixed-size bytes can't be empty");
+        }
+        if let Kind::List(inner) = self {
+            inner.check()?;
+        }
+        if let Kind::Struct(fields) = self {
+            // arrow2 gets the length from the first child
+            ensure!(!fields.is_empty(), "structs need at least one child");
+            for (i, child) in fields.iter().enumerate() {
+                ensure!(
+                    !fields[..i].iter().any(|other| other.name == child.name),
+                    "there are two children called {:?}",
+                    child.name
+                );
+                child
+                    .kind
+                    .check()
+                    .with_context(|| anyhow!("checking child {:?}", child.name))?;
+            }
+        }
+        if let Kind::Map(key, value) = self {
+            ensure!(
+                !matches!(**key, Kind::List(_) | Kind::Struct(_) | Kind::Map(..)),
+                "map keys can't be {}s",
+                key
+            );
+            key.check()?;
+            value.check()?;
+        }
+        if let Kind::Decimal { precision, scale } = self {
+            ensure!(
+                (1..=38).contains(precision),
+                "decimals have a precision of 1 to 38, not {}",
+                precision
+            );
+            // parquet allows them to be equal, but parquet2 won't write it
+            ensure!(
+                scale < precision,
+                "a decimal's scale ({}) has to be less than its precision ({})",
+                scale,
+                precision
+            );
+        }
+        Ok(())
+    }
+
     pub fn default_encoding(&self) -> Encoding {
         match self {
             // don't think there's a reasonable encoding for these
-            Kind::Bool | Kind::U8 => Encoding::Plain,
+            Kind::Bool | Kind::U8 | Kind::I8 | Kind::I16 => Encoding::Plain,
+            Kind::Null => Encoding::Plain,
             // maybe this would practically benefit from the string encoding?
-            Kind::Uuid => Encoding::Plain,
+            Kind::Uuid | Kind::FixedBytes(_) | Kind::Ipv4 | Kind::Ipv6 => Encoding::Plain,
             // TODO: (writing with arrow2) > External format error: Invalid argument error: The datatype Int32 cannot be encoded by DeltaBinaryPacked
-            Kind::TimestampSecsZ | Kind::I64 | Kind::I32 => Encoding::Plain,
+            Kind::TimestampSecsZ | Kind::Timestamp { .. } | Kind::I64 | Kind::I32 => {
+                Encoding::Plain
+            }
+            Kind::Date | Kind::DateMillis | Kind::TimeMillis | Kind::TimeMicros => Encoding::Plain,
+            Kind::Duration(_) | Kind::Decimal { .. } => Encoding::Plain,
+            Kind::U16 | Kind::U32 | Kind::U64 => Encoding::Plain,
             // TODO: Error: External format error: Invalid argument error: The datatype Float64 cannot be encoded by ByteStreamSplit
             Kind::F64 => Encoding::Plain,
+            // arrow2 can't, so see split
+            Kind::F32 => Encoding::ByteStreamSplit,
             // TODO: (reading with datafusion) > ArrowError(ParquetError("Error reading batch from projects.parquet (size: 286037286): Parquet argument error: NYI: Encoding DELTA_LENGTH_BYTE_ARRAY is not supported"))
-            Kind::String => Encoding::Plain,
+            Kind::String | Kind::LargeString | Kind::Json => Encoding::Plain,
+            // arrow2 can only write dictionaries like this
+            Kind::Enum(_) => Encoding::RleDictionary,
+            Kind::Geometry | Kind::Binary => Encoding::Plain,
+            // for the values, which split can't find in a nested column
+            Kind::List(_) | Kind::Struct(_) | Kind::Map(..) => Encoding::Plain,
         }
     }
 }
 
-pub struct VarArray {
-    pub inner: Box<dyn MutableArray>,
+// the short names, as used by schema::parse_schema
+impl FromStr for Kind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // the inner kind keeps its case, for timestamps' timezones
+        if let Some(inner) = strip_wrapper(s.trim(), "list<", '>') {
+            return Ok(Kind::List(Box::new(inner.parse()?)));
+        }
+        // the values keep their case, and can't contain commas
+        if let Some(inner) = strip_wrapper(s.trim(), "enum<", '>') {
+            return Ok(Kind::Enum(
+                inner.split(',').map(|v| v.trim().to_string()).collect(),
+            ));
+        }
+        if let Some(inner) = strip_wrapper(s.trim(), "map<", '>') {
+            let (key, value) = split_top_level(inner)
+                .ok_or_else(|| anyhow!("expected map<key, value>, not {:?}", s.trim()))?;
+            return Ok(Kind::Map(Box::new(key.parse()?), Box::new(value.parse()?)));
+        }
+        // as parse_schema, so the children are only nullable with a ?
+        if let Some(inner) = strip_wrapper(s.trim(), "struct<", '>') {
+            return Ok(Kind::Struct(parse_schema(inner)?));
+        }
+        Ok(
+            match s.trim().to_ascii_lowercase().replace(' ', "").as_str() {
+                "bool" | "boolean" => Kind::Bool,
+                "null" => Kind::Null,
+                "uuid" | "fixed(16)" => Kind::Uuid,
+                "u8" | "uint8" => Kind::U8,
+                "u16" | "uint16" => Kind::U16,
+                "u32" | "uint32" => Kind::U32,
+                "u64" | "uint64" => Kind::U64,
+                "i8" | "int8" => Kind::I8,
+                "i16" | "int16" => Kind::I16,
+                "i32" | "int32" => Kind::I32,
+                "i64" | "int64" => Kind::I64,
+                "f32" | "float32" | "float" => Kind::F32,
+                "f64" | "float64" | "double" => Kind::F64,
+                "string" | "str" | "utf8" => Kind::String,
+                "large_string" | "large_utf8" => Kind::LargeString,
+                "json" => Kind::Json,
+                "ipv4" | "inet4" => Kind::Ipv4,
+                "ipv6" | "inet6" => Kind::Ipv6,
+                "timestamp_s" | "timestamp[s]" | "timestamp[s,utc]" | "timestamp_secs_z" => {
+                    Kind::TimestampSecsZ
+                }
+                "date" | "date32" => Kind::Date,
+                "date_millis" | "date64" => Kind::DateMillis,
+                "time_millis" | "time32[ms]" => Kind::TimeMillis,
+                "time_micros" | "time64[us]" => Kind::TimeMicros,
+                "geometry" | "wkb" => Kind::Geometry,
+                "binary" | "bytes" => Kind::Binary,
+                other => match parse_timestamp(s.trim())
+                    .or_else(|| parse_duration(other))
+                    .or_else(|| parse_decimal(other))
+                    .or_else(|| parse_fixed(other))
+                {
+                    Some(kind) => kind,
+                    None => bail!("unrecognised or unsupported kind {:?}", other),
+                },
+            },
+        )
+    }
 }
 
-impl VarArray {
-    fn new<T: MutableArray + 'static>(array: T) -> Self {
-        Self {
-            inner: Box::new(array),
+// e.g. list<i64>, ignoring the prefix's case
+fn strip_wrapper<'s>(s: &'s str, prefix: &str, suffix: char) -> Option<&'s str> {
+    if !s.get(..prefix.len())?.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    s[prefix.len()..].strip_suffix(suffix)
+}
+
+// at the first comma which isn't inside brackets, as in map<string, decimal(10,2)>
+fn split_top_level(s: &str) -> Option<(&str, &str)> {
+    let mut depth = 0i32;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '(' | '<' => depth += 1,
+            ']' | ')' | '>' => depth -= 1,
+            ',' if depth == 0 => return Some((&s[..i], &s[i + 1..])),
+            _ => (),
         }
     }
+    None
+}
+
+// e.g. timestamp[ms], or timestamp[us, Europe/London]; the timezone keeps its case
+fn parse_timestamp(s: &str) -> Option<Kind> {
+    if !s.get(..10)?.eq_ignore_ascii_case("timestamp[") {
+        return None;
+    }
+    let inner = s[10..].strip_suffix(']')?;
+    let (unit, tz) = match inner.split_once(',') {
+        Some((unit, tz)) => (unit, Some(tz.trim().to_string())),
+        None => (inner, None),
+    };
+    let unit = parse_unit(&unit.trim().to_ascii_lowercase())?;
+    Some(Kind::Timestamp { unit, tz })
+}
 
+// e.g. duration[ms], already lowercased
+fn parse_duration(s: &str) -> Option<Kind> {
+    let unit = s.strip_prefix("duration[")?.strip_suffix(']')?;
+    Some(Kind::Duration(parse_unit(unit)?))
+}
+
+// whether the unscaled value has at most `precision` digits
+pub(crate) fn decimal_fits(unscaled: i128, precision: usize) -> bool {
+    u32::try_from(precision)
+        .ok()
+        .and_then(|precision| 10u128.checked_pow(precision))
+        .is_some_and(|limit| unscaled.unsigned_abs() < limit)
+}
+
+// as stored in an Ipv4 or Ipv6 column
+pub(crate) fn ip_bytes(kind: &Kind, addr: IpAddr) -> Result<Vec<u8>> {
+    Ok(match (kind, addr) {
+        (Kind::Ipv4, IpAddr::V4(v4)) => v4.octets().to_vec(),
+        (Kind::Ipv4, IpAddr::V6(v6)) => match v6.to_ipv4_mapped() {
+            Some(v4) => v4.octets().to_vec(),
+            None => bail!("{} isn't a v4 address", v6),
+        },
+        (Kind::Ipv6, IpAddr::V4(v4)) => v4.to_ipv6_mapped().octets().to_vec(),
+        (Kind::Ipv6, IpAddr::V6(v6)) => v6.octets().to_vec(),
+        (kind, _) => bail!("{} columns don't hold addresses", kind),
+    })
+}
+
+#[cfg(feature = "json")]
+fn is_json(val: &str) -> bool {
+    serde_json::from_str::<serde::de::IgnoredAny>(val).is_ok()
+}
+
+// without the json feature, they're trusted
+#[cfg(not(feature = "json"))]
+fn is_json(_: &str) -> bool {
+    true
+}
+
+// e.g. decimal(10,2), already lowercased, and without the spaces
+fn parse_decimal(s: &str) -> Option<Kind> {
+    let (precision, scale) = s
+        .strip_prefix("decimal(")?
+        .strip_suffix(')')?
+        .split_once(',')?;
+    Some(Kind::Decimal {
+        precision: precision.parse().ok()?,
+        scale: scale.parse().ok()?,
+    })
+}
+
+// e.g. fixed_bytes(32), or fixed(32); fixed(16) is a uuid
+fn parse_fixed(s: &str) -> Option<Kind> {
+    let size = s
+        .strip_prefix("fixed_bytes(")
+        .or_else(|| s.strip_prefix("fixed("))?
+        .strip_suffix(')')?;
+    Some(Kind::FixedBytes(size.parse().ok()?))
+}
+
+fn parse_unit(s: &str) -> Option<TimeUnit> {
+    Some(match s {
+        "s" => TimeUnit::Second,
+        "ms" => TimeUnit::Millisecond,
+        "us" => TimeUnit::Microsecond,
+        "ns" => TimeUnit::Nanosecond,
+        _ => return None,
+    })
+}
+
+fn unit_name(unit: TimeUnit) -> &'static str {
+    match unit {
+        TimeUnit::Second => "s",
+        TimeUnit::Millisecond => "ms",
+        TimeUnit::Microsecond => "us",
+        TimeUnit::Nanosecond => "ns",
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Timestamp { unit, tz: None } => {
+                return write!(f, "timestamp[{}]", unit_name(*unit))
+            }
+            Kind::Timestamp { unit, tz: Some(tz) } => {
+                return write!(f, "timestamp[{}, {}]", unit_name(*unit), tz)
+            }
+            Kind::Duration(unit) => return write!(f, "duration[{}]", unit_name(*unit)),
+            Kind::Decimal { precision, scale } => {
+                return write!(f, "decimal({}, {})", precision, scale)
+            }
+            Kind::FixedBytes(size) => return write!(f, "fixed_bytes({})", size),
+            Kind::List(inner) => return write!(f, "list<{}>", inner),
+            Kind::Map(key, value) => return write!(f, "map<{}, {}>", key, value),
+            Kind::Enum(values) => return write!(f, "enum<{}>", values.join(", ")),
+            Kind::Struct(fields) => {
+                f.write_str("struct<")?;
+                for (i, child) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", child.name, child.kind)?;
+                    if child.nullable {
+                        f.write_str("?")?;
+                    }
+                }
+                return f.write_str(">");
+            }
+            Kind::Bool => "bool",
+            Kind::Null => "null",
+            Kind::Uuid => "uuid",
+            Kind::U8 => "u8",
+            Kind::U16 => "u16",
+            Kind::U32 => "u32",
+            Kind::U64 => "u64",
+            Kind::I8 => "i8",
+            Kind::I16 => "i16",
+            Kind::I32 => "i32",
+            Kind::I64 => "i64",
+            Kind::F32 => "f32",
+            Kind::F64 => "f64",
+            Kind::String => "string",
+            Kind::LargeString => "large_string",
+            Kind::Json => "json",
+            Kind::Ipv4 => "ipv4",
+            Kind::Ipv6 => "ipv6",
+            Kind::TimestampSecsZ => "timestamp_s",
+            Kind::Date => "date",
+            Kind::DateMillis => "date_millis",
+            Kind::TimeMillis => "time_millis",
+            Kind::TimeMicros => "time_micros",
+            Kind::Geometry => "geometry",
+            Kind::Binary => "binary",
+        })
+    }
+}
+
+// a column, as push errors describe it: which, what it is, and how far in
+pub(crate) struct ColumnAt<'t> {
+    pub(crate) field: &'t TableField,
+    index: usize,
+    row: usize,
+}
+
+impl fmt::Display for ColumnAt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "column {:?} (#{}, a {}, at row {})",
+            self.field.name, self.index, self.field.kind, self.row
+        )
+    }
+}
+
+pub struct VarArray {
+    pub inner: Box<dyn MutableArray>,
+    // what it was built for, which says how to measure it
+    kind: Kind,
+}
+
+impl VarArray {
     pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
         self.inner.as_any().downcast_ref()
     }
@@ -122,6 +872,59 @@ impl VarArray {
         self.inner.as_mut_any().downcast_mut()
     }
 
+    // checked in debug builds only
+    unsafe fn downcast_unchecked<T: Any>(&mut self) -> &mut T {
+        debug_assert!(self.inner.as_any().is::<T>(), "wrong type for this column");
+        &mut *(self.inner.as_mut_any() as *mut dyn Any as *mut T)
+    }
+
+    // the nested kinds aren't supported
+    pub fn value(&self, row: usize) -> Result<Value<'_>> {
+        ensure!(row < self.inner.len(), "there's no row {}", row);
+        if !self.inner.validity().is_none_or(|v| v.get(row)) {
+            return Ok(Value::Null);
+        }
+        macro_rules! primitive {
+            ($($t:ty => $variant:ident),*) => {
+                $(
+                    if let Some(arr) = self.downcast_ref::<MutablePrimitiveArray<$t>>() {
+                        return Ok(Value::$variant(arr.values()[row]));
+                    }
+                )*
+            };
+        }
+        primitive!(u8 => U8, u16 => U16, u32 => U32, u64 => U64, i8 => I8, i16 => I16,
+            i32 => I32, i64 => I64, i128 => I128, f32 => F32, f64 => F64);
+        Ok(
+            if let Some(arr) = self.downcast_ref::<MutableBooleanArray>() {
+                Value::Bool(arr.values().get(row))
+            } else if let Some(arr) = self.downcast_ref::<MutableUtf8Array<i32>>() {
+                Value::Str(arr.value(row))
+            } else if let Some(arr) = self.downcast_ref::<MutableUtf8Array<i64>>() {
+                Value::Str(arr.value(row))
+            } else if let Some(arr) = self.downcast_ref::<MutableEnumArray>() {
+                Value::Str(arr.value(row))
+            } else if let Some(arr) = self.downcast_ref::<MutableFixedSizeBinaryArray>() {
+                Value::Bytes(arr.value(row))
+            } else if let Some(arr) = self.downcast_ref::<MutableBinaryArray<i32>>() {
+                let (start, end) = arr.offsets().start_end(row);
+                Value::Bytes(&arr.values()[start..end])
+            } else if self.downcast_ref::<MutableNullArray>().is_some() {
+                Value::Null
+            } else {
+                bail!("can't read back {:?} values", self.inner.data_type())
+            },
+        )
+    }
+
+    // all of `arr`, which has to be of the type the column was built from, as read from a file;
+    // nothing is copied if any of it doesn't fit
+    pub(crate) fn copy_from(&mut self, arr: &dyn Array) -> Result<()> {
+        self.kind.check_extend(self.inner.as_ref(), arr)?;
+        self.kind.extend(self.inner.as_mut(), arr);
+        Ok(())
+    }
+
     // this moves, but has to be called from a mut ref?!
     fn as_arc(&mut self) -> Arc<dyn Array> {
         self.inner.as_arc()
@@ -130,58 +933,230 @@ impl VarArray {
 
 impl MemUsage for VarArray {
     fn mem_usage(&self) -> usize {
-        // some regrets
-        if let Some(v) = self.downcast_ref::<MutableUtf8Array<i32>>() {
-            v.mem_usage()
-        } else if let Some(v) = self.downcast_ref::<MutablePrimitiveArray<i64>>() {
-            v.mem_usage()
-        } else if let Some(v) = self.downcast_ref::<MutablePrimitiveArray<i32>>() {
-            v.mem_usage()
-        } else if let Some(v) = self.downcast_ref::<MutablePrimitiveArray<i16>>() {
-            v.mem_usage()
-        } else if let Some(v) = self.downcast_ref::<MutablePrimitiveArray<u8>>() {
-            v.mem_usage()
-        } else if let Some(v) = self.downcast_ref::<MutableBooleanArray>() {
-            v.mem_usage()
-        } else {
-            debug_assert!(false, "unsupported type");
-            // just wildly overestimate
-            self.inner.len() * 16
-        }
+        self.kind.mem_usage(self.inner.as_ref())
+    }
+}
+
+fn exact<T: MemUsage + Any>(arr: &dyn MutableArray) -> usize {
+    built::<T>(arr).mem_usage()
+}
+
+fn list_mem_usage(
+    arr: &MutableListArray<i32, Box<dyn MutableArray>>,
+    values: impl FnOnce(&dyn MutableArray) -> usize,
+) -> usize {
+    arr.validity().mem_usage() + arr.offsets().mem_usage() + values(arr.values().as_ref())
+}
+
+fn struct_mem_usage<'k>(
+    arr: &MutableStructArray,
+    kinds: impl IntoIterator<Item = &'k Kind>,
+) -> usize {
+    let values = arr.values().iter().zip(kinds);
+    arr.validity().mem_usage()
+        + values
+            .map(|(values, kind)| kind.mem_usage(values.as_ref()))
+            .sum::<usize>()
+}
+
+// the builder array_with_capacity made for a kind
+pub(crate) fn built<T: Any>(arr: &dyn MutableArray) -> &T {
+    arr.as_any()
+        .downcast_ref()
+        .expect("builders are built for their kind")
+}
+
+// some of a table's columns, in the order they were asked for; see Table::columns
+pub struct ColumnSet<'t> {
+    columns: Vec<&'t mut VarArray>,
+}
+
+impl<'t> Deref for ColumnSet<'t> {
+    type Target = [&'t mut VarArray];
+
+    fn deref(&self) -> &Self::Target {
+        &self.columns
+    }
+}
+
+impl DerefMut for ColumnSet<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.columns
     }
 }
 
 pub struct Table {
-    schema: Box<[Kind]>,
+    schema: Box<[TableField]>,
     builders: Box<[VarArray]>,
+    // a single value, repeated for every row by take_batch, instead of being pushed
+    constants: Box<[Option<Box<dyn Array>>]>,
     cap: usize,
-    mem_used: usize,
+    coercion: Coercion,
+    // the normalised names, for the RowBuilder's and RowView's lookups
+    name_matching: NameMatching,
+    names: HashMap<String, usize>,
+    // the rows, and mem_estimate, when recent_mem_estimate last worked it out
+    estimated: (usize, usize),
+}
+
+// the first of any duplicates, as find_field
+fn name_index(schema: &[TableField], matching: NameMatching) -> HashMap<String, usize> {
+    let mut names = HashMap::with_capacity(schema.len());
+    for (i, field) in schema.iter().enumerate().rev() {
+        names.insert(matching.normalize(&field.name).into_owned(), i);
+    }
+    names
+}
+
+// a constant, as a column of `rows` rows
+fn repeated(value: &dyn Array, rows: usize) -> Arc<dyn Array> {
+    if value.null_count() > 0 {
+        return Arc::from(new_null_array(value.data_type().clone(), rows));
+    }
+    Arc::from(gather(value, &vec![0; rows]))
 }
 
-fn make_builders(schema: &[Kind], cap: usize) -> Box<[VarArray]> {
+fn make_builders(schema: &[TableField], cap: usize) -> Box<[VarArray]> {
     schema
         .iter()
-        .map(|kind| kind.array_with_capacity(cap))
+        .map(|field| field.kind.array_with_capacity(cap))
         .collect()
 }
 
 impl Table {
+    // all of the columns are nullable, and named after their index
     pub fn with_capacity(schema: &[Kind], cap: usize) -> Self {
+        let fields = schema
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| TableField::new(i, kind.clone(), true))
+            .collect::<Vec<_>>();
+        Self::from_fields(&fields, cap)
+    }
+
+    pub fn from_fields(schema: &[TableField], cap: usize) -> Self {
         Self {
             schema: schema.to_vec().into_boxed_slice(),
             builders: make_builders(schema, cap),
+            constants: schema
+                .iter()
+                .map(|f| (f.kind == Kind::Null).then(|| new_null_array(DataType::Null, 1)))
+                .collect(),
             cap,
-            mem_used: 0,
+            coercion: Coercion::default(),
+            name_matching: NameMatching::default(),
+            names: name_index(schema, NameMatching::default()),
+            estimated: (0, 0),
+        }
+    }
+
+    // for push_primitive values which aren't the column's type
+    pub fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    // for looking columns up by name; a Packer's table matches as its writer does
+    pub fn set_name_matching(&mut self, matching: NameMatching) {
+        self.name_matching = matching;
+        self.names = name_index(&self.schema, matching);
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.names
+            .get(self.name_matching.normalize(name).as_ref())
+            .copied()
+    }
+
+    // every row of column `i` is `value`, a single element array of the column's type, until
+    // this is called again; nothing is pushed to the column. The constraint isn't checked
+    pub fn set_constant(&mut self, i: usize, value: Box<dyn Array>) -> Result<()> {
+        let field = &self.schema[i];
+        ensure!(
+            value.len() == 1,
+            "a constant is one value, not {}",
+            value.len()
+        );
+        ensure!(
+            value.data_type() == &field.kind.to_arrow(),
+            "column {:?} is a {}, not a {:?}",
+            field.name,
+            field.kind,
+            value.data_type()
+        );
+        if value.null_count() > 0 {
+            self.check_nullable(i)?;
         }
+        ensure!(
+            self.builders[i].inner.is_empty(),
+            "column {:?} already has values pushed",
+            field.name
+        );
+        ensure!(
+            self.constants
+                .iter()
+                .enumerate()
+                .any(|(j, c)| j != i && c.is_none()),
+            "at least one column has to be pushed to, to know how many rows there are"
+        );
+        self.constants[i] = Some(value);
+        Ok(())
+    }
+
+    // set_constant, or an always null column; nothing can be pushed to it
+    pub fn is_constant(&self, i: usize) -> bool {
+        self.constants[i].is_some()
+    }
+
+    pub fn set_all_null(&mut self, i: usize) -> Result<()> {
+        self.set_constant(i, new_null_array(self.schema[i].kind.to_arrow(), 1))
+    }
+
+    // after changing the schema, e.g. for Packer::add_column
+    pub(crate) fn copy_constants(&mut self, from: &Table) {
+        for (mine, theirs) in self.constants.iter_mut().zip(from.constants.iter()) {
+            *mine = theirs.clone();
+        }
+    }
+
+    pub(crate) fn column_at(&self, i: usize) -> ColumnAt<'_> {
+        ColumnAt {
+            field: &self.schema[i],
+            index: i,
+            row: self.builders[i].inner.len(),
+        }
+    }
+
+    // for the push_* which don't fit the column
+    fn cant_push(&self, i: usize, what: &str) -> anyhow::Error {
+        anyhow!("can't push {} to {}", what, self.column_at(i))
+    }
+
+    // writing through get() / columns() skips this
+    fn check_nullable(&self, i: usize) -> Result<()> {
+        ensure!(
+            self.schema[i].nullable,
+            "{} isn't nullable, but a null was pushed",
+            self.column_at(i)
+        );
+        Ok(())
     }
 
     pub fn check_consistent(&self) -> Result<()> {
-        let expectation = self.builders[0].inner.len();
-        for (i, b) in self.builders.iter().enumerate().skip(1) {
+        let expectation = self.rows();
+        for (i, (b, constant)) in self.builders.iter().zip(self.constants.iter()).enumerate() {
+            if constant.is_some() {
+                ensure!(
+                    b.inner.is_empty(),
+                    "column {:?} is constant, but has {} values pushed",
+                    self.schema[i].name,
+                    b.inner.len()
+                );
+                continue;
+            }
             ensure!(
                 b.inner.len() == expectation,
-                "expected col {} to have length {}, not {}",
-                i,
+                "expected column {:?} to have {} rows, not {}",
+                self.schema[i].name,
                 expectation,
                 b.inner.len()
             );
@@ -190,14 +1165,218 @@ impl Table {
         Ok(())
     }
 
+    // what the builders have allocated, including validity and offsets: at least what they hold,
+    // and, as they grow by doubling, under twice that (unless they were made with more room).
+    // Walks every builder, so is O(columns)
     pub fn mem_estimate(&self) -> usize {
-        self.mem_used
+        self.builders.iter().map(|b| b.mem_usage()).sum()
+    }
+
+    // mem_estimate, for checking after every row: it's only worked out again once the rows have
+    // grown by a sixteenth since it last was (or have been taken), so it can be that far behind,
+    // but walks the builders O(log rows) times, not once per row
+    pub(crate) fn recent_mem_estimate(&mut self) -> usize {
+        let rows = self.rows();
+        let (at, estimate) = self.estimated;
+        if rows >= at && rows - at < (at / 16).max(1) {
+            return estimate;
+        }
+        let estimate = self.mem_estimate();
+        self.estimated = (rows, estimate);
+        estimate
+    }
+
+    pub fn schema(&self) -> &[TableField] {
+        &self.schema
+    }
+
+    pub fn row<'r>(&mut self) -> RowBuilder<'_, 'r> {
+        RowBuilder::new(self)
+    }
+
+    // a value for every column, checked as the RowBuilder's setters do; nothing is pushed unless
+    // the whole row is acceptable. Nested columns can only be Value::Null
+    pub fn push_row(&mut self, values: &[Value]) -> Result<()> {
+        ensure!(
+            values.len() == self.schema.len(),
+            "expected {} values, one per column, got {}",
+            self.schema.len(),
+            values.len()
+        );
+        for (i, val) in values.iter().enumerate() {
+            val.check(&self.column_at(i))?;
+        }
+        self.commit_row(values.to_vec())
+    }
+
+    pub(crate) fn commit_row(&mut self, values: Vec<Value>) -> Result<()> {
+        let checked = values
+            .into_iter()
+            .zip(self.constants.iter())
+            .enumerate()
+            .map(|(i, (val, constant))| -> Result<Option<Checked>> {
+                if constant.is_some() {
+                    return Ok(None);
+                }
+                let column = &self.column_at(i);
+                let field = column.field;
+                let constraint = field.constraint.as_ref();
+                let checked = match (val, constraint) {
+                    (Value::Null, _) => None,
+                    (Value::Str(v), Some(c)) => c.check_str(column, v)?.map(Checked::Str),
+                    (Value::U8(v), Some(c)) => c.check_number(column, v)?.map(Checked::U8),
+                    (Value::U16(v), Some(c)) => c.check_number(column, v)?.map(Checked::U16),
+                    (Value::U32(v), Some(c)) => c.check_number(column, v)?.map(Checked::U32),
+                    (Value::U64(v), Some(c)) => c.check_number(column, v)?.map(Checked::U64),
+                    (Value::I8(v), Some(c)) => c.check_number(column, v)?.map(Checked::I8),
+                    (Value::I16(v), Some(c)) => c.check_number(column, v)?.map(Checked::I16),
+                    (Value::I32(v), Some(c)) => c.check_number(column, v)?.map(Checked::I32),
+                    (Value::I64(v), Some(c)) => c.check_number(column, v)?.map(Checked::I64),
+                    (Value::F32(v), Some(c)) => c.check_number(column, v)?.map(Checked::F32),
+                    (Value::F64(v), Some(c)) => c.check_number(column, v)?.map(Checked::F64),
+                    (Value::Bool(v), _) => Some(Checked::Bool(v)),
+                    (Value::U8(v), None) => Some(Checked::U8(v)),
+                    (Value::U16(v), None) => Some(Checked::U16(v)),
+                    (Value::U32(v), None) => Some(Checked::U32(v)),
+                    (Value::U64(v), None) => Some(Checked::U64(v)),
+                    (Value::I8(v), None) => Some(Checked::I8(v)),
+                    (Value::I16(v), None) => Some(Checked::I16(v)),
+                    (Value::I32(v), None) => Some(Checked::I32(v)),
+                    (Value::I64(v), None) => Some(Checked::I64(v)),
+                    (Value::F32(v), None) => Some(Checked::F32(v)),
+                    // the constraints' bounds are in the column's scale, so don't apply
+                    (Value::I128(v), _) => Some(Checked::I128(v)),
+                    (Value::F64(v), None) => Some(Checked::F64(v)),
+                    (Value::Str(v), None) => Some(Checked::Str(Cow::Borrowed(v))),
+                    (Value::Bytes(v), _) if matches!(field.kind, Kind::Binary | Kind::Geometry) => {
+                        Some(Checked::Binary(Cow::Borrowed(v)))
+                    }
+                    (Value::Bytes(v), _) => Some(Checked::Bytes(Cow::Borrowed(v))),
+                };
+                // after the constraint, which may have changed it
+                match (&checked, &field.kind) {
+                    (Some(Checked::Str(v)), Kind::Enum(values)) => ensure!(
+                        values.iter().any(|allowed| allowed == v),
+                        "{:?} isn't one of the values of {}",
+                        v,
+                        column
+                    ),
+                    (Some(Checked::Str(v)), Kind::Json) => {
+                        ensure!(is_json(v), "{} isn't json: {:?}", column, v)
+                    }
+                    _ => (),
+                }
+                match checked {
+                    Some(checked) => Ok(Some(checked)),
+                    None => match field.checked_default()? {
+                        Some(default) => Ok(Some(default)),
+                        None => {
+                            ensure!(field.nullable, "{} isn't nullable", column);
+                            Ok(Some(Checked::Null))
+                        }
+                    },
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // the RowBuilder checked the types against the kinds, which decide the builders
+        for (i, val) in checked.into_iter().enumerate() {
+            if let Some(val) = val {
+                unsafe { self.push_checked(i, val) };
+            }
+        }
+
+        Ok(())
+    }
+
+    // the value has to match the kind of column `i`
+    unsafe fn push_checked(&mut self, i: usize, val: Checked) {
+        let arr = &mut self.builders[i];
+        unsafe {
+            match val {
+                Checked::Null => arr.inner.push_null(),
+                Checked::Bool(v) => arr
+                    .downcast_unchecked::<MutableBooleanArray>()
+                    .push(Some(v)),
+                Checked::U8(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<u8>>()
+                    .push(Some(v)),
+                Checked::U16(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<u16>>()
+                    .push(Some(v)),
+                Checked::U32(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<u32>>()
+                    .push(Some(v)),
+                Checked::U64(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<u64>>()
+                    .push(Some(v)),
+                Checked::I8(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<i8>>()
+                    .push(Some(v)),
+                Checked::I16(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<i16>>()
+                    .push(Some(v)),
+                Checked::I32(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<i32>>()
+                    .push(Some(v)),
+                Checked::I64(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<i64>>()
+                    .push(Some(v)),
+                Checked::I128(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<i128>>()
+                    .push(Some(v)),
+                Checked::F32(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<f32>>()
+                    .push(Some(v)),
+                Checked::F64(v) => arr
+                    .downcast_unchecked::<MutablePrimitiveArray<f64>>()
+                    .push(Some(v)),
+                Checked::Str(v) if matches!(self.schema[i].kind, Kind::Enum(_)) => arr
+                    .downcast_unchecked::<MutableEnumArray>()
+                    .try_push(&self.schema[i].name, Some(&v))
+                    .expect("commit_row checked it's allowed"),
+                Checked::Str(v) if self.schema[i].kind == Kind::LargeString => arr
+                    .downcast_unchecked::<MutableUtf8Array<i64>>()
+                    .push(Some(v)),
+                Checked::Str(v) => arr
+                    .downcast_unchecked::<MutableUtf8Array<i32>>()
+                    .push(Some(v)),
+                Checked::Bytes(v) => arr
+                    .downcast_unchecked::<MutableFixedSizeBinaryArray>()
+                    .push(Some(v)),
+                Checked::Binary(v) => arr
+                    .downcast_unchecked::<MutableBinaryArray<i32>>()
+                    .push(Some(v)),
+            }
+        }
+    }
+
+    // a buffered value, e.g. to compare against the last row's key
+    pub fn value_at(&self, col: usize, row: usize) -> Result<Value<'_>> {
+        let field = self
+            .schema
+            .get(col)
+            .ok_or_else(|| anyhow!("there's no column {}", col))?;
+        ensure!(
+            self.constants[col].is_none(),
+            "column {:?} is constant, so nothing's buffered for it",
+            field.name
+        );
+        self.builders[col]
+            .value(row)
+            .with_context(|| anyhow!("reading column {:?}", field.name))
+    }
+
+    // over the buffered rows, e.g. to validate or sample them before they're flushed
+    pub fn iter_rows(&self) -> impl Iterator<Item = RowView<'_>> {
+        (0..self.rows()).map(move |row| RowView::new(self, row))
     }
 
     pub fn get(&mut self, item: usize) -> &mut VarArray {
         &mut self.builders[item]
     }
 
+    #[deprecated = "the columns are in the table's order, not the order asked for; see columns()"]
     pub fn get_many(&mut self, items: &[usize]) -> Vec<&mut VarArray> {
         self.builders
             .iter_mut()
@@ -207,84 +1386,781 @@ impl Table {
             .collect()
     }
 
+    // in the order asked for, which can't include a column twice; call finish_bulk_push after
+    pub fn columns(&mut self, items: &[usize]) -> Result<ColumnSet<'_>> {
+        let mut slots = self.builders.iter_mut().map(Some).collect::<Vec<_>>();
+        let columns = items
+            .iter()
+            .map(|&i| {
+                slots
+                    .get_mut(i)
+                    .ok_or_else(|| anyhow!("there's no column {}", i))?
+                    .take()
+                    .ok_or_else(|| anyhow!("column {} was asked for twice", i))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ColumnSet { columns })
+    }
+
     pub fn finish_bulk_push(&mut self) -> Result<()> {
         self.check_consistent()?;
-        self.mem_used = self.builders.iter().map(|b| b.mem_usage()).sum();
         Ok(())
     }
 
     pub fn rows(&self) -> usize {
-        self.builders[0].inner.len()
+        self.builders
+            .iter()
+            .zip(self.constants.iter())
+            .find(|(_, constant)| constant.is_none())
+            .map(|(b, _)| b.inner.len())
+            // only null columns, which can't have any rows
+            .unwrap_or(0)
     }
 
+    // or the column's default, if it has one
     pub fn push_null(&mut self, i: usize) -> Result<()> {
-        // only off by a factor of about eight
-        self.mem_used += 1;
+        // so rows can be pushed as usual, but the column's constant
+        if self.schema[i].kind == Kind::Null {
+            return Ok(());
+        }
+        if let Some(default) = self.schema[i].checked_default()? {
+            // checked against the kind
+            unsafe { self.push_checked(i, default) };
+            return Ok(());
+        }
+        self.check_nullable(i)?;
         self.builders[i].inner.push_null();
         Ok(())
     }
 
+    // as the typed push_* for the value's type; see IntoScalar
+    pub fn push<V: IntoScalar>(&mut self, i: usize, val: Option<V>) -> Result<()> {
+        match val {
+            Some(val) => val.push_to(self, i),
+            None => self.push_null(i),
+        }
+    }
+
     pub fn push_str(&mut self, i: usize, val: Option<&str>) -> Result<()> {
+        let field = &self.schema[i];
+        let val = match (val, &field.constraint) {
+            (Some(val), Some(constraint)) => constraint.check_str(&self.column_at(i), val)?,
+            (val, _) => val.map(Cow::Borrowed),
+        };
+        let val = val.as_deref();
+        if val.is_none() {
+            return self.push_null(i);
+        }
+        if let (Some(val), Kind::Json) = (val, &field.kind) {
+            ensure!(is_json(val), "{} isn't json: {:?}", self.column_at(i), val);
+        }
         let arr = &mut self.builders[i];
         if let Some(arr) = arr.downcast_mut::<MutableUtf8Array<i32>>() {
-            self.mem_used +=
-                val.map(|val| val.len()).unwrap_or_default() + std::mem::size_of::<i32>();
             arr.try_push(val)?;
             Ok(())
+        } else if let Some(arr) = arr.downcast_mut::<MutableUtf8Array<i64>>() {
+            arr.try_push(val)?;
+            Ok(())
+        } else if let Some(arr) = arr.downcast_mut::<MutableEnumArray>() {
+            arr.try_push(&self.schema[i].name, val)?;
+            Ok(())
         } else {
-            Err(anyhow!("can't push a string to this column"))
+            Err(self.cant_push(i, "a string"))
         }
     }
 
     pub fn push_bool(&mut self, i: usize, val: Option<bool>) -> Result<()> {
+        if val.is_none() {
+            return self.push_null(i);
+        }
         let arr = &mut self.builders[i];
         if let Some(arr) = arr.downcast_mut::<MutableBooleanArray>() {
-            // only off by a factor of about four
-            self.mem_used += 1;
             arr.try_push(val)?;
             Ok(())
         } else {
-            Err(anyhow!("can't push a bool to this column"))
+            Err(self.cant_push(i, "a bool"))
+        }
+    }
+
+    #[cfg(feature = "uuid")]
+    pub fn push_uuid(&mut self, i: usize, val: Option<uuid::Uuid>) -> Result<()> {
+        if self.schema[i].kind != Kind::Uuid {
+            return Err(self.cant_push(i, "a uuid"));
         }
+        self.push_fsb(i, val.as_ref().map(uuid::Uuid::as_bytes))
+    }
+
+    pub fn push_ip(&mut self, i: usize, val: Option<IpAddr>) -> Result<()> {
+        let val = match val {
+            Some(val) => val,
+            None => return self.push_null(i),
+        };
+        let bytes = ip_bytes(&self.schema[i].kind, val)
+            .with_context(|| anyhow!("pushing to {}", self.column_at(i)))?;
+        self.push_fsb(i, Some(bytes))
     }
 
     pub fn push_fsb(&mut self, i: usize, val: Option<impl AsRef<[u8]>>) -> Result<()> {
+        let val = match val {
+            Some(val) => val,
+            None => return self.push_null(i),
+        };
+        let size = match self.builders[i].downcast_ref::<MutableFixedSizeBinaryArray>() {
+            Some(arr) => arr.size(),
+            None => return Err(self.cant_push(i, "fixed-size bytes")),
+        };
+        // arrow2's error is vague, and comes after it's made a mess
+        let val = val.as_ref();
+        ensure!(
+            val.len() == size,
+            "{} expects {} bytes, got {}",
+            self.column_at(i),
+            size,
+            val.len()
+        );
         let arr = &mut self.builders[i];
+        if let Some(arr) = arr.downcast_mut::<MutableFixedSizeBinaryArray>() {
+            arr.try_push(Some(val))?;
+            Ok(())
+        } else {
+            Err(self.cant_push(i, "fixed-size bytes"))
+        }
+    }
+
+    // well-known binary; see push_point
+    pub fn push_wkb(&mut self, i: usize, val: Option<&[u8]>) -> Result<()> {
         let val = match val {
             Some(val) => val,
-            None => {
-                arr.inner.push_null();
-                return Ok(());
-            }
+            None => return self.push_null(i),
         };
+        ensure!(
+            geometry_type(val).is_some(),
+            "{} expects WKB, which this doesn't start like",
+            self.column_at(i)
+        );
+        let arr = &mut self.builders[i];
+        if let Some(arr) = arr.downcast_mut::<MutableBinaryArray<i32>>() {
+            arr.try_push(Some(val))?;
+            Ok(())
+        } else {
+            Err(self.cant_push(i, "a geometry"))
+        }
+    }
 
-        if let Some(arr) = arr.downcast_mut::<MutableFixedSizeBinaryArray>() {
-            self.mem_used += arr.size();
-            arr.try_push(Some(val.as_ref()))?;
+    // geometry columns check it's WKB; see push_wkb
+    pub fn push_bytes(&mut self, i: usize, val: Option<&[u8]>) -> Result<()> {
+        if self.schema[i].kind == Kind::Geometry {
+            return self.push_wkb(i, val);
+        }
+        let val = match val {
+            Some(val) => val,
+            None => return self.push_null(i),
+        };
+        let arr = &mut self.builders[i];
+        if let Some(arr) = arr.downcast_mut::<MutableBinaryArray<i32>>() {
+            arr.try_push(Some(val))?;
             Ok(())
         } else {
-            Err(anyhow!("can't push a uuid to this column"))
+            Err(self.cant_push(i, "bytes"))
+        }
+    }
+
+    // the values aren't checked against the field's constraint; nulls in the list are fine
+    pub fn push_list<V: ChildValue>(
+        &mut self,
+        i: usize,
+        vals: Option<impl IntoIterator<Item = Option<V>>>,
+    ) -> Result<()> {
+        let vals = match vals {
+            Some(vals) => vals,
+            None => return self.push_null(i),
+        };
+        type List = MutableListArray<i32, Box<dyn MutableArray>>;
+        if self.builders[i].downcast_ref::<List>().is_none() {
+            return Err(self.cant_push(i, "a list"));
         }
+        // nothing's pushed unless they'll all fit, as a half-pushed list can't be taken back
+        let values = self.builders[i]
+            .downcast_ref::<List>()
+            .expect("just checked")
+            .values();
+        if !V::fits(values.as_ref()) {
+            return Err(self.cant_push(i, &format!("a list of {}s", std::any::type_name::<V>())));
+        }
+        let arr = self.builders[i]
+            .downcast_mut::<List>()
+            .expect("just checked");
+        for val in vals {
+            match val {
+                Some(val) => val.push_to(arr.mut_values().as_mut())?,
+                None => arr.mut_values().push_null(),
+            }
+        }
+        arr.try_push_valid()?;
+        Ok(())
+    }
+
+    // `f` sets the children, by index (see StructRow::child); the ones it doesn't set are null,
+    // so have to be nullable. If `f` fails, or leaves a required child unset, a null row is
+    // pushed anyway, so the children stay the same length
+    pub fn push_struct(
+        &mut self,
+        i: usize,
+        f: impl FnOnce(&mut StructRow) -> Result<()>,
+    ) -> Result<()> {
+        let fields = match &self.schema[i].kind {
+            Kind::Struct(fields) => fields,
+            _ => return Err(self.cant_push(i, "a struct")),
+        };
+        let arr = self.builders[i]
+            .downcast_mut::<MutableStructArray>()
+            .expect("structs are built as structs");
+        let mut row = StructRow::new(arr, fields);
+        let result = f(&mut row).and_then(|()| row.check_set());
+        row.finish(result.is_ok());
+        result
+    }
+
+    // the values aren't checked against the field's constraint; the keys aren't checked to be unique
+    pub fn push_map<K: ChildValue, V: ChildValue>(
+        &mut self,
+        i: usize,
+        entries: Option<impl IntoIterator<Item = (K, Option<V>)>>,
+    ) -> Result<()> {
+        let entries = match entries {
+            Some(entries) => entries,
+            None => return self.push_null(i),
+        };
+        if self.builders[i].downcast_ref::<MutableMapArray>().is_none() {
+            return Err(self.cant_push(i, "a map"));
+        }
+        // as column_at, but not holding on to the builders
+        let column = ColumnAt {
+            field: &self.schema[i],
+            index: i,
+            row: self.builders[i].inner.len(),
+        };
+        let arr = self.builders[i]
+            .downcast_mut::<MutableMapArray>()
+            .expect("just checked");
+        arr.try_push(&column, entries)?;
+        Ok(())
+    }
+
+    pub fn push_point(&mut self, i: usize, lon_lat: Option<(f64, f64)>) -> Result<()> {
+        let wkb = lon_lat.map(|(lon, lat)| point_wkb(lon, lat));
+        self.push_wkb(i, wkb.as_ref().map(|wkb| &wkb[..]))
+    }
+
+    pub fn push_date(&mut self, i: usize, ymd: Option<(i32, u32, u32)>) -> Result<()> {
+        let days = ymd
+            .map(|(year, month, day)| days_from_civil(year, month, day))
+            .transpose()?;
+        match &self.schema[i].kind {
+            Kind::Date => {
+                let days = days.map(i32::try_from).transpose()?;
+                self.push_primitive(i, days)
+            }
+            Kind::DateMillis => self.push_primitive(i, days.map(|days| days * MILLIS_PER_DAY)),
+            _ => Err(self.cant_push(i, "a date")),
+        }
+    }
+
+    // the unscaled value, which has to fit in the column's precision; see push_primitive
+    pub fn push_decimal(&mut self, i: usize, unscaled: Option<i128>) -> Result<()> {
+        if !matches!(self.schema[i].kind, Kind::Decimal { .. }) {
+            return Err(self.cant_push(i, "a decimal"));
+        }
+        self.push_primitive(i, unscaled)
+    }
+
+    // truncated to the column's unit
+    pub fn push_duration(&mut self, i: usize, val: Option<Duration>) -> Result<()> {
+        let unit = match &self.schema[i].kind {
+            Kind::Duration(unit) => *unit,
+            _ => return Err(self.cant_push(i, "a duration")),
+        };
+        let val = val
+            .map(|val| {
+                let ticks = match unit {
+                    TimeUnit::Second => u128::from(val.as_secs()),
+                    TimeUnit::Millisecond => val.as_millis(),
+                    TimeUnit::Microsecond => val.as_micros(),
+                    TimeUnit::Nanosecond => val.as_nanos(),
+                };
+                i64::try_from(ticks).with_context(|| anyhow!("{:?} is too long", val))
+            })
+            .transpose()?;
+        self.push_primitive(i, val)
+    }
+
+    // in the column's unit; it's an error if that'd lose precision
+    #[cfg(feature = "chrono")]
+    pub fn push_timestamp(
+        &mut self,
+        i: usize,
+        val: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<()> {
+        let val = val.map(|val| (val.timestamp(), val.timestamp_subsec_nanos()));
+        self.push_epoch(i, val)
+    }
+
+    // as push_timestamp, from any offset
+    #[cfg(feature = "time")]
+    pub fn push_offset_date_time(
+        &mut self,
+        i: usize,
+        val: Option<time::OffsetDateTime>,
+    ) -> Result<()> {
+        let val = val.map(|val| (val.unix_timestamp(), val.nanosecond()));
+        self.push_epoch(i, val)
+    }
+
+    // as push_timestamp; times before 1970 are fine
+    pub fn push_system_time(&mut self, i: usize, val: Option<SystemTime>) -> Result<()> {
+        let val = val.map(|val| match val.duration_since(UNIX_EPOCH) {
+            Ok(after) => (after.as_secs() as i64, after.subsec_nanos()),
+            Err(before) => {
+                let before = before.duration();
+                match before.subsec_nanos() {
+                    0 => (-(before.as_secs() as i64), 0),
+                    nanos => (-(before.as_secs() as i64) - 1, 1_000_000_000 - nanos),
+                }
+            }
+        });
+        self.push_epoch(i, val)
+    }
+
+    // seconds and nanoseconds since 1970, for the timestamp kinds
+    fn push_epoch(&mut self, i: usize, val: Option<(i64, u32)>) -> Result<()> {
+        let field = &self.schema[i];
+        let unit = match &field.kind {
+            Kind::TimestampSecsZ => TimeUnit::Second,
+            Kind::Timestamp { unit, .. } => *unit,
+            _ => return Err(self.cant_push(i, "a timestamp")),
+        };
+        let (per_sec, nanos_per_tick) = match unit {
+            TimeUnit::Second => (1, 1_000_000_000),
+            TimeUnit::Millisecond => (1_000, 1_000_000),
+            TimeUnit::Microsecond => (1_000_000, 1_000),
+            TimeUnit::Nanosecond => (1_000_000_000, 1),
+        };
+        let val = val
+            .map(|(secs, nanos)| -> Result<i64> {
+                ensure!(
+                    nanos % nanos_per_tick == 0,
+                    "{}ns past {}s is too precise for {}, which is in {}",
+                    nanos,
+                    secs,
+                    self.column_at(i),
+                    unit_name(unit)
+                );
+                secs.checked_mul(per_sec)
+                    .and_then(|ticks| ticks.checked_add(i64::from(nanos / nanos_per_tick)))
+                    .ok_or_else(|| anyhow!("{}s is out of range for {}", secs, self.column_at(i)))
+            })
+            .transpose()?;
+        self.push_primitive(i, val)
     }
 
     pub fn push_primitive<T: NativeType>(&mut self, i: usize, val: Option<T>) -> Result<()> {
+        if self.coercion != Coercion::Exact
+            && self.builders[i]
+                .downcast_ref::<MutablePrimitiveArray<T>>()
+                .is_none()
+        {
+            return self
+                .push_coerced(i, val)
+                .with_context(|| anyhow!("pushing to {}", self.column_at(i)));
+        }
+
+        let field = &self.schema[i];
+        let val = match (val, &field.constraint) {
+            (Some(val), Some(constraint)) => constraint.check_number(&self.column_at(i), val)?,
+            (val, _) => val,
+        };
+        let val = match val {
+            Some(val) => val,
+            None => return self.push_null(i),
+        };
+        // decimals are stored as i128s, but only have the column's precision
+        if let (Kind::Decimal { precision, .. }, Some(&unscaled)) =
+            (&field.kind, (&val as &dyn Any).downcast_ref::<i128>())
+        {
+            ensure!(
+                decimal_fits(unscaled, *precision),
+                "{} has more than {} digits, for {}",
+                unscaled,
+                precision,
+                self.column_at(i)
+            );
+        }
         let arr = &mut self.builders[i];
         if let Some(arr) = arr.downcast_mut::<MutablePrimitiveArray<T>>() {
-            self.mem_used += std::mem::size_of::<T>();
-            arr.try_push(val)?;
+            arr.try_push(Some(val))?;
             Ok(())
         } else {
-            Err(anyhow!(
-                "can't push an {} to this column",
-                std::any::type_name::<T>()
-            ))
+            Err(self.cant_push(i, &format!("an {}", std::any::type_name::<T>())))
+        }
+    }
+
+    // as push_primitive for each value, but in one go, unless they have to be checked or coerced
+    pub fn extend_primitive<T: NativeType>(&mut self, i: usize, vals: &[T]) -> Result<()> {
+        if !self.can_extend::<T>(i) {
+            for val in vals {
+                self.push_primitive(i, Some(*val))?;
+            }
+            return Ok(());
         }
+        let arr = self.builders[i]
+            .downcast_mut::<MutablePrimitiveArray<T>>()
+            .expect("checked by can_extend");
+        arr.extend_from_slice(vals);
+        Ok(())
+    }
+
+    // the nulls are as push_null, so are an error unless the column's nullable
+    pub fn extend_nullable_primitive<T: NativeType>(
+        &mut self,
+        i: usize,
+        vals: &[Option<T>],
+    ) -> Result<()> {
+        if !self.can_extend::<T>(i) || self.schema[i].default.is_some() {
+            for val in vals {
+                self.push_primitive(i, *val)?;
+            }
+            return Ok(());
+        }
+        if vals.iter().any(Option::is_none) {
+            self.check_nullable(i)?;
+        }
+        let arr = self.builders[i]
+            .downcast_mut::<MutablePrimitiveArray<T>>()
+            .expect("checked by can_extend");
+        arr.extend_trusted_len(vals.iter().copied());
+        Ok(())
+    }
+
+    // no constraint to apply, and no coercion needed
+    fn can_extend<T: NativeType>(&self, i: usize) -> bool {
+        // decimals' digits are checked in push_primitive
+        self.schema[i].constraint.is_none()
+            && !matches!(self.schema[i].kind, Kind::Decimal { .. })
+            && self.builders[i]
+                .downcast_ref::<MutablePrimitiveArray<T>>()
+                .is_some()
+    }
+
+    fn push_coerced<T: NativeType>(&mut self, i: usize, val: Option<T>) -> Result<()> {
+        let coercion = self.coercion;
+        match self.schema[i].kind {
+            Kind::U8 => {
+                let val = val.map(|v| to_int::<T, u8>(v, coercion, (0, u8::MAX.into())));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::U16 => {
+                let val = val.map(|v| to_int::<T, u16>(v, coercion, (0, u16::MAX.into())));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::U32 => {
+                let val = val.map(|v| to_int::<T, u32>(v, coercion, (0, u32::MAX.into())));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::U64 => {
+                let val = val.map(|v| to_int::<T, u64>(v, coercion, (0, u64::MAX.into())));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::I8 => {
+                let range = (i8::MIN.into(), i8::MAX.into());
+                let val = val.map(|v| to_int::<T, i8>(v, coercion, range));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::I16 => {
+                let range = (i16::MIN.into(), i16::MAX.into());
+                let val = val.map(|v| to_int::<T, i16>(v, coercion, range));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::I32 | Kind::Date | Kind::TimeMillis => {
+                let range = (i32::MIN.into(), i32::MAX.into());
+                let val = val.map(|v| to_int::<T, i32>(v, coercion, range));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::I64
+            | Kind::TimestampSecsZ
+            | Kind::Timestamp { .. }
+            | Kind::DateMillis
+            | Kind::TimeMicros
+            | Kind::Duration(_) => {
+                let range = (i64::MIN.into(), i64::MAX.into());
+                let val = val.map(|v| to_int::<T, i64>(v, coercion, range));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::F32 => {
+                let val = val.map(|v| to_f32(v, coercion));
+                self.push_primitive(i, val.transpose()?)
+            }
+            Kind::F64 => {
+                let val = val.map(|v| to_float(v, coercion));
+                self.push_primitive(i, val.transpose()?)
+            }
+            _ => Err(self.cant_push(i, &format!("an {}", std::any::type_name::<T>()))),
+        }
+    }
+
+    /// # Safety
+    /// `i` must be in range, and column `i` must be a `Kind::String` or `Kind::Json` (so not a
+    /// `LargeString` or an `Enum`, whose builders are different); anything else is undefined
+    /// behaviour, which only debug builds catch.
+    ///
+    /// Nothing else is checked either, so, unlike push_str, a null can go into a column which
+    /// isn't nullable, a constant column can be pushed to, and the constraint and json check are
+    /// skipped; those leave a table which is wrong, but safe.
+    pub unsafe fn push_str_unchecked(&mut self, i: usize, val: Option<&str>) {
+        let arr = self
+            .builders
+            .get_unchecked_mut(i)
+            .downcast_unchecked::<MutableUtf8Array<i32>>();
+        arr.push(val);
+    }
+
+    /// # Safety
+    /// `i` must be in range, and column `i`'s kind must be stored as exactly `T`, e.g. an `I64`,
+    /// `Timestamp` or `Duration` column for an i64, a `Date` for an i32; there's no coercion, so
+    /// an i32 for an `I64` column is undefined behaviour, which only debug builds catch.
+    ///
+    /// As for push_str_unchecked, nullability, constants, the constraint and a decimal's
+    /// precision aren't checked.
+    pub unsafe fn push_primitive_unchecked<T: NativeType>(&mut self, i: usize, val: Option<T>) {
+        let arr = self
+            .builders
+            .get_unchecked_mut(i)
+            .downcast_unchecked::<MutablePrimitiveArray<T>>();
+        arr.push(val);
     }
 
     pub fn take_batch(&mut self) -> Vec<Arc<dyn Array>> {
-        let ret = self.builders.iter_mut().map(|arr| arr.as_arc()).collect();
+        let rows = self.rows();
+        let ret = self
+            .builders
+            .iter_mut()
+            .zip(self.constants.iter())
+            .map(|(arr, constant)| match constant {
+                None => arr.as_arc(),
+                Some(value) => repeated(value.as_ref(), rows),
+            })
+            .collect();
+        self.clear();
+        ret
+    }
+
+    // as take_batch, but only the first n rows; the rest stay buffered
+    pub fn take_rows(&mut self, n: usize) -> Result<Vec<Arc<dyn Array>>> {
+        self.check_consistent()?;
+        let rows = self.rows();
+        if n >= rows {
+            return Ok(self.take_batch());
+        }
+        let batch = self.snapshot_batch()?;
+        let rest = batch
+            .iter()
+            .map(|arr| arr.sliced(n, rows - n))
+            .collect::<Vec<_>>();
+        self.replace_rows(&rest)?;
+        Ok(batch
+            .into_iter()
+            .map(|arr| Arc::from(arr.sliced(0, n)))
+            .collect())
+    }
+
+    // reorders the buffered rows by these columns, as SortingPacker does, so each row group is
+    // sorted, and its statistics are tighter
+    pub fn sort_by(&mut self, keys: &[usize]) -> Result<()> {
+        for &key in keys {
+            ensure!(key < self.schema.len(), "there's no column {}", key);
+        }
+        let arrays = self.snapshot_batch()?;
+        let order = sort_order(&arrays, keys)?;
+        let sorted = arrays
+            .iter()
+            .map(|arr| gather(arr.as_ref(), &order))
+            .collect::<Vec<_>>();
+        self.replace_rows(&sorted)
+    }
+
+    // discards the buffered rows, e.g. if whatever they came from was rolled back; constants stay
+    pub fn clear(&mut self) {
         self.builders = make_builders(&self.schema, self.cap);
-        self.mem_used = 0;
+    }
+
+    // the buffered rows, as take_batch would give them, e.g. to validate before flushing; they're
+    // copied, so the table isn't changed
+    pub fn snapshot_batch(&self) -> Result<Vec<Box<dyn Array>>> {
+        self.check_consistent()?;
+        let rows = self.rows();
+        Ok(self
+            .schema
+            .iter()
+            .zip(self.builders.iter())
+            .zip(self.constants.iter())
+            .map(|((field, arr), constant)| match constant {
+                None => field.kind.clone_builder(arr.inner.as_ref()).as_box(),
+                Some(value) => repeated(value.as_ref(), rows).to_boxed(),
+            })
+            .collect())
+    }
+
+    // the buffered rows, as an arrow ipc stream; they're still in the table afterwards
+    pub fn snapshot(&self, out: impl Write) -> Result<()> {
+        let schema = Schema::from(
+            self.schema
+                .iter()
+                .map(|f| f.to_arrow_field())
+                .collect::<Vec<_>>(),
+        );
+        let rows = self.rows();
+        let arrays = self.snapshot_batch()?;
+
+        let mut writer = StreamWriter::new(out, IpcWriteOptions { compression: None });
+        writer.start(&schema, None)?;
+        if rows > 0 {
+            writer.write(&Chunk::new(arrays), None)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    // copies the rows from a snapshot after any already buffered; they were checked when they
+    // were pushed, so aren't again. The whole snapshot is read first, so if any of it's bad, the
+    // table isn't changed
+    pub fn restore(&mut self, mut from: impl Read) -> Result<()> {
+        let metadata = read_stream_metadata(&mut from)?;
+        let found = &metadata.schema.fields;
+        ensure!(
+            found.len() == self.schema.len(),
+            "snapshot has {} columns, expected {}",
+            found.len(),
+            self.schema.len()
+        );
+        for (found, field) in found.iter().zip(self.schema.iter()) {
+            let expected = field.to_arrow_field();
+            ensure!(
+                found.name == expected.name && found.data_type == expected.data_type,
+                "snapshot has column {:?} ({:?}), expected {:?} ({:?})",
+                found.name,
+                found.data_type,
+                expected.name,
+                expected.data_type
+            );
+        }
+
+        // collected in builders of our own, then copied across in one go
+        let mut restored = make_builders(&self.schema, 0);
+        for state in StreamReader::new(from, metadata, None) {
+            let chunk = match state? {
+                StreamState::Some(chunk) => chunk,
+                StreamState::Waiting => bail!("snapshot was truncated"),
+            };
+            for ((builder, field), arr) in restored
+                .iter_mut()
+                .zip(self.schema.iter())
+                .zip(chunk.arrays())
+            {
+                field
+                    .kind
+                    .check_extend(builder.inner.as_ref(), arr.as_ref())?;
+                field.kind.extend(builder.inner.as_mut(), arr.as_ref());
+            }
+        }
+        let arrays = restored
+            .iter_mut()
+            .map(|arr| arr.inner.as_box())
+            .collect::<Vec<_>>();
+        self.extend_from(&arrays)
+    }
+
+    // the other table's rows, after any already buffered, e.g. to merge tables built on other
+    // threads; the columns, and any constants, have to match
+    pub fn append(&mut self, mut other: Table) -> Result<()> {
+        ensure!(
+            other.schema.len() == self.schema.len(),
+            "appending a table with {} columns, expected {}",
+            other.schema.len(),
+            self.schema.len()
+        );
+        for (i, (theirs, mine)) in other.schema.iter().zip(self.schema.iter()).enumerate() {
+            ensure!(
+                theirs.name == mine.name
+                    && theirs.kind == mine.kind
+                    && theirs.nullable == mine.nullable,
+                "appending a table with column {:?} ({}), expected {:?} ({})",
+                theirs.name,
+                theirs.kind,
+                mine.name,
+                mine.kind
+            );
+            ensure!(
+                other.constants[i] == self.constants[i],
+                "appending a table with a different constant for column {:?}",
+                mine.name
+            );
+        }
+        other.check_consistent()?;
+        self.extend_from(&other.take_batch())
+    }
+
+    // copies the arrays, of the columns' types, after the buffered rows, without the checks the
+    // push_* make; every column is checked before any are copied to, so either all of the rows
+    // are copied, or none are. Constant columns are skipped
+    pub(crate) fn extend_from(&mut self, arrays: &[impl AsRef<dyn Array>]) -> Result<()> {
+        ensure!(
+            arrays.len() == self.schema.len(),
+            "copying {} columns, expected {}",
+            arrays.len(),
+            self.schema.len()
+        );
+        let rows = arrays
+            .first()
+            .map(|arr| arr.as_ref().len())
+            .unwrap_or_default();
+        for (i, arr) in arrays.iter().enumerate() {
+            let arr = arr.as_ref();
+            ensure!(
+                arr.len() == rows,
+                "copying {} rows to {}, expected {}",
+                arr.len(),
+                self.column_at(i),
+                rows
+            );
+            if self.constants[i].is_some() {
+                continue;
+            }
+            let field = &self.schema[i];
+            ensure!(
+                field.nullable || arr.null_count() == 0,
+                "{} isn't nullable, but nulls were copied to it",
+                self.column_at(i)
+            );
+            field
+                .kind
+                .check_extend(self.builders[i].inner.as_ref(), arr)
+                .with_context(|| anyhow!("copying to {}", self.column_at(i)))?;
+        }
+        for (i, arr) in arrays.iter().enumerate() {
+            if self.constants[i].is_none() {
+                let builder = self.builders[i].inner.as_mut();
+                self.schema[i].kind.extend(builder, arr.as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    // the buffered rows become these; if they can't, they're left as they were
+    fn replace_rows(&mut self, arrays: &[impl AsRef<dyn Array>]) -> Result<()> {
+        let old = std::mem::replace(&mut self.builders, make_builders(&self.schema, self.cap));
+        let ret = self.extend_from(arrays);
+        if ret.is_err() {
+            self.builders = old;
+        }
         ret
     }
 }
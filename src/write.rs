@@ -1,130 +1,1360 @@
-use std::io::Write;
-use std::sync::Arc;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use crate::erratum::join;
-use anyhow::{anyhow, bail, Result};
+use crate::geo::{geo_key_value, geometry_types, GeometryTypes};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use arrow2::array::Array;
 use arrow2::chunk::Chunk;
-use arrow2::datatypes::Schema;
-use arrow2::datatypes::{Field as ArrowField, Metadata};
+use arrow2::compute::cast::{cast, CastOptions};
+use arrow2::compute::concatenate::concatenate;
+use arrow2::datatypes::{DataType, Field, IntegerType, Schema};
 use arrow2::error::Error as ArrowError;
 use arrow2::io::parquet::write::{
-    CompressionOptions, FileWriter, RowGroupIterator, Version, WriteOptions,
+    array_to_columns, can_encode, compress, transverse, CompressedPage, CompressionOptions,
+    Compressor, DynIter, DynStreamingIterator, Encoding, FallibleStreamingIterator, KeyValue, Page,
+    ParquetType, RowGroupIter, SchemaDescriptor, Version, WriteOptions,
 };
-use crossbeam_channel::{SendError, Sender};
+use crossbeam_channel::{Receiver, SendError, Sender};
 use log::info;
+use parquet2::error::Error as ParquetError;
+use parquet2::page::DictPage;
+use parquet2::write::{FileWriter, WriteOptions as FileWriteOptions};
+use sha2::{Digest, Sha256};
+use tracing::{field, info_span};
 
-use crate::table::TableField;
+use crate::metrics::{Counter, Metrics};
+use crate::profile::{convert, WriteProfile};
+use crate::progress::{emit, Progress};
+use crate::report::{column_reports, ColumnReport};
+use crate::schema::{
+    arrow_schema_key_value, check_names, to_arrow_schema, to_parquet_schema, NameMatching,
+};
+use crate::split::{can_split, split};
+use crate::table::{Kind, TableField};
+
+type Batch = Result<Chunk<Arc<dyn Array>>, ArrowError>;
+
+// a sink, its hash, if we were hashing, and how its columns came out
+struct Finished<W> {
+    inner: W,
+    sha256: Option<[u8; 32]>,
+    report: Vec<ColumnReport>,
+}
 
-pub struct Writer<W> {
+#[derive(Clone, Default)]
+pub struct WriterOptions {
+    // encode and write on the calling thread, instead of one thread per sink
+    pub single_threaded: bool,
+    pub metrics: Option<Arc<dyn Metrics>>,
+    // use an unbounded channel, or keep up, as sending blocks the writer
+    pub progress: Option<Sender<Progress>>,
+    // hand batches to the sinks strictly in turn, instead of to whichever is free, so the same
+    // input always produces the same files
+    pub reproducible: bool,
+    // defaults to identifying this crate
+    pub created_by: Option<String>,
+    // written to the footer as "application", to identify the producer
+    pub application: Option<String>,
+    // overrides the page version, and the fields' encodings and types, to suit a reader
+    pub profile: Option<WriteProfile>,
+    // for find_field, and so also the dataframe / repack column lookups
+    pub name_matching: NameMatching,
+    // encode and compress on this many threads, leaving the sink threads to only write; the
+    // sinks then take turns, as if reproducible. 0 does it all on the sink threads
+    pub compression_threads: usize,
+    // hold submitted batches back until there are this many rows, or bytes, between them, then
+    // write them as one row group; whatever is left is written by finish
+    pub coalesce_rows: Option<usize>,
+    pub coalesce_bytes: Option<usize>,
+    // a final batch smaller than this is appended to the one before, instead of being written as
+    // a tiny row group of its own; this holds the last two batches back until finish
+    pub merge_trailing_rows: Option<usize>,
+    // hand each batch straight to a thread which is ready for it, instead of queueing it; submit
+    // then waits, but there's never a finished batch sitting around in a channel
+    pub rendezvous: bool,
+    // trial the first batch's columns with each encoding we can write for them (and the profile
+    // allows), and keep the smallest for the rest of the file, instead of the fields' encodings.
+    // The integer, string and binary columns are also tried as dictionaries, dictionary page
+    // included; they still read back as their own type
+    pub auto_encoding: bool,
+    // declare every timestamp without a timezone to be in UTC, so readers agree, including those
+    // in lists, structs and maps; parquet itself only records this (isAdjustedToUTC) for the
+    // units it has, i.e. not for bare seconds
+    pub utc_timestamps: bool,
+    // hash everything written to each sink, for finish_with_sha256
+    pub sha256: bool,
+    // per sink; writes sleep to keep the average under this, so we don't starve other disk users
+    pub max_bytes_per_second: Option<u64>,
+}
+
+pub struct Writer<W: Write> {
     schema: Box<[TableField]>,
-    threads: Vec<JoinHandle<Result<W>>>,
-    tx: Option<Sender<Result<Chunk<Arc<dyn Array>>, ArrowError>>>,
+    sinks: Sinks<W>,
+    metrics: Option<Arc<dyn Metrics>>,
+    progress: Option<Sender<Progress>>,
+    // the last batch which couldn't be submitted, for the caller to retry elsewhere
+    failed_batch: Option<Vec<Arc<dyn Array>>>,
+    name_matching: NameMatching,
+    // batches held back for coalescing
+    pending: Vec<Chunk<Arc<dyn Array>>>,
+    coalesce_rows: Option<usize>,
+    coalesce_bytes: Option<usize>,
+    // the most recent batches, in case the last one is to be merged
+    held: Vec<Chunk<Arc<dyn Array>>>,
+    merge_trailing_rows: Option<usize>,
+    layout: Arc<Layout>,
+    sha256: bool,
+    // per sink, as counted by the encoders
+    written: Vec<Arc<AtomicU64>>,
 }
 
-fn out_thread<W: Write + Send + 'static>(
-    mut inner: W,
-    schema: &[TableField],
-    rx: impl IntoIterator<Item = Result<Chunk<Arc<dyn Array>>, ArrowError>> + Send + 'static,
-) -> Result<JoinHandle<Result<W>>> {
-    let arrow_schema = Schema::from(
-        schema
+enum Sinks<W: Write> {
+    Threads {
+        threads: Vec<JoinHandle<Result<Finished<W>>>>,
+        // the compression threads, if any, which pass their errors on to the sinks
+        pool: Vec<JoinHandle<()>>,
+        tx: Option<Senders>,
+        // sink indexes, sent by threads which have given up
+        failed: Receiver<usize>,
+    },
+    // batches are encoded and written during submit, taking turns between the sinks
+    Inline {
+        encoders: Vec<Encoder<W>>,
+        next: usize,
+    },
+}
+
+enum Senders {
+    Shared(Sender<Batch>),
+    InTurn {
+        txs: Vec<Sender<Batch>>,
+        next: usize,
+    },
+    // numbered, so the sinks can put them back in order after the compression threads
+    Pool {
+        tx: Sender<(usize, Batch)>,
+        next: usize,
+    },
+}
+
+impl Senders {
+    fn send(&mut self, batch: Batch) -> Result<(), SendError<Batch>> {
+        match self {
+            Senders::Shared(tx) => tx.send(batch),
+            Senders::InTurn { txs, next } => {
+                let current = *next;
+                *next = (current + 1) % txs.len();
+                txs[current].send(batch)
+            }
+            Senders::Pool { tx, next } => {
+                let seq = *next;
+                *next += 1;
+                tx.send((seq, batch))
+                    .map_err(|SendError((_, batch))| SendError(batch))
+            }
+        }
+    }
+}
+
+// everything the encoder writes goes through here
+struct Sink<W> {
+    inner: W,
+    written: Arc<AtomicU64>,
+    hasher: Option<Sha256>,
+    throttle: Option<Throttle>,
+}
+
+struct Throttle {
+    bytes_per_second: u64,
+    start: Instant,
+    sent: u64,
+}
+
+impl Throttle {
+    fn new(bytes_per_second: u64) -> Self {
+        Throttle {
+            bytes_per_second: bytes_per_second.max(1),
+            start: Instant::now(),
+            sent: 0,
+        }
+    }
+
+    fn wrote(&mut self, len: usize) {
+        self.sent += len as u64;
+        let due = Duration::from_secs_f64(self.sent as f64 / self.bytes_per_second as f64);
+        if let Some(early) = due.checked_sub(self.start.elapsed()) {
+            std::thread::sleep(early);
+        }
+    }
+}
+
+impl<W: Write> Write for Sink<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.written.fetch_add(len as u64, Ordering::Relaxed);
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..len]);
+        }
+        if let Some(throttle) = &mut self.throttle {
+            throttle.wrote(len);
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct Encoder<W: Write> {
+    writer: FileWriter<Sink<W>>,
+    key_value_metadata: Vec<KeyValue>,
+    written: Arc<AtomicU64>,
+    sink: usize,
+    metrics: Option<Arc<dyn Metrics>>,
+    progress: Option<Sender<Progress>>,
+    layout: Arc<Layout>,
+    // for the geo footer, one per geometry column
+    geometry_types: Vec<GeometryTypes>,
+}
+
+// how batches are turned into pages; shared with the compression threads
+struct Layout {
+    arrow_schema: Schema,
+    parquet_schema: SchemaDescriptor,
+    options: WriteOptions,
+    // with the fields' compression and statistics
+    column_options: Vec<WriteOptions>,
+    encodings: Vec<Vec<Encoding>>,
+    // columns which the profile writes as a different type
+    converted: Vec<bool>,
+    // for auto_encoding, what to try for each column, and what was picked
+    candidates: Option<Vec<Vec<Encoding>>>,
+    chosen: OnceLock<Vec<Vec<Encoding>>>,
+    // the Kind::Geometry columns, for the footer
+    geometry: Vec<usize>,
+}
+
+// a row group, encoded and compressed, but not yet written
+struct Compressed {
+    rows: usize,
+    // one per parquet column
+    columns: Vec<Vec<CompressedPage>>,
+    // one per geometry column
+    geometry_types: Vec<GeometryTypes>,
+}
+
+impl Layout {
+    fn new(schema: &[TableField], options: &WriterOptions) -> Result<Self> {
+        let mut arrow_schema = to_arrow_schema(schema);
+        // one for each leaf, for the nested kinds
+        let mut encodings = schema
+            .iter()
+            .map(|f| {
+                transverse(&f.kind.to_arrow(), |leaf| match leaf {
+                    // e.g. an enum in a struct
+                    DataType::Dictionary(..) => Encoding::RleDictionary,
+                    _ => f.encoding,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut version = Version::V2;
+        let mut converted = vec![false; schema.len()];
+
+        if let Some(profile) = options.profile {
+            profile.check_names(schema)?;
+            version = profile.version();
+            for (((field, arrow_field), encoding), converted) in schema
+                .iter()
+                .zip(arrow_schema.fields.iter_mut())
+                .zip(encodings.iter_mut())
+                .zip(converted.iter_mut())
+            {
+                arrow_field.data_type = profile.written_type(&field.kind);
+                *converted = arrow_field.data_type != field.kind.to_arrow();
+                *encoding = transverse(&arrow_field.data_type, |leaf| {
+                    profile.encoding(leaf, field.encoding)
+                });
+            }
+        }
+
+        for field in arrow_schema.fields.iter_mut() {
+            field.data_type = writable(&field.data_type, field.is_nullable);
+        }
+
+        if options.utc_timestamps {
+            for field in arrow_schema.fields.iter_mut() {
+                in_utc(&mut field.data_type);
+            }
+        }
+
+        let candidates = options.auto_encoding.then(|| {
+            arrow_schema
+                .fields
+                .iter()
+                .map(|field| {
+                    [
+                        Encoding::Plain,
+                        Encoding::DeltaBinaryPacked,
+                        Encoding::DeltaLengthByteArray,
+                        Encoding::ByteStreamSplit,
+                        Encoding::RleDictionary,
+                    ]
+                    .into_iter()
+                    .filter(|&encoding| match encoding {
+                        // left as the profile has them, as they can only be dictionary encoded
+                        _ if matches!(field.data_type, DataType::Dictionary(..)) => false,
+                        Encoding::ByteStreamSplit => can_split(&field.data_type),
+                        Encoding::RleDictionary => can_dictionary(&field.data_type),
+                        _ => can_encode(&field.data_type, encoding),
+                    })
+                    .filter(|&encoding| match options.profile {
+                        Some(profile) => profile.encoding(&field.data_type, encoding) == encoding,
+                        None => true,
+                    })
+                    .collect()
+                })
+                .collect()
+        });
+
+        let parquet_schema = to_parquet_schema(schema, &arrow_schema)?;
+        let geometry = schema
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| matches!(field.kind, Kind::Geometry))
+            .map(|(i, _)| i)
+            .collect();
+
+        let options = WriteOptions {
+            // this is also the page indexes, which parquet2 can't write for only some columns
+            write_statistics: schema.iter().all(|field| field.statistics),
+            compression: default_compression(),
+            version,
+            data_pagesize_limit: None,
+        };
+        let column_options = schema
             .iter()
-            .map(|f| ArrowField {
-                name: f.name.to_string(),
-                data_type: f.kind.to_arrow(),
-                is_nullable: f.nullable,
-                metadata: Metadata::default(),
+            .map(|field| WriteOptions {
+                write_statistics: field.statistics,
+                compression: field.compression.unwrap_or(options.compression),
+                ..options
             })
-            .collect::<Vec<_>>(),
-    );
-
-    let write_options = WriteOptions {
-        write_statistics: true,
-        compression: CompressionOptions::Zstd(None),
-        version: Version::V2,
-        data_pagesize_limit: None,
+            .collect();
+
+        Ok(Layout {
+            arrow_schema,
+            parquet_schema,
+            options,
+            column_options,
+            encodings,
+            converted,
+            candidates,
+            chosen: OnceLock::new(),
+            geometry,
+        })
+    }
+
+    fn encodings(&self) -> &[Vec<Encoding>] {
+        self.chosen.get().unwrap_or(&self.encodings)
+    }
+
+    // called with the first batch, before anyone can be encoding with the encodings
+    fn choose_encodings(&self, batch: &Chunk<Arc<dyn Array>>) -> Result<()> {
+        let candidates = match &self.candidates {
+            Some(candidates) if self.chosen.get().is_none() => candidates,
+            _ => return Ok(()),
+        };
+
+        let batch = self.prepare(batch.clone())?;
+        let mut chosen = Vec::with_capacity(candidates.len());
+        for (((array, type_), options), (candidates, requested)) in batch
+            .arrays()
+            .iter()
+            .zip(self.parquet_schema.fields())
+            .zip(&self.column_options)
+            .zip(candidates.iter().zip(&self.encodings))
+        {
+            // for every leaf, except the dictionaries (in nested columns), which have to stay
+            let leaves = |encoding| {
+                requested
+                    .iter()
+                    .map(|&leaf| match leaf {
+                        Encoding::RleDictionary => leaf,
+                        _ => encoding,
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let mut best: Option<(usize, Encoding)> = None;
+            for &encoding in candidates {
+                let encodings = leaves(encoding);
+                let mut size = 0;
+                for pages in encode_column(array, type_.clone(), *options, &encodings)? {
+                    for page in pages {
+                        size += match page? {
+                            Page::Dict(page) => dict_size(&page, options.compression)?,
+                            page => match compress(page, Vec::new(), options.compression)? {
+                                CompressedPage::Data(page) => page.compressed_size(),
+                                CompressedPage::Dict(_) => unreachable!("it was a data page"),
+                            },
+                        };
+                    }
+                }
+                if best.is_none_or(|(smallest, _)| size < smallest) {
+                    best = Some((size, encoding));
+                }
+            }
+            chosen.push(match best {
+                Some((_, encoding)) => leaves(encoding),
+                None => requested.clone(),
+            });
+        }
+
+        info!("chose encodings: {:?}", chosen);
+        let _ = self.chosen.set(chosen);
+        Ok(())
+    }
+
+    fn prepare(&self, batch: Chunk<Arc<dyn Array>>) -> Result<Chunk<Arc<dyn Array>>> {
+        Ok(Chunk::try_new(
+            batch
+                .into_arrays()
+                .into_iter()
+                .zip(&self.arrow_schema.fields)
+                .zip(&self.converted)
+                .map(|((array, field), &converted)| match converted {
+                    true => convert(array, &field.data_type),
+                    false => Ok(array),
+                })
+                .collect::<Result<Vec<_>>>()?,
+        )?)
+    }
+
+    fn geometry_types(&self, batch: &Chunk<Arc<dyn Array>>) -> Vec<GeometryTypes> {
+        self.geometry
+            .iter()
+            .map(|&i| {
+                let mut seen = GeometryTypes::new();
+                geometry_types(batch.arrays()[i].as_ref(), &mut seen);
+                seen
+            })
+            .collect()
+    }
+
+    fn encode(&self, batch: Chunk<Arc<dyn Array>>) -> Result<Compressed> {
+        let rows = batch.len();
+        let geometry_types = self.geometry_types(&batch);
+        let batch = self.prepare(batch)?;
+        let mut columns = Vec::with_capacity(self.parquet_schema.fields().len());
+        for (((array, type_), encodings), options) in batch
+            .into_arrays()
+            .into_iter()
+            .zip(self.parquet_schema.fields())
+            .zip(self.encodings())
+            .zip(&self.column_options)
+        {
+            for pages in encode_column(array, type_.clone(), *options, encodings)? {
+                columns.push(
+                    pages
+                        .map(|page| Ok(compress(page?, Vec::new(), options.compression)?))
+                        .collect::<Result<Vec<_>>>()?,
+                );
+            }
+        }
+        Ok(Compressed {
+            rows,
+            columns,
+            geometry_types,
+        })
+    }
+
+    // as arrow2's row_group_iter, but with the options per column
+    fn row_group(
+        &self,
+        batch: Chunk<Arc<dyn Array>>,
+    ) -> Result<RowGroupIter<'static, ParquetError>> {
+        let batch = self.prepare(batch)?;
+        let mut columns = Vec::with_capacity(self.parquet_schema.fields().len());
+        for (((array, type_), encodings), options) in batch
+            .into_arrays()
+            .into_iter()
+            .zip(self.parquet_schema.fields())
+            .zip(self.encodings())
+            .zip(&self.column_options)
+        {
+            for pages in encode_column(array, type_.clone(), *options, encodings)? {
+                let pages =
+                    pages.map(|page| page.map_err(|e| ParquetError::OutOfSpec(e.to_string())));
+                columns.push(Ok(DynStreamingIterator::new(Compressor::new(
+                    DynIter::new(pages),
+                    options.compression,
+                    Vec::new(),
+                ))));
+            }
+        }
+        Ok(DynIter::new(columns.into_iter()))
+    }
+}
+
+// arrow2 writes every value of a required leaf, even those under a null struct, which leaves the
+// column with more values than levels, so the children of a nullable struct are written nullable
+fn writable(data_type: &DataType, nullable: bool) -> DataType {
+    let field = |f: &Field, nullable: bool| {
+        Field::new(&f.name, writable(&f.data_type, nullable), nullable)
+            .with_metadata(f.metadata.clone())
     };
-    let encodings = schema.iter().map(|f| vec![f.encoding]).collect();
+    match data_type {
+        DataType::Struct(children) => DataType::Struct(
+            children
+                .iter()
+                .map(|child| field(child, child.is_nullable || nullable))
+                .collect(),
+        ),
+        DataType::List(item) => DataType::List(Box::new(field(item, item.is_nullable))),
+        DataType::Map(entries, sorted) => {
+            DataType::Map(Box::new(field(entries, entries.is_nullable)), *sorted)
+        }
+        other => other.clone(),
+    }
+}
+
+// for utc_timestamps: every timestamp without a timezone, including those in nested columns
+fn in_utc(data_type: &mut DataType) {
+    match data_type {
+        DataType::Timestamp(_, tz @ None) => *tz = Some("+00:00".to_string()),
+        DataType::Struct(children) => {
+            for child in children {
+                in_utc(&mut child.data_type);
+            }
+        }
+        DataType::List(item) | DataType::Map(item, _) => in_utc(&mut item.data_type),
+        _ => (),
+    }
+}
+
+// as arrow2's array_to_columns, but also for byte_stream_split, and dictionary encoding columns
+// which aren't arrow dictionaries (as auto_encoding can pick)
+fn encode_column(
+    array: impl AsRef<dyn Array> + Send + Sync,
+    type_: ParquetType,
+    options: WriteOptions,
+    encodings: &[Encoding],
+) -> arrow2::error::Result<Vec<DynIter<'static, arrow2::error::Result<Page>>>> {
+    let data_type = array.as_ref().data_type();
+    if encodings == [Encoding::RleDictionary] && can_dictionary(data_type) {
+        let dictionary =
+            DataType::Dictionary(IntegerType::UInt32, Box::new(data_type.clone()), false);
+        let array = cast(array.as_ref(), &dictionary, CastOptions::default())?;
+        return array_to_columns(array, type_, options, encodings);
+    }
+    if encodings != [Encoding::ByteStreamSplit] || !can_split(data_type) {
+        return array_to_columns(array, type_, options, encodings);
+    }
+    Ok(array_to_columns(array, type_, options, &[Encoding::Plain])?
+        .into_iter()
+        .map(|pages| {
+            DynIter::new(
+                pages.map(|page| {
+                    split(page?).map_err(|e| ArrowError::ExternalFormat(e.to_string()))
+                }),
+            )
+        })
+        .collect())
+}
 
-    Ok(std::thread::spawn(move || -> Result<W> {
-        let rg_iter =
-            RowGroupIterator::try_new(rx.into_iter(), &arrow_schema, write_options, encodings)?;
-        let mut writer = FileWriter::try_new(&mut inner, arrow_schema, write_options)?;
+// what arrow2 can cast to a dictionary, without changing the values' type
+fn can_dictionary(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Utf8
+            | DataType::LargeUtf8
+            | DataType::Binary
+            | DataType::LargeBinary
+    )
+}
+
+// as compress would make it; CompressedDictPage doesn't say
+fn dict_size(page: &DictPage, compression: CompressionOptions) -> Result<usize> {
+    if compression == CompressionOptions::Uncompressed {
+        return Ok(page.buffer.len());
+    }
+    let mut compressed = Vec::new();
+    parquet2::compression::compress(compression, &page.buffer, &mut compressed)?;
+    Ok(compressed.len())
+}
 
-        for rg in rg_iter {
-            let row_group = rg?;
-            writer.write(row_group)?;
+// replays already compressed pages to the FileWriter
+struct Pages {
+    pages: std::vec::IntoIter<CompressedPage>,
+    current: Option<CompressedPage>,
+}
+
+impl FallibleStreamingIterator for Pages {
+    type Item = CompressedPage;
+    type Error = ParquetError;
+
+    fn advance(&mut self) -> Result<(), ParquetError> {
+        self.current = self.pages.next();
+        Ok(())
+    }
+
+    fn get(&self) -> Option<&CompressedPage> {
+        self.current.as_ref()
+    }
+}
+
+impl<W: Write + 'static> Encoder<W> {
+    fn new(inner: W, index: usize, layout: &Arc<Layout>, options: &WriterOptions) -> Result<Self> {
+        let created_by = options
+            .created_by
+            .clone()
+            .unwrap_or_else(|| format!("pack-it version {}", env!("CARGO_PKG_VERSION")));
+
+        let mut key_value_metadata = Vec::new();
+        if let Some(application) = &options.application {
+            key_value_metadata.push(KeyValue {
+                key: "application".to_string(),
+                value: Some(application.clone()),
+            });
         }
+        key_value_metadata.push(arrow_schema_key_value(&layout.arrow_schema));
+
+        let written = Arc::new(AtomicU64::new(0));
+        let sink = Sink {
+            inner,
+            written: Arc::clone(&written),
+            hasher: options.sha256.then(Sha256::default),
+            throttle: options.max_bytes_per_second.map(Throttle::new),
+        };
+
+        Ok(Self {
+            writer: FileWriter::new(
+                sink,
+                layout.parquet_schema.clone(),
+                FileWriteOptions {
+                    write_statistics: layout.options.write_statistics,
+                    version: layout.options.version,
+                },
+                Some(created_by),
+            ),
+            key_value_metadata,
+            written,
+            sink: index,
+            metrics: options.metrics.clone(),
+            progress: options.progress.clone(),
+            layout: Arc::clone(layout),
+            geometry_types: vec![GeometryTypes::new(); layout.geometry.len()],
+        })
+    }
 
-        writer.end(None)?;
-        Ok(inner)
-    }))
+    fn write(&mut self, batch: Chunk<Arc<dyn Array>>) -> Result<()> {
+        let rows = batch.len();
+        self.saw(self.layout.geometry_types(&batch));
+        self.recorded(rows, |encoder| {
+            let row_group = encoder.layout.row_group(batch)?;
+            encoder.writer.write(row_group)?;
+            Ok(())
+        })
+    }
+
+    fn write_compressed(&mut self, group: Compressed) -> Result<()> {
+        self.saw(group.geometry_types);
+        self.recorded(group.rows, |encoder| {
+            encoder
+                .writer
+                .write(DynIter::new(group.columns.into_iter().map(|pages| {
+                    Ok(DynStreamingIterator::new(Pages {
+                        pages: pages.into_iter(),
+                        current: None,
+                    }))
+                })))?;
+            Ok(())
+        })
+    }
+
+    fn saw(&mut self, geometry_types: Vec<GeometryTypes>) {
+        for (seen, types) in self.geometry_types.iter_mut().zip(geometry_types) {
+            seen.extend(types);
+        }
+    }
+
+    fn recorded(&mut self, rows: usize, work: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        let span = info_span!("encode", rows, bytes = field::Empty).entered();
+        let start = Instant::now();
+        let before = self.written.load(Ordering::Relaxed);
+
+        work(self)?;
+
+        let bytes = self.written.load(Ordering::Relaxed) - before;
+        span.record("bytes", bytes);
+        if let Some(metrics) = &self.metrics {
+            metrics.counter(Counter::BytesWritten, bytes);
+            metrics.counter(Counter::EncodeMicros, micros(start.elapsed()));
+        }
+        emit(
+            &self.progress,
+            Progress::RowGroupWritten {
+                sink: self.sink,
+                rows,
+                bytes,
+            },
+        );
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Finished<W>> {
+        if !self.layout.geometry.is_empty() {
+            let columns = self
+                .layout
+                .geometry
+                .iter()
+                .map(|&i| self.layout.arrow_schema.fields[i].name.as_str())
+                .zip(&self.geometry_types)
+                .collect::<Vec<_>>();
+            self.key_value_metadata.push(geo_key_value(&columns));
+        }
+        self.writer.end(Some(self.key_value_metadata))?;
+        let (mut sink, metadata) = self.writer.into_inner_and_metadata();
+        sink.inner.flush()?;
+        Ok(Finished {
+            inner: sink.inner,
+            sha256: sink.hasher.map(|hasher| hasher.finalize().into()),
+            report: column_reports(&metadata),
+        })
+    }
+}
+
+// a sink which can make what's been written to it durable, as File::sync_all; see finish_durably
+pub trait Durable {
+    fn sync(&mut self) -> io::Result<()>;
+}
+
+impl Durable for File {
+    fn sync(&mut self) -> io::Result<()> {
+        self.sync_all()
+    }
+}
+
+impl<W: Durable + Write> Durable for BufWriter<W> {
+    fn sync(&mut self) -> io::Result<()> {
+        self.flush()?;
+        self.get_mut().sync()
+    }
+}
+
+impl<D: Durable + ?Sized> Durable for &mut D {
+    fn sync(&mut self) -> io::Result<()> {
+        (**self).sync()
+    }
+}
+
+impl<D: Durable + ?Sized> Durable for Box<D> {
+    fn sync(&mut self) -> io::Result<()> {
+        (**self).sync()
+    }
+}
+
+// zstd needs a C compiler for the target, which e.g. wasm32 doesn't have
+#[cfg(feature = "zstd")]
+fn default_compression() -> CompressionOptions {
+    CompressionOptions::Zstd(None)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn default_compression() -> CompressionOptions {
+    CompressionOptions::Snappy
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+fn out_thread<W: Write + Send + 'static>(
+    encoder: Encoder<W>,
+    rx: impl IntoIterator<Item = Batch> + Send + 'static,
+    failed: Sender<usize>,
+) -> Result<JoinHandle<Result<Finished<W>>>> {
+    sink_thread(encoder, failed, move |encoder, row_group| {
+        for batch in rx {
+            encoder.write(batch?)?;
+            *row_group += 1;
+        }
+        Ok(())
+    })
+}
+
+// this sink's share of the row groups is every sinks'th one, but the pool finishes them in any order
+fn ordered_out_thread<W: Write + Send + 'static>(
+    encoder: Encoder<W>,
+    sinks: usize,
+    rx: Receiver<(usize, Result<Compressed>)>,
+    failed: Sender<usize>,
+) -> Result<JoinHandle<Result<Finished<W>>>> {
+    sink_thread(encoder, failed, move |encoder, row_group| {
+        let mut next = encoder.sink;
+        let mut pending = BTreeMap::new();
+        for (seq, group) in rx {
+            pending.insert(seq, group);
+            while let Some(group) = pending.remove(&next) {
+                encoder.write_compressed(group?)?;
+                *row_group += 1;
+                next += sinks;
+            }
+        }
+        ensure!(
+            pending.is_empty(),
+            "row group {} never arrived from the compression threads",
+            next
+        );
+        Ok(())
+    })
+}
+
+fn compression_thread(
+    index: usize,
+    layout: Arc<Layout>,
+    rx: Receiver<(usize, Batch)>,
+    sinks: Vec<Sender<(usize, Result<Compressed>)>>,
+) -> Result<JoinHandle<()>> {
+    Ok(std::thread::Builder::new()
+        .name(format!("pack-it-compress-{}", index))
+        .spawn(move || {
+            for (seq, batch) in rx {
+                // the sink is waiting for this one, so it has to get something
+                let group = catch_unwind(AssertUnwindSafe(|| layout.encode(batch?)))
+                    .unwrap_or_else(|payload| {
+                        Err(anyhow!(
+                            "compression thread {} panicked on row group {}: {}",
+                            index,
+                            seq,
+                            panic_message(&*payload)
+                        ))
+                    });
+
+                // the sink has failed, and will have told the submitter
+                if sinks[seq % sinks.len()].send((seq, group)).is_err() {
+                    break;
+                }
+            }
+        })?)
+}
+
+fn sink_thread<W: Write + Send + 'static>(
+    mut encoder: Encoder<W>,
+    failed: Sender<usize>,
+    work: impl FnOnce(&mut Encoder<W>, &mut usize) -> Result<()> + Send + 'static,
+) -> Result<JoinHandle<Result<Finished<W>>>> {
+    let sink = encoder.sink;
+    Ok(std::thread::Builder::new()
+        .name(format!("pack-it-writer-{}", sink))
+        .spawn(move || -> Result<Finished<W>> {
+            let columns = encoder
+                .layout
+                .arrow_schema
+                .fields
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let mut row_group = 0;
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                work(&mut encoder, &mut row_group)?;
+                encoder.finish()
+            }))
+            .unwrap_or_else(|payload| {
+                Err(anyhow!(
+                    "sink {} panicked on its row group {} (columns: {}): {}",
+                    sink,
+                    row_group,
+                    columns,
+                    panic_message(&*payload)
+                ))
+            });
+
+            // let the submitter know to come and collect the error, instead of waiting for finish
+            if result.is_err() {
+                let _ = failed.send(sink);
+            }
+
+            result
+        })?)
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "(unknown payload)"
+    }
 }
 
 impl<W: Write + Send + 'static> Writer<W> {
     pub fn new(
-        inner: impl IntoIterator<Item = W, IntoIter = impl Iterator<Item = W> + ExactSizeIterator>,
+        inner: impl IntoIterator<Item = W, IntoIter = impl ExactSizeIterator<Item = W>>,
         schema: &[TableField],
     ) -> Result<Self> {
-        let inner = inner.into_iter();
+        Self::with_options(inner, schema, WriterOptions::default())
+    }
 
-        let (tx, rx) = crossbeam_channel::bounded(inner.len());
+    pub fn single_threaded(
+        inner: impl IntoIterator<Item = W, IntoIter = impl ExactSizeIterator<Item = W>>,
+        schema: &[TableField],
+    ) -> Result<Self> {
+        let options = WriterOptions {
+            single_threaded: true,
+            ..Default::default()
+        };
+        Self::with_options(inner, schema, options)
+    }
 
-        let threads = inner
-            .into_iter()
-            .map(|inner| out_thread(inner, schema, rx.clone()))
+    pub fn with_options(
+        inner: impl IntoIterator<Item = W, IntoIter = impl ExactSizeIterator<Item = W>>,
+        schema: &[TableField],
+        options: WriterOptions,
+    ) -> Result<Self> {
+        check_names(schema, options.name_matching)?;
+        // a bad default would otherwise only show up with the first null
+        for field in schema {
+            field
+                .kind
+                .check()
+                .with_context(|| anyhow!("column {:?}", field.name))?;
+            field.checked_default()?;
+            ensure!(
+                field.nullable || field.kind != Kind::Null,
+                "column {:?} is always null, so has to be nullable",
+                field.name
+            );
+        }
+
+        let inner = inner.into_iter();
+        let layout = Arc::new(Layout::new(schema, &options)?);
+
+        let encoders = inner
+            .enumerate()
+            .map(|(sink, inner)| Encoder::new(inner, sink, &layout, &options))
             .collect::<Result<Vec<_>>>()?;
+        let written = encoders
+            .iter()
+            .map(|encoder| Arc::clone(&encoder.written))
+            .collect();
+
+        // there's no std::thread on wasm32 (it compiles, but panics)
+        let sinks = if options.single_threaded || cfg!(target_arch = "wasm32") {
+            Sinks::Inline { encoders, next: 0 }
+        } else {
+            let (failed_tx, failed) = crossbeam_channel::unbounded();
+            let capacity = |queued: usize| if options.rendezvous { 0 } else { queued };
+
+            let mut pool = Vec::new();
+            let (threads, tx) = if options.compression_threads > 0 && !encoders.is_empty() {
+                let sinks = encoders.len();
+                let mut txs = Vec::with_capacity(sinks);
+                let threads = encoders
+                    .into_iter()
+                    .map(|encoder| {
+                        let (tx, rx) =
+                            crossbeam_channel::bounded(capacity(options.compression_threads));
+                        txs.push(tx);
+                        ordered_out_thread(encoder, sinks, rx, failed_tx.clone())
+                    })
+                    .collect::<Result<_>>()?;
+
+                let (tx, rx) = crossbeam_channel::bounded(capacity(options.compression_threads));
+                for index in 0..options.compression_threads {
+                    pool.push(compression_thread(
+                        index,
+                        Arc::clone(&layout),
+                        rx.clone(),
+                        txs.clone(),
+                    )?);
+                }
+                (threads, Senders::Pool { tx, next: 0 })
+            } else if options.reproducible {
+                let mut txs = Vec::with_capacity(encoders.len());
+                let threads = encoders
+                    .into_iter()
+                    .map(|encoder| {
+                        let (tx, rx) = crossbeam_channel::bounded(capacity(1));
+                        txs.push(tx);
+                        out_thread(encoder, rx, failed_tx.clone())
+                    })
+                    .collect::<Result<_>>()?;
+                (threads, Senders::InTurn { txs, next: 0 })
+            } else {
+                let (tx, rx) = crossbeam_channel::bounded(capacity(encoders.len()));
+                let threads = encoders
+                    .into_iter()
+                    .map(|encoder| out_thread(encoder, rx.clone(), failed_tx.clone()))
+                    .collect::<Result<_>>()?;
+                (threads, Senders::Shared(tx))
+            };
+
+            Sinks::Threads {
+                threads,
+                pool,
+                tx: Some(tx),
+                failed,
+            }
+        };
 
         Ok(Self {
             schema: schema.to_vec().into_boxed_slice(),
-            threads,
-            tx: Some(tx),
+            sinks,
+            metrics: options.metrics,
+            progress: options.progress,
+            failed_batch: None,
+            name_matching: options.name_matching,
+            pending: Vec::new(),
+            coalesce_rows: options.coalesce_rows,
+            coalesce_bytes: options.coalesce_bytes,
+            held: Vec::new(),
+            merge_trailing_rows: options.merge_trailing_rows,
+            layout,
+            sha256: options.sha256,
+            written,
         })
     }
 
+    // to all the sinks, so far; batches which are still queued, or being encoded, aren't counted
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+            .iter()
+            .map(|written| written.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn schema(&self) -> &[TableField] {
+        &self.schema
+    }
+
+    pub fn name_matching(&self) -> NameMatching {
+        self.name_matching
+    }
+
     pub fn find_field(&self, name: &str) -> Option<(usize, &TableField)> {
-        self.schema.iter().enumerate().find(|(_, f)| f.name == name)
+        let name = self.name_matching.normalize(name);
+        self.schema
+            .iter()
+            .enumerate()
+            .find(|(_, f)| self.name_matching.normalize(&f.name) == name)
     }
 
     pub fn submit_batch(&mut self, batch: impl IntoIterator<Item = Arc<dyn Array>>) -> Result<()> {
-        let result = Chunk::try_new(batch.into_iter().collect())?;
+        let arrays = batch.into_iter().collect::<Vec<_>>();
+        // kept for the caller, as a batch which fails to write is
+        let batch = match self.check_batch(&arrays) {
+            Ok(()) => Chunk::new(arrays),
+            Err(e) => {
+                self.failed_batch = Some(arrays);
+                return Err(e);
+            }
+        };
+        if self.coalesce_rows.is_none() && self.coalesce_bytes.is_none() {
+            return self.submit_holding(batch);
+        }
+
+        self.pending.push(batch);
+        if self.pending_full() {
+            self.submit_pending()?;
+        }
+        Ok(())
+    }
 
-        let tx = self
-            .tx
-            .as_mut()
-            .ok_or_else(|| anyhow!("previously failed"))?;
+    fn pending_full(&self) -> bool {
+        let rows = || self.pending.iter().map(|b| b.len()).sum::<usize>();
+        let bytes = || {
+            self.pending
+                .iter()
+                .flat_map(|b| self.schema.iter().zip(b.arrays()))
+                .map(|(field, arr)| field.kind.array_mem_usage(arr.as_ref()))
+                .sum::<usize>()
+        };
+        self.coalesce_rows.is_some_and(|target| rows() >= target)
+            || self.coalesce_bytes.is_some_and(|target| bytes() >= target)
+    }
 
-        if let Err(SendError(_)) = tx.send(Ok(result)) {
-            // all of the writers have failed, so we need to die
-            // (this doesn't catch the case where one writer has died)
-            drop(self.tx.take());
+    fn submit_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let batch = concat(std::mem::take(&mut self.pending))?;
+        self.submit_holding(batch)
+    }
 
-            // this should fail
-            join_all(&mut self.threads)?;
+    fn submit_holding(&mut self, batch: Chunk<Arc<dyn Array>>) -> Result<()> {
+        if self.merge_trailing_rows.is_none() {
+            return self.submit_retaining(batch);
+        }
 
-            bail!("all of the threads have gone, but none have bothered to tell us why");
+        self.held.push(batch);
+        if self.held.len() > 2 {
+            let oldest = self.held.remove(0);
+            self.submit_retaining(oldest)?;
         }
+        Ok(())
+    }
 
+    fn submit_held(&mut self) -> Result<()> {
+        let mut held = std::mem::take(&mut self.held);
+        let merge = match (self.merge_trailing_rows, held.as_slice()) {
+            (Some(min), [_, last]) => last.len() < min,
+            _ => false,
+        };
+        if merge {
+            held = vec![concat(held)?];
+        }
+        for batch in held {
+            self.submit_retaining(batch)?;
+        }
         Ok(())
     }
 
-    pub fn finish(mut self) -> Result<Vec<W>> {
-        if self.threads.is_empty() {
-            bail!("had previously failed");
+    fn submit_retaining(&mut self, batch: Chunk<Arc<dyn Array>>) -> Result<()> {
+        // cheap; the arrays are shared
+        let retained = batch.clone();
+        let result = self.submit(batch);
+        if let Err(e) = &result {
+            self.failed_batch = Some(retained.into_arrays());
+            emit(
+                &self.progress,
+                Progress::Failed {
+                    error: format!("{:?}", e),
+                },
+            );
+        }
+        result
+    }
+
+    fn check_batch(&self, arrays: &[Arc<dyn Array>]) -> Result<()> {
+        ensure!(
+            arrays.len() == self.schema.len(),
+            "expected {} columns, not {}",
+            self.schema.len(),
+            arrays.len()
+        );
+        // nothing's converted, so they have to be exactly what the table would have built
+        for (array, field) in arrays.iter().zip(self.schema.iter()) {
+            ensure!(
+                array.len() == arrays[0].len(),
+                "column {:?} has {} rows, but the first column has {}",
+                field.name,
+                array.len(),
+                arrays[0].len()
+            );
+            ensure!(
+                array.data_type() == &field.kind.to_arrow(),
+                "column {:?} should be {:?}, not {:?}",
+                field.name,
+                field.kind.to_arrow(),
+                array.data_type()
+            );
+            ensure!(
+                field.nullable || array.null_count() == 0,
+                "column {:?} isn't nullable, but has {} nulls",
+                field.name,
+                array.null_count()
+            );
         }
+        Ok(())
+    }
+
+    // batches which were already accepted, but not yet written, are lost with the sink
+    pub fn take_failed_batch(&mut self) -> Option<Vec<Arc<dyn Array>>> {
+        self.failed_batch.take()
+    }
+
+    fn submit(&mut self, result: Chunk<Arc<dyn Array>>) -> Result<()> {
+        self.layout.choose_encodings(&result)?;
+
+        match &mut self.sinks {
+            Sinks::Threads {
+                threads,
+                tx,
+                failed,
+                ..
+            } => {
+                if tx.is_none() {
+                    bail!("previously failed");
+                }
+
+                if let Ok(sink) = failed.try_recv() {
+                    // the failed thread has finished, and the others will once they've drained
+                    drop(tx.take());
+
+                    // this should fail, with the thread's error
+                    join_all(threads)?;
+
+                    bail!("sink {} failed, but didn't say why", sink);
+                }
+
+                let sender = tx.as_mut().expect("checked above");
+
+                let start = Instant::now();
+                let sent = sender.send(Ok(result));
+                if let Some(metrics) = &self.metrics {
+                    metrics.counter(Counter::ChannelWaitMicros, micros(start.elapsed()));
+                }
+
+                if let Err(SendError(_)) = sent {
+                    // the writers have failed, so we need to die (unless they're taking turns,
+                    // this doesn't catch the case where only one writer has died)
+                    drop(tx.take());
 
+                    // this should fail
+                    join_all(threads)?;
+
+                    bail!("all of the threads have gone, but none have bothered to tell us why");
+                }
+            }
+            Sinks::Inline { encoders, next } => {
+                if encoders.is_empty() {
+                    bail!("previously failed");
+                }
+
+                let current = *next;
+                *next = (current + 1) % encoders.len();
+
+                if let Err(e) = encoders[current].write(result) {
+                    encoders.clear();
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<Vec<W>> {
+        Ok(self.finish_all()?.into_iter().map(|f| f.inner).collect())
+    }
+
+    // as finish, but each sink's flushed and synced before it returns, so a power cut can't
+    // leave a corrupt file which had been reported as written
+    pub fn finish_durably(self) -> Result<Vec<W>>
+    where
+        W: Durable,
+    {
+        let mut sinks = self.finish()?;
+        for (i, sink) in sinks.iter_mut().enumerate() {
+            sink.sync().with_context(|| anyhow!("syncing sink {}", i))?;
+        }
+        Ok(sinks)
+    }
+
+    // as finish, but also with the sha256 of each sink's file, if the option was set
+    pub fn finish_with_sha256(self) -> Result<Vec<(W, [u8; 32])>> {
+        ensure!(self.sha256, "the sha256 option wasn't set");
+        Ok(self
+            .finish_all()?
+            .into_iter()
+            .map(|f| (f.inner, f.sha256.expect("option set")))
+            .collect())
+    }
+
+    // as finish, but also with how each column of each sink's file came out
+    pub fn finish_with_report(self) -> Result<Vec<(W, Vec<ColumnReport>)>> {
+        Ok(self
+            .finish_all()?
+            .into_iter()
+            .map(|f| (f.inner, f.report))
+            .collect())
+    }
+
+    fn finish_all(mut self) -> Result<Vec<Finished<W>>> {
         info!("finishing...");
-        drop(self.tx);
 
-        join_all(&mut self.threads)
+        // these report their own failures
+        self.submit_pending()?;
+        self.submit_held()?;
+
+        let progress = self.progress.clone();
+        let result = self.finish_sinks();
+        emit(
+            &progress,
+            match &result {
+                Ok(_) => Progress::Finished,
+                Err(e) => Progress::Failed {
+                    error: format!("{:?}", e),
+                },
+            },
+        );
+        result
     }
+
+    fn finish_sinks(self) -> Result<Vec<Finished<W>>> {
+        match self.sinks {
+            Sinks::Threads {
+                mut threads,
+                pool,
+                tx,
+                ..
+            } => {
+                if threads.is_empty() {
+                    bail!("had previously failed");
+                }
+
+                drop(tx);
+                for thread in pool {
+                    join(thread);
+                }
+
+                join_all(&mut threads)
+            }
+            Sinks::Inline { encoders, .. } => {
+                if encoders.is_empty() {
+                    bail!("had previously failed");
+                }
+
+                encoders.into_iter().map(|e| e.finish()).collect()
+            }
+        }
+    }
+}
+
+fn concat(batches: Vec<Chunk<Arc<dyn Array>>>) -> Result<Chunk<Arc<dyn Array>>> {
+    if batches.len() == 1 {
+        return Ok(batches.into_iter().next().expect("checked"));
+    }
+
+    let columns = batches
+        .first()
+        .map(|b| b.arrays().len())
+        .unwrap_or_default();
+    let arrays = (0..columns)
+        .map(|col| -> Result<Arc<dyn Array>> {
+            let parts = batches
+                .iter()
+                .map(|b| b.arrays()[col].as_ref())
+                .collect::<Vec<_>>();
+            Ok(Arc::from(concatenate(&parts)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Chunk::try_new(arrays)?)
 }
 
+// in the order the sinks were given, as Inline's finish returns them
 fn join_all<T>(threads: &mut Vec<JoinHandle<Result<T>>>) -> Result<Vec<T>> {
     let mut ret = Vec::with_capacity(threads.len());
-    while let Some(thread) = threads.pop() {
+    for thread in threads.drain(..) {
         ret.push(join(thread)?);
     }
     Ok(ret)
@@ -0,0 +1,55 @@
+mod common;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+use common::table;
+
+#[test]
+fn append_after_the_buffered_rows() -> Result<()> {
+    let mut mine = table()?;
+    let theirs = table()?;
+    let expected = theirs.snapshot_batch()?;
+    mine.append(theirs)?;
+    assert_eq!(10, mine.rows());
+    for (expected, actual) in expected.iter().zip(mine.take_batch()) {
+        assert_eq!(expected.as_ref(), &*actual.sliced(0, 5));
+        assert_eq!(expected.as_ref(), &*actual.sliced(5, 5));
+    }
+    Ok(())
+}
+
+#[test]
+fn append_checks_every_column_first() -> Result<()> {
+    let mut mine = Table::from_fields(&parse_schema("id:i64, name:string")?, 4);
+    mine.push_primitive(0, Some(1i64))?;
+    mine.push_str(1, Some("one"))?;
+    let theirs = Table::from_fields(&parse_schema("id:i64, name:i64")?, 4);
+    assert!(mine.append(theirs).is_err());
+    mine.check_consistent()?;
+    assert_eq!(1, mine.rows());
+    Ok(())
+}
+
+#[test]
+fn append_of_a_partial_row() -> Result<()> {
+    let schema = parse_schema("id:i64, name:string")?;
+    let mut mine = Table::from_fields(&schema, 4);
+    mine.push_primitive(0, Some(1i64))?;
+    mine.push_str(1, Some("one"))?;
+    let mut theirs = Table::from_fields(&schema, 4);
+    theirs.push_primitive(0, Some(2i64))?;
+    assert!(mine.append(theirs).is_err());
+    let batch = mine.take_batch();
+    assert_eq!(
+        &Int64Array::from_slice([1]) as &dyn Array,
+        batch[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["one"]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    Ok(())
+}
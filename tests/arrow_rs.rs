@@ -0,0 +1,79 @@
+#![cfg(feature = "arrow")]
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use arrow2::array::{Array as Array2, Int64Array as Int64Array2};
+use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+use arrow_array::{Array, Int64Array, RecordBatch, StringArray};
+use pack_it::{Kind, Packer, Table, TableField};
+
+fn fields() -> Vec<TableField> {
+    vec![
+        TableField::new("id", Kind::I64, false),
+        TableField::new("name", Kind::String, true),
+    ]
+}
+
+fn table() -> Result<Table> {
+    let mut table = Table::with_capacity(&[Kind::I64, Kind::String], 4);
+    table.push_primitive(0, Some(1i64))?;
+    table.push_str(1, Some("one"))?;
+    table.push_primitive(0, Some(2i64))?;
+    table.push_str(1, None)?;
+    Ok(table)
+}
+
+#[test]
+fn take_record_batch() -> Result<()> {
+    let mut table = table()?;
+    let batch = table.take_record_batch(&fields())?;
+    assert_eq!(0, table.rows());
+    assert_eq!(2, batch.num_rows());
+    assert_eq!("name", batch.schema().field(1).name());
+    assert!(batch.schema().field(1).is_nullable());
+    let ids = batch.column(0).as_any().downcast_ref::<Int64Array>();
+    assert_eq!(Some(&Int64Array::from(vec![1, 2])), ids);
+    let names = batch.column(1).as_any().downcast_ref::<StringArray>();
+    assert_eq!(Some(&StringArray::from(vec![Some("one"), None])), names);
+    assert!(!batch.column(1).is_valid(1));
+    Ok(())
+}
+
+#[test]
+fn take_record_batch_of_a_partial_row() -> Result<()> {
+    let mut table = table()?;
+    table.push_primitive(0, Some(3i64))?;
+    assert!(table.take_record_batch(&fields()).is_err());
+    assert_eq!(3, table.rows());
+    table.push_str(1, Some("three"))?;
+    assert_eq!(3, table.take_record_batch(&fields())?.num_rows());
+    Ok(())
+}
+
+#[test]
+fn submit_record_batch_by_name() -> Result<()> {
+    let batch = table()?.take_record_batch(&fields())?;
+    let ids = batch.column(0).clone();
+    let names = batch.column(1).clone();
+    let reordered = RecordBatch::try_from_iter([("name", names), ("id", ids)])?;
+
+    let mut packer = Packer::new(Vec::new(), &fields())?;
+    packer.submit_record_batch(&reordered)?;
+    let mut file = Cursor::new(packer.finish()?);
+    let metadata = read_metadata(&mut file)?;
+    let schema = infer_schema(&metadata)?;
+    let mut reader = FileReader::new(file, metadata.row_groups, schema, None, None, None);
+    let chunk = reader.next().expect("a row group")?;
+    let ids = Int64Array2::from_slice([1, 2]);
+    assert_eq!(&ids as &dyn Array2, chunk.arrays()[0].as_ref());
+
+    let schema = [
+        TableField::new("id", Kind::I64, false),
+        TableField::new("email", Kind::String, true),
+    ];
+    let mut packer = Packer::new(Vec::new(), &schema)?;
+    let err = packer.submit_record_batch(&reordered).unwrap_err();
+    assert!(err.to_string().contains("email"), "{}", err);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use anyhow::Result;
+use arrow2::array::Utf8Array;
+use arrow2::io::parquet::write::Encoding;
+use pack_it::report::ColumnReport;
+use pack_it::schema::parse_schema;
+use pack_it::{Packer, PackerOptions, WriterOptions};
+
+use common::read;
+
+// a sorted column, a random one, and one with only a few values, repeated
+fn written() -> Result<(Vec<u8>, Vec<ColumnReport>, Vec<String>)> {
+    let options = PackerOptions {
+        writer: WriterOptions {
+            auto_encoding: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let schema = parse_schema("sorted:i64, noise:f64, colour:string")?;
+    let mut packer = Packer::with_options(Vec::new(), &schema, options)?;
+    let mut colours = Vec::new();
+    let mut state = 1u64;
+    for i in 0..10_000i64 {
+        // xorshift, for values with nothing in common
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let colour = format!("colour number {}", state % 50);
+        let table = packer.table();
+        table.push_primitive(0, Some(1_700_000_000 + i * 3))?;
+        table.push_primitive(1, Some(f64::from_bits(state >> 2)))?;
+        table.push_str(2, Some(&colour))?;
+        colours.push(colour);
+    }
+    let (file, reports) = packer.finish_with_report()?;
+    Ok((file, reports, colours))
+}
+
+#[test]
+fn picks_the_smallest_encoding() -> Result<()> {
+    let (_, reports, _) = written()?;
+    let (sorted, noise, colour) = (&reports[0], &reports[1], &reports[2]);
+    assert!(
+        sorted.encodings.contains(&Encoding::DeltaBinaryPacked),
+        "{}",
+        sorted
+    );
+    assert!(noise.encodings.contains(&Encoding::Plain), "{}", noise);
+    assert!(
+        !noise.encodings.contains(&Encoding::ByteStreamSplit),
+        "{}",
+        noise
+    );
+    assert!(
+        colour.encodings.contains(&Encoding::RleDictionary),
+        "{}",
+        colour
+    );
+    Ok(())
+}
+
+#[test]
+fn dictionaries_read_back_as_the_columns_type() -> Result<()> {
+    let (file, _, colours) = written()?;
+    let (_, groups) = read(&file)?;
+    let mut actual = Vec::new();
+    for group in &groups {
+        let arr = group.arrays()[2]
+            .as_any()
+            .downcast_ref::<Utf8Array<i32>>()
+            .expect("strings, not a dictionary");
+        actual.extend(arr.values_iter().map(|v| v.to_string()));
+    }
+    assert_eq!(colours, actual);
+    Ok(())
+}
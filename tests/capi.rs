@@ -0,0 +1,95 @@
+#![cfg(feature = "capi")]
+
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+use pack_it::capi::*;
+
+fn create(path: &Path, kinds: &[u32]) -> *mut PackItPacker {
+    let path = CString::new(path.to_str().expect("utf-8")).expect("no nuls");
+    let names = ["id", "name"].map(|n| CString::new(n).expect("no nuls"));
+    let names = names.iter().map(|n| n.as_ptr()).collect::<Vec<_>>();
+    let nullable = [false, true];
+    unsafe {
+        pack_it_packer_create(
+            path.as_ptr(),
+            kinds.len(),
+            names.as_ptr(),
+            kinds.as_ptr(),
+            nullable.as_ptr(),
+        )
+    }
+}
+
+fn last_error() -> String {
+    let error = pack_it_last_error();
+    assert!(!error.is_null());
+    unsafe { CStr::from_ptr(error) }
+        .to_string_lossy()
+        .to_string()
+}
+
+#[test]
+fn writes_a_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    // i64, string
+    let packer = create(&path, &[4, 6]);
+    assert!(!packer.is_null());
+    unsafe {
+        for (id, name) in [(1, Some("one")), (2, None)] {
+            assert_eq!(0, pack_it_push_i64(packer, 0, id));
+            match name {
+                Some(name) => assert_eq!(
+                    0,
+                    pack_it_push_str(packer, 1, name.as_ptr().cast::<c_char>(), name.len())
+                ),
+                None => assert_eq!(0, pack_it_push_null(packer, 1)),
+            }
+            assert_eq!(0, pack_it_end_row(packer));
+        }
+        assert_eq!(0, pack_it_finish(packer));
+    }
+
+    let mut file = Cursor::new(fs::read(&path)?);
+    let metadata = read_metadata(&mut file)?;
+    let schema = infer_schema(&metadata)?;
+    let mut reader = FileReader::new(file, metadata.row_groups, schema, None, None, None);
+    let chunk = reader.next().expect("a row group")?;
+    assert!(reader.next().is_none());
+    let arrays = chunk.arrays();
+    assert_eq!(
+        &Int64Array::from_slice([1, 2]) as &dyn Array,
+        arrays[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from([Some("one"), None]) as &dyn Array,
+        arrays[1].as_ref()
+    );
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    assert!(create(&path, &[4, 999]).is_null());
+    assert!(last_error().contains("999"), "{}", last_error());
+
+    let packer = create(&path, &[4, 6]);
+    unsafe {
+        assert_eq!(-1, pack_it_push_bool(packer, 0, true));
+        assert!(!last_error().is_empty());
+        assert_eq!(-1, pack_it_push_null(packer, 0));
+        assert!(last_error().contains("nullable"), "{}", last_error());
+        assert_eq!(-1, pack_it_push_i64(std::ptr::null_mut(), 0, 1));
+        assert!(last_error().contains("null packer"), "{}", last_error());
+        pack_it_free(packer);
+    }
+    Ok(())
+}
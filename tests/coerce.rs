@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow2::array::{Array, PrimitiveArray};
+use arrow2::types::NativeType;
+use pack_it::coerce::Coercion;
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+// `val` pushed to a column of `kind`, read back
+fn push<T: NativeType>(coercion: Coercion, kind: &str, val: T) -> Result<Arc<dyn Array>> {
+    let mut table = Table::from_fields(&parse_schema(&format!("v:{}", kind))?, 1);
+    table.set_coercion(coercion);
+    let pushed = table.push_primitive(0, Some(val));
+    // a value which can't be coerced isn't pushed
+    if pushed.is_err() {
+        assert_eq!(0, table.rows());
+    }
+    pushed?;
+    Ok(table.take_batch().remove(0))
+}
+
+fn one<T: NativeType>(val: T) -> PrimitiveArray<T> {
+    PrimitiveArray::from_slice([val])
+}
+
+#[test]
+fn exact() -> Result<()> {
+    assert_eq!(
+        &one(7i64) as &dyn Array,
+        push(Coercion::Exact, "i64", 7i64)?.as_ref()
+    );
+    assert!(push(Coercion::Exact, "i64", 7i32).is_err());
+    assert!(push(Coercion::Exact, "f64", 7f32).is_err());
+    Ok(())
+}
+
+#[test]
+fn widen() -> Result<()> {
+    let widen = Coercion::Widen;
+    assert_eq!(&one(7i64) as &dyn Array, push(widen, "i64", 7i32)?.as_ref());
+    assert_eq!(&one(7i64) as &dyn Array, push(widen, "i64", 7u32)?.as_ref());
+    assert_eq!(&one(7u16) as &dyn Array, push(widen, "u16", 7u8)?.as_ref());
+    assert_eq!(&one(7f64) as &dyn Array, push(widen, "f64", 7i32)?.as_ref());
+    assert_eq!(&one(7f32) as &dyn Array, push(widen, "f32", 7i16)?.as_ref());
+    assert_eq!(
+        &one(0.5f64) as &dyn Array,
+        push(widen, "f64", 0.5f32)?.as_ref()
+    );
+
+    // these values would fit, but others of their types wouldn't
+    assert!(push(widen, "i32", 7i64).is_err());
+    assert!(push(widen, "i64", 7u64).is_err());
+    assert!(push(widen, "u32", 7i32).is_err());
+    assert!(push(widen, "f64", 7i64).is_err());
+    assert!(push(widen, "f32", 7i32).is_err());
+    assert!(push(widen, "f32", 0.5f64).is_err());
+    // floats never widen into integers
+    assert!(push(widen, "i64", 7f32).is_err());
+    Ok(())
+}
+
+#[test]
+fn checked() -> Result<()> {
+    let checked = Coercion::Checked;
+    assert_eq!(
+        &one(7i64) as &dyn Array,
+        push(checked, "i64", 7u64)?.as_ref()
+    );
+    assert_eq!(
+        &one(7i32) as &dyn Array,
+        push(checked, "i32", 7i64)?.as_ref()
+    );
+    assert_eq!(
+        &one(255u8) as &dyn Array,
+        push(checked, "u8", 255i64)?.as_ref()
+    );
+    assert_eq!(
+        &one(3i32) as &dyn Array,
+        push(checked, "i32", 3f64)?.as_ref()
+    );
+    assert_eq!(
+        &one(9007199254740992f64) as &dyn Array,
+        push(checked, "f64", 1i64 << 53)?.as_ref()
+    );
+    assert_eq!(
+        &one(0.5f32) as &dyn Array,
+        push(checked, "f32", 0.5f64)?.as_ref()
+    );
+    assert_eq!(
+        &one(16777216f32) as &dyn Array,
+        push(checked, "f32", 1i32 << 24)?.as_ref()
+    );
+
+    // the values which don't survive
+    assert!(push(checked, "i64", u64::MAX).is_err());
+    assert!(push(checked, "u8", 256i64).is_err());
+    assert!(push(checked, "u32", -1i32).is_err());
+    assert!(push(checked, "i8", i16::MIN).is_err());
+    assert!(push(checked, "i32", 3.5f64).is_err());
+    assert!(push(checked, "i32", f64::NAN).is_err());
+    assert!(push(checked, "i64", f64::INFINITY).is_err());
+    assert!(push(checked, "i32", 1e10f64).is_err());
+    assert!(push(checked, "f64", (1i64 << 53) + 1).is_err());
+    assert!(push(checked, "f32", (1i32 << 24) + 1).is_err());
+    assert!(push(checked, "f32", 0.1f64).is_err());
+    Ok(())
+}
+
+#[test]
+fn uncoercible() -> Result<()> {
+    for coercion in [Coercion::Widen, Coercion::Checked] {
+        // no number type to convert from
+        assert!(push(coercion, "i64", 7i128).is_err());
+        // or to
+        assert!(push(coercion, "bool", 1i32).is_err());
+        assert!(push(coercion, "string", 1i32).is_err());
+    }
+    Ok(())
+}
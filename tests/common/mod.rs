@@ -0,0 +1,65 @@
+#![allow(dead_code)]
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use arrow2::array::Array;
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::Schema;
+use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+use pack_it::schema::parse_schema;
+use pack_it::{Table, Writer};
+
+// one of (nearly) everything, with some nulls, including inside the nested columns
+pub fn table() -> Result<Table> {
+    let schema = parse_schema(
+        "id:i64, name:string?, tags:list<string>?, point:struct<x:f64, y:f64?>?, \
+         attrs:map<string, i64>?, colour:enum<red, green>?, hash:fixed(4)?, price:decimal(10,2)?",
+    )?;
+    let mut table = Table::from_fields(&schema, 8);
+    for i in 0..5i64 {
+        let odd = i % 2 == 1;
+        table.push_primitive(0, Some(i))?;
+        table.push_str(1, (!odd).then_some("name"))?;
+        table.push_list(
+            2,
+            Some([Some("a"), None, Some("c")].into_iter().take(i as usize)),
+        )?;
+        table.push_struct(3, |row| {
+            row.set(0, Some(i as f64))?;
+            if !odd {
+                row.set(1, Some(-(i as f64)))?;
+            }
+            Ok(())
+        })?;
+        table.push_map(4, odd.then_some([("k", Some(i)), ("n", None)]))?;
+        table.push_str(5, Some(if odd { "green" } else { "red" }))?;
+        table.push_fsb(6, (!odd).then_some([1, 2, 3, i as u8]))?;
+        table.push_decimal(7, Some(i as i128 * 100 + 5))?;
+    }
+    Ok(table)
+}
+
+pub fn assert_same(expected: &[impl AsRef<dyn Array>], actual: &[impl AsRef<dyn Array>]) {
+    assert_eq!(expected.len(), actual.len());
+    for (expected, actual) in expected.iter().zip(actual) {
+        assert_eq!(expected.as_ref(), actual.as_ref());
+    }
+}
+
+// the table's rows, as one row group of a file
+pub fn written(mut table: Table) -> Result<Vec<u8>> {
+    let mut writer = Writer::single_threaded([Vec::new()], table.schema())?;
+    writer.submit_batch(table.take_batch())?;
+    Ok(writer.finish()?.pop().expect("one sink"))
+}
+
+pub type RowGroups = Vec<Chunk<Box<dyn Array>>>;
+
+pub fn read(file: &[u8]) -> Result<(Schema, RowGroups)> {
+    let mut file = Cursor::new(file);
+    let metadata = read_metadata(&mut file)?;
+    let schema = infer_schema(&metadata)?;
+    let reader = FileReader::new(file, metadata.row_groups, schema.clone(), None, None, None);
+    Ok((schema, reader.collect::<Result<_, _>>()?))
+}
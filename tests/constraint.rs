@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use pack_it::constraint::{Constraint, Violation};
+use pack_it::row::Value;
+use pack_it::{Kind, Table, TableField};
+use regex::Regex;
+
+fn schema(on_violation: Violation) -> Result<[TableField; 2]> {
+    Ok([
+        TableField::new("n", Kind::I64, true).constraint(Constraint {
+            min: Some(0.),
+            max: Some(10.),
+            on_violation,
+            ..Default::default()
+        }),
+        TableField::new("code", Kind::String, true).constraint(Constraint {
+            max_len: Some(3),
+            pattern: Some(Regex::new("^[a-z]+$")?),
+            on_violation,
+            ..Default::default()
+        }),
+    ])
+}
+
+#[test]
+fn violating_rows_are_rejected() -> Result<()> {
+    let mut table = Table::from_fields(&schema(Violation::Error)?, 4);
+    table.push_row(&[Value::I64(1), Value::Str("abc")])?;
+    for bad in [
+        [Value::I64(11), Value::Str("abc")],
+        [Value::I64(-1), Value::Str("abc")],
+        [Value::I64(1), Value::Str("abcd")],
+        [Value::I64(1), Value::Str("ABC")],
+    ] {
+        let err = table.push_row(&bad).expect_err("violates");
+        assert!(
+            format!("{:#}", err).contains("constraint violated"),
+            "{:#}",
+            err
+        );
+        // nothing of the row was pushed
+        assert_eq!(1, table.rows());
+        table.check_consistent()?;
+    }
+
+    // one column at a time, the rejected value isn't pushed, so the row can still be finished
+    assert!(table.push_primitive(0, Some(20i64)).is_err());
+    table.push_primitive(0, Some(10i64))?;
+    assert!(table.push_str(1, Some("toolong")).is_err());
+    table.push_str(1, Some("xyz"))?;
+    table.check_consistent()?;
+
+    let batch = table.take_batch();
+    assert_eq!(
+        &Int64Array::from_slice([1, 10]) as &dyn Array,
+        batch[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["abc", "xyz"]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    Ok(())
+}
+
+#[test]
+fn violations_can_be_null() -> Result<()> {
+    let mut table = Table::from_fields(&schema(Violation::Null)?, 4);
+    table.push_row(&[Value::I64(11), Value::Str("abcd")])?;
+    table.push_primitive(0, Some(5i64))?;
+    table.push_str(1, Some("ABC"))?;
+    table.check_consistent()?;
+    let batch = table.take_batch();
+    assert_eq!(
+        &Int64Array::from([None, Some(5)]) as &dyn Array,
+        batch[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from([None::<&str>, None]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    Ok(())
+}
+
+#[test]
+fn violations_can_be_clamped() -> Result<()> {
+    let mut table = Table::from_fields(&schema(Violation::Clamp)?, 4);
+    table.push_row(&[Value::I64(11), Value::Str("abcd")])?;
+    table.push_primitive(0, Some(-3i64))?;
+    table.push_str(1, Some("ab"))?;
+    // a pattern can't be clamped to
+    assert!(table.push_row(&[Value::I64(1), Value::Str("ABC")]).is_err());
+    table.check_consistent()?;
+    let batch = table.take_batch();
+    assert_eq!(
+        &Int64Array::from_slice([10, 0]) as &dyn Array,
+        batch[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["abc", "ab"]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    Ok(())
+}
+
+#[test]
+fn only_allowed_values() -> Result<()> {
+    let allowed = ["red", "green"].map(String::from);
+    let schema = [
+        TableField::new("colour", Kind::String, false).constraint(Constraint {
+            allowed: Some(HashSet::from(allowed)),
+            ..Default::default()
+        }),
+    ];
+    let mut table = Table::from_fields(&schema, 4);
+    table.push_str(0, Some("red"))?;
+    assert!(table.push_str(0, Some("blue")).is_err());
+    assert!(table.push_row(&[Value::Str("blue")]).is_err());
+    assert_eq!(1, table.rows());
+    table.check_consistent()?;
+    Ok(())
+}
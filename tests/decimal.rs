@@ -0,0 +1,91 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int128Array};
+use arrow2::datatypes::DataType;
+use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+use pack_it::schema::parse_schema;
+use pack_it::{Kind, Packer, Table, Writer};
+
+#[test]
+fn parse() -> Result<()> {
+    let decimal = Kind::Decimal {
+        precision: 10,
+        scale: 2,
+    };
+    assert_eq!(decimal, "decimal(10,2)".parse()?);
+    assert_eq!(decimal, "DECIMAL( 10, 2 )".parse()?);
+    assert_eq!(decimal, decimal.to_string().parse()?);
+    assert_eq!(DataType::Decimal(10, 2), decimal.to_arrow());
+    Ok(())
+}
+
+#[test]
+fn precision_and_scale_are_checked() -> Result<()> {
+    for kind in [
+        "decimal(0,0)",
+        "decimal(39,0)",
+        "decimal(4,5)",
+        "decimal(4,4)",
+    ] {
+        let schema = parse_schema(&format!("price:{}", kind))?;
+        assert!(Writer::new([Vec::new()], &schema).is_err(), "{}", kind);
+    }
+    Writer::new([Vec::new()], &parse_schema("price:decimal(38,37)")?)?;
+    Ok(())
+}
+
+#[test]
+fn push_decimal_checks_the_digits() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("price:decimal(4,2)?")?, 4);
+    table.push_decimal(0, Some(9999))?;
+    table.push_decimal(0, Some(-9999))?;
+    table.push_decimal(0, None)?;
+    assert!(table.push_decimal(0, Some(10000)).is_err());
+    assert!(table.push_decimal(0, Some(-10000)).is_err());
+    assert_eq!(3, table.rows());
+
+    let mut row = table.row();
+    assert!(row.set_decimal(0, 12345).is_err());
+    row.set_decimal(0, 1234)?;
+    row.commit()?;
+    let expected =
+        Int128Array::from([Some(9999), Some(-9999), None, Some(1234)]).to(DataType::Decimal(4, 2));
+    assert_eq!(&expected as &dyn Array, table.take_batch()[0].as_ref());
+    Ok(())
+}
+
+#[test]
+fn push_primitive_checks_the_digits() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("price:decimal(4,2)?")?, 4);
+    table.push_primitive(0, Some(9999i128))?;
+    assert!(table.push_primitive(0, Some(10000i128)).is_err());
+    assert_eq!(1, table.rows());
+    assert!(table.extend_primitive(0, &[10000i128]).is_err());
+    assert!(table
+        .extend_nullable_primitive(0, &[None, Some(-10000i128)])
+        .is_err());
+    table.extend_primitive(0, &[-9999i128])?;
+    Ok(())
+}
+
+#[test]
+fn written_and_read_back() -> Result<()> {
+    let mut packer = Packer::new(Vec::new(), &parse_schema("price:decimal(10,2)?")?)?;
+    for unscaled in [Some(123), None, Some(-9_999_999_999)] {
+        packer.table().push_decimal(0, unscaled)?;
+    }
+    let file = packer.finish()?;
+
+    let mut file = Cursor::new(file);
+    let metadata = read_metadata(&mut file)?;
+    let schema = infer_schema(&metadata)?;
+    assert_eq!(DataType::Decimal(10, 2), schema.fields[0].data_type);
+    let chunk = FileReader::new(file, metadata.row_groups, schema, None, None, None)
+        .next()
+        .expect("a row group")?;
+    let expected =
+        Int128Array::from([Some(123), None, Some(-9_999_999_999)]).to(DataType::Decimal(10, 2));
+    assert_eq!(&expected as &dyn Array, chunk.arrays()[0].as_ref());
+    Ok(())
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "derive")]
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use pack_it::{PackRecord, Table};
+
+#[derive(PackRecord)]
+struct Visit {
+    id: i64,
+    #[pack_it(rename = "user")]
+    user_name: String,
+    referrer: Option<String>,
+}
+
+#[test]
+fn schema_from_the_fields() {
+    let schema = Visit::schema();
+    let names = schema.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(vec!["id", "user", "referrer"], names);
+    let nullable = schema.iter().map(|f| f.nullable).collect::<Vec<_>>();
+    assert_eq!(vec![false, false, true], nullable);
+}
+
+#[test]
+fn push_records() -> Result<()> {
+    let mut table = Table::from_fields(&Visit::schema(), 4);
+    for (id, referrer) in [(1, Some("search")), (2, None)] {
+        Visit {
+            id,
+            user_name: format!("user{}", id),
+            referrer: referrer.map(str::to_string),
+        }
+        .push_into(&mut table)?;
+    }
+    let batch = table.take_batch();
+    assert_eq!(
+        &Int64Array::from_slice([1, 2]) as &dyn Array,
+        batch[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["user1", "user2"]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from([Some("search"), None]) as &dyn Array,
+        batch[2].as_ref()
+    );
+    Ok(())
+}
+
+#[test]
+fn compile_errors() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
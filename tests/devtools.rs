@@ -0,0 +1,33 @@
+#![cfg(feature = "devtools")]
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array};
+use pack_it::devtools::{fill, generate};
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+#[test]
+fn generate_is_repeatable() -> Result<()> {
+    let schema = parse_schema("id:i64, name:string?, when:timestamp_s?, score:f64?")?;
+    let mut left = generate(&schema, 20, 7)?;
+    let mut right = generate(&schema, 20, 7)?;
+    left.check_consistent()?;
+    assert_eq!(20, left.rows());
+    assert_eq!(left.take_batch(), right.take_batch());
+    Ok(())
+}
+
+#[test]
+fn fill_leaves_constants() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, source:i64")?, 8);
+    table.set_constant(1, Int64Array::from_slice([7]).boxed())?;
+    fill(&mut table, 8, 1)?;
+    table.check_consistent()?;
+    let batch = table.take_batch();
+    assert_eq!(8, batch[0].len());
+    assert_eq!(
+        &Int64Array::from_slice([7; 8]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    Ok(())
+}
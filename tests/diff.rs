@@ -0,0 +1,95 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use pack_it::diff::{diff, Diff, RowDifference, SchemaDifference};
+use pack_it::{Kind, Packer, TableField};
+
+// an id, and a nullable name, unless the schema says otherwise
+fn file(schema: &[(&str, Kind, bool)], rows: &[(i64, Option<&str>)]) -> Result<Vec<u8>> {
+    let schema = schema
+        .iter()
+        .map(|(name, kind, nullable)| TableField::new(name, kind.clone(), *nullable))
+        .collect::<Vec<_>>();
+    let mut packer = Packer::new(Vec::new(), &schema)?;
+    for (id, name) in rows {
+        packer.table().push_primitive(0, Some(*id))?;
+        packer.table().push_str(1, *name)?;
+    }
+    packer.finish()
+}
+
+const SCHEMA: &[(&str, Kind, bool)] = &[("id", Kind::I64, false), ("name", Kind::String, true)];
+
+fn diffed(left: &[u8], right: &[u8], key: Option<&str>, limit: usize) -> Result<Diff> {
+    diff(Cursor::new(left), Cursor::new(right), key, limit)
+}
+
+#[test]
+fn same() -> Result<()> {
+    let left = file(SCHEMA, &[(1, Some("a")), (2, None)])?;
+    assert!(diffed(&left, &left, Some("id"), 10)?.is_empty());
+    assert!(diffed(&left, &left, None, 10)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn rows_by_key() -> Result<()> {
+    let left = file(SCHEMA, &[(1, Some("a")), (2, Some("b"))])?;
+    let right = file(SCHEMA, &[(3, Some("c")), (2, None)])?;
+    let diff = diffed(&left, &right, Some("id"), 10)?;
+    assert!(diff.schema.is_empty());
+    assert!(!diff.truncated);
+    assert!(matches!(&diff.rows[..], [
+        RowDifference::OnlyInLeft { key: one },
+        RowDifference::Changed { key: two, column, left, right },
+        RowDifference::OnlyInRight { key: three },
+    ] if one == "1" && two == "2" && column == "name" && left == "b" && right == "null"
+        && three == "3"));
+    Ok(())
+}
+
+#[test]
+fn schemas() -> Result<()> {
+    let left = file(SCHEMA, &[])?;
+    let right = file(
+        &[("id", Kind::I32, false), ("title", Kind::String, false)],
+        &[],
+    )?;
+    let diff = diffed(&left, &right, None, 10)?;
+    assert!(matches!(&diff.schema[..], [
+        SchemaDifference::TypeChanged { name: id, .. },
+        SchemaDifference::OnlyInLeft(name),
+        SchemaDifference::OnlyInRight(title),
+    ] if id == "id" && name == "name" && title == "title"));
+    Ok(())
+}
+
+#[test]
+fn limited() -> Result<()> {
+    let left = file(SCHEMA, &[(1, Some("a")), (2, Some("b"))])?;
+    let right = file(SCHEMA, &[(1, Some("x")), (2, Some("y"))])?;
+    let diff = diffed(&left, &right, None, 1)?;
+    assert_eq!(1, diff.rows.len());
+    assert!(diff.truncated);
+    Ok(())
+}
+
+#[test]
+fn duplicate_keys() -> Result<()> {
+    let once = file(SCHEMA, &[(1, Some("a"))])?;
+    let twice = file(SCHEMA, &[(1, Some("a")), (1, Some("b"))])?;
+    assert!(diffed(&twice, &once, Some("id"), 10).is_err());
+    assert!(diffed(&once, &twice, Some("id"), 10).is_err());
+    Ok(())
+}
+
+#[test]
+fn missing_key() -> Result<()> {
+    let left = file(SCHEMA, &[])?;
+    let right = file(
+        &[("key", Kind::I64, false), ("name", Kind::String, true)],
+        &[],
+    )?;
+    assert!(diffed(&left, &right, Some("id"), 10).is_err());
+    Ok(())
+}
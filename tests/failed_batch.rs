@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int32Array, Int64Array};
+use arrow2::chunk::Chunk;
+use pack_it::{Kind, Packer, TableField, Writer};
+
+fn schema() -> [TableField; 2] {
+    [
+        TableField::new("a", Kind::I64, false),
+        TableField::new("b", Kind::I64, false),
+    ]
+}
+
+#[test]
+fn a_batch_of_uneven_columns_is_kept() -> Result<()> {
+    let mut writer = Writer::single_threaded([Vec::new()], &schema())?;
+    let batch: Vec<Arc<dyn Array>> = vec![
+        Arc::new(Int64Array::from_slice([1, 2])),
+        Arc::new(Int64Array::from_slice([3])),
+    ];
+    assert!(writer.submit_batch(batch.clone()).is_err());
+    let kept = writer.take_failed_batch().expect("kept");
+    assert_eq!(batch, kept);
+    assert!(writer.take_failed_batch().is_none());
+    Ok(())
+}
+
+#[test]
+fn a_batch_of_the_wrong_columns_is_kept() -> Result<()> {
+    let mut writer = Writer::single_threaded([Vec::new()], &schema())?;
+    let batch: Vec<Arc<dyn Array>> = vec![Arc::new(Int64Array::from_slice([1, 2]))];
+    assert!(writer.submit_batch(batch.clone()).is_err());
+    assert_eq!(Some(batch), writer.take_failed_batch());
+    Ok(())
+}
+
+#[test]
+fn a_batch_of_the_wrong_types_is_kept() -> Result<()> {
+    let mut writer = Writer::single_threaded([Vec::new()], &schema())?;
+    let batch: Vec<Arc<dyn Array>> = vec![
+        Arc::new(Int64Array::from_slice([1, 2])),
+        Arc::new(Int32Array::from_slice([3, 4])),
+    ];
+    assert!(writer.submit_batch(batch.clone()).is_err());
+    assert_eq!(Some(batch), writer.take_failed_batch());
+    Ok(())
+}
+
+#[test]
+fn a_batch_with_nulls_in_a_required_column_is_kept() -> Result<()> {
+    let mut writer = Writer::single_threaded([Vec::new()], &schema()[..1])?;
+    let batch: Vec<Arc<dyn Array>> = vec![Arc::new(Int64Array::from([Some(1), None]))];
+    assert!(writer.submit_batch(batch.clone()).is_err());
+    assert_eq!(Some(batch), writer.take_failed_batch());
+    Ok(())
+}
+
+#[test]
+fn a_chunk_of_the_wrong_types_is_kept_after_the_buffered_rows() -> Result<()> {
+    let mut packer = Packer::new(Vec::new(), &schema()[..1])?;
+    packer.table().push_primitive(0, Some(1i64))?;
+    let batch: Vec<Arc<dyn Array>> = vec![Arc::new(Int32Array::from_slice([2]))];
+    assert!(packer.submit_chunk(Chunk::new(batch.clone())).is_err());
+    assert_eq!(Some(batch), packer.take_failed_batch());
+    assert_eq!(1, packer.flushed_rows());
+    Ok(())
+}
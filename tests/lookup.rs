@@ -0,0 +1,166 @@
+mod common;
+
+use anyhow::Result;
+use arrow2::array::{Array, DictionaryArray, Int64Array, Utf8Array};
+use arrow2::datatypes::DataType;
+use arrow2::io::parquet::read::{read_metadata, ParquetType};
+use pack_it::repack::{
+    lookup_join, lookup_join_matching, transform, Action, LoopDecision, Op, Repack,
+};
+use pack_it::schema::{parse_schema, NameMatching};
+use pack_it::Table;
+use parquet2::schema::types::PrimitiveLogicalType;
+use std::io::Cursor;
+
+use common::{read, written};
+
+fn users() -> Result<Vec<u8>> {
+    let mut table = Table::from_fields(&parse_schema("User_Id:i64, Name:string?")?, 4);
+    for (id, name) in [(1i64, "one"), (2, "two"), (2, "again")] {
+        table.push_primitive(0, Some(id))?;
+        table.push_str(1, Some(name))?;
+    }
+    written(table)
+}
+
+fn events(schema: &str, push: impl Fn(&mut Table) -> Result<()>) -> Result<Vec<u8>> {
+    let mut table = Table::from_fields(&parse_schema(schema)?, 4);
+    push(&mut table)?;
+    written(table)
+}
+
+fn joined(input: Vec<u8>, split: pack_it::repack::Split) -> Result<Vec<u8>> {
+    let mut repack = Repack {
+        ops: vec![
+            Op {
+                input: "user".to_string(),
+                action: Action::Copy,
+            },
+            Op {
+                input: "user".to_string(),
+                action: Action::Split(split),
+            },
+        ],
+    };
+    transform(Cursor::new(input), Vec::new(), &mut repack, |_, _| {
+        LoopDecision::Include
+    })
+}
+
+#[test]
+fn join_on_integers() -> Result<()> {
+    let input = events("user:i64?", |table| {
+        for user in [Some(2i64), None, Some(3), Some(1)] {
+            table.push_primitive(0, user)?;
+        }
+        Ok(())
+    })?;
+    let split = lookup_join(Cursor::new(users()?), "User_Id", &["Name"])?;
+    let (schema, chunks) = read(&joined(input, split)?)?;
+    assert_eq!("Name", schema.fields[1].name);
+    let names = Utf8Array::<i32>::from([Some("two"), None, None, Some("one")]);
+    assert_eq!(&names as &dyn Array, chunks[0].arrays()[1].as_ref());
+    Ok(())
+}
+
+#[test]
+fn join_by_the_name_matching() -> Result<()> {
+    assert!(lookup_join(Cursor::new(users()?), "user_id", &["name"]).is_err());
+    let matching = NameMatching {
+        case_insensitive: true,
+        ..Default::default()
+    };
+    let split = lookup_join_matching(Cursor::new(users()?), "user_id", &["name"], matching)?;
+    let input = events("user:i64", |table| table.push_primitive(0, Some(1i64)))?;
+    let (_, chunks) = read(&joined(input, split)?)?;
+    let ids = Int64Array::from_slice([1]);
+    assert_eq!(&ids as &dyn Array, chunks[0].arrays()[0].as_ref());
+    let names = Utf8Array::<i32>::from([Some("one")]);
+    assert_eq!(&names as &dyn Array, chunks[0].arrays()[1].as_ref());
+    Ok(())
+}
+
+#[test]
+fn strings_dont_match_integers() -> Result<()> {
+    let input = events("user:string", |table| table.push_str(0, Some("1")))?;
+    let split = lookup_join(Cursor::new(users()?), "User_Id", &["Name"])?;
+    assert!(joined(input, split).is_err());
+    Ok(())
+}
+
+#[test]
+fn large_strings_match_strings() -> Result<()> {
+    let lookup = events("code:string, name:string", |table| {
+        table.push_str(0, Some("a"))?;
+        table.push_str(1, Some("apple"))
+    })?;
+    let input = events("user:large_string", |table| table.push_str(0, Some("a")))?;
+    let split = lookup_join(Cursor::new(lookup), "code", &["name"])?;
+    let (_, chunks) = read(&joined(input, split)?)?;
+    let names = Utf8Array::<i32>::from([Some("apple")]);
+    assert_eq!(&names as &dyn Array, chunks[0].arrays()[1].as_ref());
+    Ok(())
+}
+
+#[test]
+fn enum_values_come_from_the_lookup_file() -> Result<()> {
+    let lookup = events("code:i64, colour:enum<red, green>", |table| {
+        for (code, colour) in [(1i64, "green"), (2, "red")] {
+            table.push_primitive(0, Some(code))?;
+            table.push_str(1, Some(colour))?;
+        }
+        Ok(())
+    })?;
+    // the input's own colour column has nothing to do with the lookup's
+    let input = events("user:i64, colour:string", |table| {
+        for user in [2i64, 1] {
+            table.push_primitive(0, Some(user))?;
+            table.push_str(1, Some("purple"))?;
+        }
+        Ok(())
+    })?;
+    let split = lookup_join(Cursor::new(lookup), "code", &["colour"])?;
+    let (schema, chunks) = read(&joined(input, split)?)?;
+    assert!(matches!(
+        schema.fields[1].data_type,
+        DataType::Dictionary(..)
+    ));
+    let colours = chunks[0].arrays()[1]
+        .as_any()
+        .downcast_ref::<DictionaryArray<u32>>()
+        .expect("an enum");
+    let colours = colours.iter_typed::<Utf8Array<i32>>()?.collect::<Vec<_>>();
+    assert_eq!(vec![Some("red"), Some("green")], colours);
+    Ok(())
+}
+
+// whether the written column is annotated as json
+fn is_json(file: &[u8], column: &str) -> Result<bool> {
+    let metadata = read_metadata(&mut Cursor::new(file))?;
+    Ok(metadata.schema().fields().iter().any(|field| match field {
+        ParquetType::PrimitiveType(primitive) => {
+            primitive.field_info.name == column
+                && primitive.logical_type == Some(PrimitiveLogicalType::Json)
+        }
+        _ => false,
+    }))
+}
+
+#[test]
+fn annotations_come_from_the_lookup_file() -> Result<()> {
+    let lookup = events("code:i64, body:string, extra:json", |table| {
+        table.push_primitive(0, Some(1i64))?;
+        table.push_str(1, Some("plain"))?;
+        table.push_str(2, Some("{}"))
+    })?;
+    // the input's json column has nothing to do with the lookup's string column
+    let input = events("user:i64, body:json", |table| {
+        table.push_primitive(0, Some(1i64))?;
+        table.push_str(1, Some("{}"))
+    })?;
+    let split = lookup_join(Cursor::new(lookup), "code", &["body", "extra"])?;
+    let out = joined(input, split)?;
+    assert!(!is_json(&out, "body")?);
+    assert!(is_json(&out, "extra")?);
+    Ok(())
+}
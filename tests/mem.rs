@@ -0,0 +1,106 @@
+mod common;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array};
+use pack_it::schema::parse_schema;
+use pack_it::{MemUsage, Packer, PackerOptions, Table, Writer, WriterOptions};
+
+use common::{read, table};
+
+#[test]
+fn vec_counts_its_capacity() {
+    let mut vec = Vec::<u64>::with_capacity(10);
+    vec.push(1);
+    assert_eq!(80, vec.mem_usage());
+}
+
+#[test]
+fn reserved_rows_are_counted() -> Result<()> {
+    let table = Table::from_fields(&parse_schema("id:i64")?, 1000);
+    assert!(table.mem_estimate() >= 8000, "{}", table.mem_estimate());
+    Ok(())
+}
+
+#[test]
+fn estimate_covers_what_was_pushed() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64?, name:string")?, 0);
+    for i in 0..100 {
+        table.push_primitive(0, (i % 3 != 0).then_some(i as i64))?;
+        table.push_str(1, Some("some name"))?;
+    }
+    // values, validity, string bytes, and offsets
+    let pushed = 100 * 8 + 100 / 8 + 100 * 9 + 101 * 4;
+    assert!(table.mem_estimate() >= pushed, "{}", table.mem_estimate());
+    // the builders grow by doubling, so at most twice that, plus their smallest allocations
+    assert!(
+        table.mem_estimate() <= 2 * pushed + 64,
+        "{}",
+        table.mem_estimate()
+    );
+    table.take_batch();
+    assert_eq!(0, table.rows());
+    Ok(())
+}
+
+#[test]
+fn packer_flushes_by_memory() -> Result<()> {
+    let limit = 64 * 1024;
+    let options = PackerOptions {
+        max_buffer_bytes: Some(limit),
+        ..Default::default()
+    };
+    let mut packer = Packer::with_options(Vec::new(), &parse_schema("id:i64")?, options)?;
+    for i in 0..100_000i64 {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.consider_flushing()?;
+    }
+    let (_, groups) = read(&packer.finish()?)?;
+    assert!(groups.len() > 1, "{}", groups.len());
+    // the estimate's worked out every sixteenth, so can be that far over
+    for group in &groups {
+        assert!(group.len() * 8 <= limit + limit / 16, "{}", group.len());
+    }
+    assert_eq!(100_000, groups.iter().map(|g| g.len()).sum::<usize>());
+    Ok(())
+}
+
+fn coalescing(coalesce_bytes: usize) -> WriterOptions {
+    WriterOptions {
+        single_threaded: true,
+        coalesce_bytes: Some(coalesce_bytes),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn writer_coalesces_by_bytes() -> Result<()> {
+    let schema = parse_schema("id:i64")?;
+    // 800 bytes a batch, so three at a time
+    let mut writer = Writer::with_options([Vec::new()], &schema, coalescing(2000))?;
+    for _ in 0..7 {
+        let batch: Vec<Arc<dyn Array>> = vec![Arc::new(Int64Array::from_vec(vec![1; 100]))];
+        writer.submit_batch(batch)?;
+    }
+    let (_, groups) = read(&writer.finish()?.pop().expect("one sink"))?;
+    assert_eq!(
+        vec![300, 300, 100],
+        groups.iter().map(|g| g.len()).collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn writer_coalesces_nested_columns() -> Result<()> {
+    let schema = table()?.schema().to_vec();
+    for (coalesce_bytes, expected) in [(1, vec![5, 5]), (usize::MAX, vec![10])] {
+        let mut writer = Writer::with_options([Vec::new()], &schema, coalescing(coalesce_bytes))?;
+        for _ in 0..2 {
+            writer.submit_batch(table()?.take_batch())?;
+        }
+        let (_, groups) = read(&writer.finish()?.pop().expect("one sink"))?;
+        assert_eq!(expected, groups.iter().map(|g| g.len()).collect::<Vec<_>>());
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use pack_it::metrics::{Counter, Metrics};
+use pack_it::{Kind, Packer, PackerOptions, TableField, WriterOptions};
+
+#[derive(Default)]
+struct Recorded(Mutex<HashMap<Counter, u64>>);
+
+impl Metrics for Recorded {
+    fn counter(&self, counter: Counter, increment: u64) {
+        *self.0.lock().unwrap().entry(counter).or_default() += increment;
+    }
+}
+
+impl Recorded {
+    fn get(&self, counter: Counter) -> u64 {
+        self.0.lock().unwrap().get(&counter).copied().unwrap_or(0)
+    }
+}
+
+#[test]
+fn rows_flushed_are_counted_at_the_flush() -> Result<()> {
+    let metrics = Arc::new(Recorded::default());
+    let options = PackerOptions {
+        writer: WriterOptions {
+            metrics: Some(metrics.clone()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut packer = Packer::with_options(
+        Vec::new(),
+        &[TableField::new("id", Kind::I64, false)],
+        options,
+    )?;
+    for i in 0..3 {
+        packer.table().push_primitive(0, Some(i as i64))?;
+    }
+    assert_eq!(0, metrics.get(Counter::RowsFlushed));
+    packer.flush()?;
+    assert_eq!(3, metrics.get(Counter::RowsFlushed));
+    assert_eq!(1, metrics.get(Counter::BatchesFlushed));
+    packer.finish()?;
+    assert_eq!(3, metrics.get(Counter::RowsFlushed));
+    Ok(())
+}
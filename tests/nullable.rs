@@ -0,0 +1,59 @@
+use anyhow::Result;
+use arrow2::array::{Array, BinaryArray};
+use pack_it::schema::parse_schema;
+use pack_it::{DefaultValue, Table};
+
+#[test]
+fn binary() -> Result<()> {
+    let mut schema = parse_schema("required:binary, optional:binary?, defaulted:binary")?;
+    schema[2].default = Some(DefaultValue::Bytes(b"none".to_vec()));
+    let mut table = Table::from_fields(&schema, 2);
+
+    let err = table.push_bytes(0, None).unwrap_err().to_string();
+    assert!(err.contains("isn't nullable"), "{}", err);
+
+    table.push_bytes(0, Some(b"one"))?;
+    table.push_bytes(1, None)?;
+    table.push_bytes(2, None)?;
+    table.check_consistent()?;
+
+    let batch = table.take_batch();
+    let optional = BinaryArray::<i32>::from([None::<&[u8]>]);
+    assert_eq!(&optional as &dyn Array, batch[1].as_ref());
+    let defaulted = BinaryArray::<i32>::from_slice([b"none"]);
+    assert_eq!(&defaulted as &dyn Array, batch[2].as_ref());
+    Ok(())
+}
+
+// every typed push, given None, has to go through the column's nullability
+#[test]
+fn every_typed_push() -> Result<()> {
+    let schema = parse_schema(
+        "b:bool, i:i64, s:string, f:fixed(4), x:binary, g:geometry, d:date, n:decimal(4,2), \
+         u:duration[ms], t:timestamp[ms], ip:ipv4, l:list<i64>, m:map<string, i64>",
+    )?;
+    let mut table = Table::from_fields(&schema, 1);
+    let pushes: Vec<(&str, Result<()>)> = vec![
+        ("bool", table.push_bool(0, None)),
+        ("primitive", table.push_primitive::<i64>(1, None)),
+        ("extend", table.extend_nullable_primitive::<i64>(1, &[None])),
+        ("str", table.push_str(2, None)),
+        ("fsb", table.push_fsb(3, None::<[u8; 4]>)),
+        ("bytes", table.push_bytes(4, None)),
+        ("wkb", table.push_wkb(5, None)),
+        ("point", table.push_point(5, None)),
+        ("date", table.push_date(6, None)),
+        ("decimal", table.push_decimal(7, None)),
+        ("duration", table.push_duration(8, None)),
+        ("system_time", table.push_system_time(9, None)),
+        ("ip", table.push_ip(10, None)),
+        ("list", table.push_list(11, None::<[Option<i64>; 0]>)),
+        ("map", table.push_map(12, None::<[(&str, Option<i64>); 0]>)),
+    ];
+    for (push, result) in pushes {
+        let err = result.expect_err(push).to_string();
+        assert!(err.contains("isn't nullable"), "{}: {}", push, err);
+    }
+    assert_eq!(0, table.rows());
+    Ok(())
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use arrow2::datatypes::Schema;
+use pack_it::schema::parse_schema;
+use pack_it::{Kind, Packer, PackerOptions, TableField};
+
+use common::read;
+
+fn names(schema: &Schema) -> Vec<&str> {
+    schema.fields.iter().map(|f| f.name.as_str()).collect()
+}
+
+#[test]
+fn add_column() -> Result<()> {
+    let schema = parse_schema("id:i64, source:string")?;
+    let mut packer = Packer::new(Vec::new(), &schema)?;
+    packer
+        .table()
+        .set_constant(1, Utf8Array::<i32>::from_slice(["feed"]).boxed())?;
+    for i in 0..3i64 {
+        packer.table().push_primitive(0, Some(i))?;
+    }
+    packer.flush()?;
+    packer.table().push_primitive(0, Some(3i64))?;
+    assert_eq!(3, packer.flushed_rows());
+
+    let first = packer.add_column(TableField::new("extra", Kind::I64, true), Vec::new())?;
+    assert_eq!(0, packer.flushed_rows());
+    assert_eq!(3, packer.schema().len());
+    for i in 4..6i64 {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.table().push_primitive(2, Some(i * 10))?;
+    }
+    packer.flush()?;
+    assert_eq!(2, packer.flushed_rows());
+    let second = packer.finish()?;
+
+    // the buffered row went out with the old schema
+    let (schema, groups) = read(&first)?;
+    assert_eq!(vec!["id", "source"], names(&schema));
+    assert_eq!(
+        vec![3, 1],
+        groups.iter().map(|g| g.len()).collect::<Vec<_>>()
+    );
+
+    let (schema, groups) = read(&second)?;
+    assert_eq!(vec!["id", "source", "extra"], names(&schema));
+    assert_eq!(1, groups.len());
+    let arrays = groups[0].arrays();
+    assert_eq!(
+        &Int64Array::from_slice([4, 5]) as &dyn Array,
+        arrays[0].as_ref()
+    );
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["feed", "feed"]) as &dyn Array,
+        arrays[1].as_ref()
+    );
+    assert_eq!(
+        &Int64Array::from_slice([40, 50]) as &dyn Array,
+        arrays[2].as_ref()
+    );
+    Ok(())
+}
+
+fn aged(max_buffer_age: Duration) -> Result<Packer<Vec<u8>>> {
+    let options = PackerOptions {
+        max_buffer_age: Some(max_buffer_age),
+        ..Default::default()
+    };
+    Packer::with_options(Vec::new(), &parse_schema("id:i64")?, options)
+}
+
+#[test]
+fn flush_if_stale() -> Result<()> {
+    let mut packer = aged(Duration::ZERO)?;
+    // nothing to flush
+    packer.flush_if_stale()?;
+    assert_eq!(0, packer.flushed_rows());
+
+    packer.table().push_primitive(0, Some(1i64))?;
+    packer.table().push_primitive(0, Some(2i64))?;
+    packer.flush_if_stale()?;
+    assert_eq!(2, packer.flushed_rows());
+    assert_eq!(0, packer.table().rows());
+
+    let mut packer = aged(Duration::from_secs(3600))?;
+    packer.table().push_primitive(0, Some(1i64))?;
+    packer.flush_if_stale()?;
+    assert_eq!(0, packer.flushed_rows());
+    assert_eq!(1, packer.table().rows());
+    Ok(())
+}
+
+#[test]
+fn consider_flushing_stale_rows() -> Result<()> {
+    let mut packer = aged(Duration::ZERO)?;
+    for i in 0..3i64 {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.consider_flushing()?;
+        assert_eq!(0, packer.table().rows());
+    }
+    let (_, groups) = read(&packer.finish()?)?;
+    assert_eq!(
+        vec![1, 1, 1],
+        groups.iter().map(|g| g.len()).collect::<Vec<_>>()
+    );
+
+    let mut packer = aged(Duration::from_secs(3600))?;
+    for i in 0..3i64 {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.consider_flushing()?;
+    }
+    assert_eq!(3, packer.table().rows());
+    Ok(())
+}
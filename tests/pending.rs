@@ -0,0 +1,98 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+
+use anyhow::Result;
+use pack_it::schema::parse_schema;
+use pack_it::{Packer, PendingFile};
+
+use common::read;
+
+#[test]
+fn persist_renames_into_place() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    let mut file = PendingFile::create(&path)?;
+    file.write_all(b"hello")?;
+    assert!(!path.exists());
+    assert!(dir.path().join("out.parquet.tmp").exists());
+
+    assert_eq!(path, file.persist()?);
+    assert_eq!(b"hello", fs::read(&path)?.as_slice());
+    assert!(!dir.path().join("out.parquet.tmp").exists());
+    Ok(())
+}
+
+#[test]
+fn dropped_without_persisting() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    let mut file = PendingFile::create(&path)?;
+    file.write_all(b"hello")?;
+    drop(file);
+    assert_eq!(0, fs::read_dir(dir.path())?.count());
+    Ok(())
+}
+
+#[test]
+fn failed_persist_removes_the_temporary_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    // a file can't be renamed over a directory with something in it
+    fs::create_dir(&path)?;
+    fs::write(path.join("taken"), b"")?;
+    let mut file = PendingFile::create(&path)?;
+    file.write_all(b"hello")?;
+    assert!(file.persist().is_err());
+    assert!(!dir.path().join("out.parquet.tmp").exists());
+    assert!(path.join("taken").exists());
+    Ok(())
+}
+
+#[test]
+fn packer_finish_renames_into_place() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    let mut packer = Packer::create(&path, &parse_schema("id:i64")?)?;
+    for i in 0..3i64 {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.consider_flushing()?;
+    }
+    assert!(!path.exists());
+
+    assert_eq!(path, packer.finish()?);
+    let (_, groups) = read(&fs::read(&path)?)?;
+    assert_eq!(3, groups.iter().map(|chunk| chunk.len()).sum::<usize>());
+    assert_eq!(1, fs::read_dir(dir.path())?.count());
+    Ok(())
+}
+
+#[test]
+fn packer_finish_with_report_renames_into_place() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    let mut packer = Packer::create(&path, &parse_schema("id:i64")?)?;
+    packer.table().push_primitive(0, Some(7i64))?;
+
+    let (persisted, report) = packer.finish_with_report()?;
+    assert_eq!(path, persisted);
+    assert_eq!(1, report.len());
+    assert!(path.exists());
+    assert_eq!(1, fs::read_dir(dir.path())?.count());
+    Ok(())
+}
+
+#[test]
+fn packer_finish_pending_without_persisting() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    let mut packer = Packer::create(&path, &parse_schema("id:i64")?)?;
+    packer.table().push_primitive(0, Some(7i64))?;
+
+    let file = packer.finish_pending()?;
+    assert!(!path.exists());
+    drop(file);
+    assert_eq!(0, fs::read_dir(dir.path())?.count());
+    Ok(())
+}
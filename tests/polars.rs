@@ -0,0 +1,43 @@
+#![cfg(feature = "polars")]
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use pack_it::polars::read_dataframes;
+use pack_it::{Kind, Packer, TableField};
+use polars_core::prelude::{Column, DataFrame};
+
+fn written(schema: &[TableField], df: &DataFrame) -> Result<DataFrame> {
+    let mut packer = Packer::new(Vec::new(), schema)?;
+    packer.submit_dataframe(df)?;
+    let file = packer.finish()?;
+    let mut frames = read_dataframes(Cursor::new(file))?;
+    frames.next().expect("a row group")
+}
+
+#[test]
+fn strings() -> Result<()> {
+    let df = DataFrame::new(vec![
+        Column::new("id".into(), [1i64, 2, 3]),
+        Column::new("name".into(), [Some("one"), None, Some("three")]),
+    ])?;
+    let schema = [
+        TableField::new("id", Kind::I64, false),
+        TableField::new("name", Kind::String, true),
+    ];
+    let back = written(&schema, &df)?;
+    assert_eq!(df, back);
+    Ok(())
+}
+
+#[test]
+fn large_strings() -> Result<()> {
+    let df = DataFrame::new(vec![Column::new(
+        "name".into(),
+        [Some("one"), None, Some("three")],
+    )])?;
+    let schema = [TableField::new("name", Kind::LargeString, true)];
+    let back = written(&schema, &df)?;
+    assert_eq!(df, back);
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use pack_it::profile::WriteProfile;
+use pack_it::schema::parse_schema;
+use pack_it::{Kind, TableField, Writer, WriterOptions};
+
+fn writer(names: &[&str], profile: WriteProfile) -> Result<Writer<Vec<u8>>> {
+    let options = WriterOptions {
+        single_threaded: true,
+        profile: Some(profile),
+        ..Default::default()
+    };
+    let schema = names
+        .iter()
+        .map(|name| TableField::new(name, Kind::I64, false))
+        .collect::<Vec<_>>();
+    Writer::with_options([Vec::new()], &schema, options)
+}
+
+#[test]
+fn athena_takes_plain_names() -> Result<()> {
+    writer(&["id", "user_2"], WriteProfile::AthenaSafe)?;
+    Ok(())
+}
+
+#[test]
+fn athena_rejects_names_which_need_quoting() -> Result<()> {
+    let long = "a".repeat(256);
+    for name in ["Id", "1st", "_id", "user-id", &long] {
+        assert!(
+            writer(&[name], WriteProfile::AthenaSafe).is_err(),
+            "{}",
+            name
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn athena_checks_child_names() -> Result<()> {
+    let options = || WriterOptions {
+        single_threaded: true,
+        profile: Some(WriteProfile::AthenaSafe),
+        ..Default::default()
+    };
+    let schema = parse_schema("point:struct<x:f64, y_1:f64>")?;
+    Writer::with_options([Vec::new()], &schema, options())?;
+    for schema in ["point:struct<X:f64>", "points:list<struct<Y:f64>>"] {
+        let schema = parse_schema(schema)?;
+        assert!(
+            Writer::with_options([Vec::new()], &schema, options()).is_err(),
+            "{}",
+            schema[0].kind
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn spark_takes_any_name() -> Result<()> {
+    writer(&["Id", "_id"], WriteProfile::SparkSafe)?;
+    Ok(())
+}
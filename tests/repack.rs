@@ -0,0 +1,338 @@
+mod common;
+
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use arrow2::array::{
+    DictionaryArray, MutableDictionaryArray, MutableUtf8Array, TryExtend, Utf8Array,
+};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, IntegerType, Schema, TimeUnit};
+use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+use arrow2::io::parquet::write::{
+    transverse, CompressionOptions, Encoding, FileWriter, RowGroupIterator, Version, WriteOptions,
+};
+use pack_it::repack::{transform, transform_stream, Action, LoopDecision, Op, Repack};
+use pack_it::schema::{from_arrow_schema, parse_schema};
+use pack_it::{Kind, PackerOptions, Table, TableField};
+use parquet2::schema::types::PrimitiveLogicalType;
+
+use common::{read, written};
+
+fn copying(columns: &[&str]) -> Repack {
+    Repack {
+        ops: columns
+            .iter()
+            .map(|name| Op {
+                input: name.to_string(),
+                action: Action::Copy,
+            })
+            .collect(),
+    }
+}
+
+fn copied(file: &[u8], columns: &[&str]) -> Result<Vec<u8>> {
+    let mut repack = copying(columns);
+    transform(Cursor::new(file), Vec::new(), &mut repack, |_, _| {
+        LoopDecision::Include
+    })
+}
+
+// every column is copied, and comes out as it went in
+fn assert_copies(table: Table) -> Result<()> {
+    let names = table
+        .schema()
+        .iter()
+        .map(|f| f.name.clone())
+        .collect::<Vec<_>>();
+    let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+    let file = written(table)?;
+    let (schema, expected) = read(&file)?;
+    let (copied_schema, actual) = read(&copied(&file, &names)?)?;
+    assert_eq!(schema, copied_schema);
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[test]
+fn copy_lists() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, tags:list<string>?")?, 4);
+    for i in 0..4i64 {
+        table.push_primitive(0, Some(i))?;
+        match i {
+            2 => table.push_null(1)?,
+            _ => table.push_list(
+                1,
+                Some([Some("a"), None, Some("c")].into_iter().take(i as usize)),
+            )?,
+        }
+    }
+    assert_copies(table)
+}
+
+// a row group per list of colours, as plain strings, and as a dictionary which only has the
+// group's colours, as other writers' do
+fn colour_groups(groups: &[&[&str]]) -> Result<Vec<u8>> {
+    let mut chunks = Vec::new();
+    for group in groups {
+        let mut colours = MutableDictionaryArray::<u32, MutableUtf8Array<i32>>::new();
+        colours.try_extend(group.iter().map(Some))?;
+        let colours: DictionaryArray<u32> = colours.into();
+        let names = Utf8Array::<i32>::from_slice(group);
+        chunks.push(Ok(Chunk::new(vec![colours.boxed(), names.boxed()])));
+    }
+
+    let colour = DataType::Dictionary(IntegerType::UInt32, Box::new(DataType::Utf8), false);
+    let schema = Schema::from(vec![
+        Field::new("colour", colour, false),
+        Field::new("name", DataType::Utf8, false),
+    ]);
+    let options = WriteOptions {
+        write_statistics: true,
+        compression: CompressionOptions::Uncompressed,
+        version: Version::V2,
+        data_pagesize_limit: None,
+    };
+    let encodings = schema
+        .fields
+        .iter()
+        .map(|f| {
+            transverse(&f.data_type, |_| match f.name.as_str() {
+                "colour" => Encoding::RleDictionary,
+                _ => Encoding::Plain,
+            })
+        })
+        .collect();
+    let groups = RowGroupIterator::try_new(chunks.into_iter(), &schema, options, encodings)?;
+    let mut writer = FileWriter::try_new(Vec::new(), schema, options)?;
+    for group in groups {
+        writer.write(group?)?;
+    }
+    writer.end(None)?;
+    Ok(writer.into_inner())
+}
+
+// the names left, after the row groups which can't have `value` in `column` are skipped
+fn pruned(file: &[u8], column: &str, value: &str) -> Result<Vec<String>> {
+    let mut repack = Repack {
+        ops: vec![Op {
+            input: "name".to_string(),
+            action: Action::Copy,
+        }],
+    };
+    let out = transform(Cursor::new(file), Vec::new(), &mut repack, |_, _| {
+        LoopDecision::IncludeIfMayEqual {
+            column: column.to_string(),
+            value: value.to_string(),
+        }
+    })?;
+
+    let mut out = Cursor::new(out);
+    let metadata = read_metadata(&mut out)?;
+    let schema = infer_schema(&metadata)?;
+    let mut names = Vec::new();
+    for chunk in FileReader::new(out, metadata.row_groups, schema, None, None, None) {
+        let chunk = chunk?;
+        let chunk = chunk.arrays()[0]
+            .as_any()
+            .downcast_ref::<Utf8Array<i32>>()
+            .expect("strings");
+        names.extend(chunk.values_iter().map(str::to_string));
+    }
+    Ok(names)
+}
+
+#[test]
+fn dictionaries_prune_row_groups() -> Result<()> {
+    let file = colour_groups(&[&["red"], &["green", "green"], &["red", "blue"]])?;
+    assert_eq!(vec!["red", "blue"], pruned(&file, "colour", "blue")?);
+    assert!(pruned(&file, "colour", "purple")?.is_empty());
+    // the strings aren't dictionary encoded, so nothing can be ruled out
+    assert_eq!(5, pruned(&file, "name", "purple")?.len());
+    Ok(())
+}
+
+#[test]
+fn copy_structs() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, point:struct<x:f64, y:f64?>?")?, 4);
+    for i in 0..4i64 {
+        table.push_primitive(0, Some(i))?;
+        match i {
+            2 => table.push_null(1)?,
+            _ => table.push_struct(1, |row| {
+                row.set(0, Some(i as f64))?;
+                row.set(1, (i == 1).then_some(-(i as f64)))?;
+                Ok(())
+            })?,
+        }
+    }
+    assert_copies(table)
+}
+
+#[test]
+fn copy_maps() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, attrs:map<string, i64>?")?, 4);
+    for i in 0..4i64 {
+        table.push_primitive(0, Some(i))?;
+        match i {
+            2 => table.push_null(1)?,
+            _ => table.push_map(
+                1,
+                Some([("k", Some(i)), ("n", None)].into_iter().take(i as usize)),
+            )?,
+        }
+    }
+    assert_copies(table)
+}
+
+#[test]
+fn copy_enums() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, colour:enum<red, green, blue>?")?, 4);
+    for i in 0..4i64 {
+        table.push_primitive(0, Some(i))?;
+        match i {
+            2 => table.push_null(1)?,
+            _ => table.push_str(1, Some(if i == 1 { "blue" } else { "red" }))?,
+        }
+    }
+    assert_copies(table)
+}
+
+#[test]
+fn copy_nulls() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, gone:null?")?, 4);
+    for i in 0..4i64 {
+        table.push_primitive(0, Some(i))?;
+    }
+    assert_copies(table)
+}
+
+#[test]
+fn copy_utc_second_timestamps() -> Result<()> {
+    let utc = Kind::Timestamp {
+        unit: TimeUnit::Second,
+        tz: Some("UTC".to_string()),
+    };
+    let mut table = Table::from_fields(&[TableField::new("at", utc.clone(), true)], 4);
+    table.push_primitive(0, Some(1_700_000_000i64))?;
+    table.push_null(0)?;
+    let file = written(table)?;
+    let (schema, _) = read(&file)?;
+    assert_eq!(utc, from_arrow_schema(&schema)?[0].kind);
+    let (copied_schema, _) = read(&copied(&file, &["at"])?)?;
+    assert_eq!(utc, from_arrow_schema(&copied_schema)?[0].kind);
+    Ok(())
+}
+
+// this process's spooled streams, in the system's temporary directory
+fn spooled_streams() -> Result<Vec<PathBuf>> {
+    let prefix = format!("pack-it-stream-{}-", std::process::id());
+    let mut found = Vec::new();
+    for entry in fs::read_dir(std::env::temp_dir())? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            found.push(entry.path());
+        }
+    }
+    Ok(found)
+}
+
+// notes whether the input was being spooled to a file by the time it had all been read
+struct Spying<R> {
+    inner: R,
+    spooled_at_end: Option<bool>,
+}
+
+impl<R: Read> Read for Spying<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && self.spooled_at_end.is_none() {
+            self.spooled_at_end = Some(!spooled_streams().map_err(io::Error::other)?.is_empty());
+        }
+        Ok(read)
+    }
+}
+
+#[test]
+fn transform_a_stream() -> Result<()> {
+    let file = written(common::table()?)?;
+    let names = [
+        "id", "name", "tags", "point", "attrs", "colour", "hash", "price",
+    ];
+    let expected = read(&copied(&file, &names)?)?;
+
+    for (memory_limit, spooled) in [(usize::MAX, false), (file.len(), false), (100, true)] {
+        let mut repack = copying(&names);
+        let mut input = Spying {
+            inner: Cursor::new(&file),
+            spooled_at_end: None,
+        };
+        let out = transform_stream(
+            &mut input,
+            Vec::new(),
+            &mut repack,
+            |_, _| LoopDecision::Include,
+            PackerOptions::default(),
+            memory_limit,
+        )?;
+        assert_eq!(expected, read(&out)?, "{}", memory_limit);
+        assert_eq!(Some(spooled), input.spooled_at_end, "{}", memory_limit);
+        assert!(spooled_streams()?.is_empty());
+    }
+    Ok(())
+}
+
+// each leaf column's path, and its logical type, if it's a uuid or json; arrow2 can't write a
+// fixed size binary in a nested column, so only json can be nested
+fn annotations(file: &[u8]) -> Result<Vec<(String, Option<PrimitiveLogicalType>)>> {
+    let metadata = read_metadata(&mut Cursor::new(file))?;
+    Ok(metadata
+        .schema()
+        .columns()
+        .iter()
+        .map(|column| {
+            let logical_type =
+                column.descriptor.primitive_type.logical_type.filter(|t| {
+                    matches!(t, PrimitiveLogicalType::Uuid | PrimitiveLogicalType::Json)
+                });
+            (column.path_in_schema.join("."), logical_type)
+        })
+        .collect())
+}
+
+#[test]
+fn nested_annotations() -> Result<()> {
+    let schema = parse_schema(
+        "doc:struct<id:i64?, body:json?>?, bodies:list<json>?, docs:map<string, json>?",
+    )?;
+    let mut table = Table::from_fields(&schema, 1);
+    table.push_struct(0, |row| {
+        row.set(1, Some("{}"))?;
+        Ok(())
+    })?;
+    table.push_list(1, Some([Some("1"), None]))?;
+    table.push_map(2, Some([("a", Some("[]"))]))?;
+    let file = written(table)?;
+
+    let expected = vec![
+        ("doc.id".to_string(), None),
+        ("doc.body".to_string(), Some(PrimitiveLogicalType::Json)),
+        (
+            "bodies.list.item".to_string(),
+            Some(PrimitiveLogicalType::Json),
+        ),
+        ("docs.map.entries.key".to_string(), None),
+        (
+            "docs.map.entries.value".to_string(),
+            Some(PrimitiveLogicalType::Json),
+        ),
+    ];
+    assert_eq!(expected, annotations(&file)?);
+    // and the copy knows they were annotated
+    let copy = copied(&file, &["doc", "bodies", "docs"])?;
+    assert_eq!(expected, annotations(&copy)?);
+    assert_eq!(read(&file)?, read(&copy)?);
+    Ok(())
+}
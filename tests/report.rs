@@ -0,0 +1,56 @@
+use anyhow::Result;
+use arrow2::io::parquet::write::Encoding;
+use pack_it::schema::parse_schema;
+use pack_it::Packer;
+
+#[test]
+fn report_per_column() -> Result<()> {
+    let schema = parse_schema("id:i64, colour:string?")?;
+    let mut packer = Packer::new(Vec::new(), &schema)?;
+    for i in 0..10 {
+        packer.table().push_primitive(0, Some(i as i64))?;
+        packer
+            .table()
+            .push_str(1, (i % 5 != 0).then_some(["red", "green"][i % 2]))?;
+    }
+    packer.flush()?;
+    packer.table().push_primitive(0, Some(10i64))?;
+    packer.table().push_str(1, Some("red"))?;
+    let (_, reports) = packer.finish_with_report()?;
+
+    assert_eq!(2, reports.len());
+    let (id, colour) = (&reports[0], &reports[1]);
+    assert_eq!("id", id.name);
+    assert_eq!(11, id.values);
+    assert_eq!(Some(0), id.nulls);
+    assert!(id.compressed_bytes > 0);
+
+    assert_eq!("colour", colour.name);
+    assert!(colour.encodings.contains(&Encoding::Plain));
+    assert_eq!(Some(2), colour.nulls);
+    assert_eq!(0, colour.dictionary_fallbacks);
+    assert!(colour.to_string().starts_with("colour: "), "{}", colour);
+    Ok(())
+}
+
+#[test]
+fn report_enum_column() -> Result<()> {
+    let schema = parse_schema("colour:enum<red, green>?")?;
+    let mut packer = Packer::new(Vec::new(), &schema)?;
+    for i in 0..10 {
+        packer
+            .table()
+            .push_str(0, (i % 5 != 0).then_some(["red", "green"][i % 2]))?;
+    }
+    let (_, reports) = packer.finish_with_report()?;
+
+    assert_eq!(1, reports.len());
+    let colour = &reports[0];
+    assert_eq!(10, colour.values);
+    assert!(colour.encodings.contains(&Encoding::RleDictionary));
+    // the statistics are the dictionary's
+    assert_eq!(None, colour.nulls);
+    // the dictionary page is plain, which isn't a fallback
+    assert_eq!(0, colour.dictionary_fallbacks);
+    Ok(())
+}
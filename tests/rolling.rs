@@ -0,0 +1,103 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use arrow2::array::Utf8Array;
+use pack_it::rolling::RollingPacker;
+use pack_it::schema::parse_schema;
+
+use common::read;
+
+fn rows_in(path: &Path) -> Result<usize> {
+    let (_, groups) = read(&fs::read(path)?)?;
+    Ok(groups.iter().map(|chunk| chunk.len()).sum())
+}
+
+fn rolled(dir: &Path, rows: i64) -> Result<Vec<usize>> {
+    let template = dir.join("out-{seq}.parquet");
+    let schema = parse_schema("id:i64")?;
+    let mut packer = RollingPacker::new(template.to_str().expect("utf-8"), &schema, 3)?;
+    for i in 0..rows {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.consider_flushing()?;
+    }
+    let files = packer.finish()?;
+    for (seq, file) in files.iter().enumerate() {
+        assert_eq!(&dir.join(format!("out-{}.parquet", seq)), file);
+    }
+    files.iter().map(|file| rows_in(file)).collect()
+}
+
+#[test]
+fn rolls_by_rows() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    assert_eq!(vec![3, 3, 1], rolled(dir.path(), 7)?);
+    Ok(())
+}
+
+#[test]
+fn no_empty_last_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    assert_eq!(vec![3, 3], rolled(dir.path(), 6)?);
+    // the next file was started, but never appears
+    assert_eq!(2, fs::read_dir(dir.path())?.count());
+    Ok(())
+}
+
+#[test]
+fn one_file_even_if_empty() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    assert_eq!(vec![0], rolled(dir.path(), 0)?);
+    Ok(())
+}
+
+#[test]
+fn files_appear_once_finished() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let template = dir.path().join("out-{seq}.parquet");
+    let schema = parse_schema("id:i64")?;
+    let mut packer = RollingPacker::new(template.to_str().expect("utf-8"), &schema, 100)?;
+    packer.table().push_primitive(0, Some(1i64))?;
+    packer.roll()?;
+    assert_eq!(&[dir.path().join("out-0.parquet")], packer.files());
+    assert!(packer.files()[0].exists());
+    assert!(!dir.path().join("out-1.parquet").exists());
+    Ok(())
+}
+
+#[test]
+fn constants_survive_rolling() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let template = dir.path().join("out-{seq}.parquet");
+    let schema = parse_schema("id:i64, source:string")?;
+    let mut packer = RollingPacker::new(template.to_str().expect("utf-8"), &schema, 2)?;
+    packer
+        .table()
+        .set_constant(1, Utf8Array::<i32>::from_slice(["feed"]).boxed())?;
+    for i in 0..5i64 {
+        packer.table().push_primitive(0, Some(i))?;
+        packer.consider_flushing()?;
+    }
+    let files = packer.finish()?;
+    assert_eq!(3, files.len());
+    for file in &files {
+        let (_, groups) = read(&fs::read(file)?)?;
+        for chunk in groups {
+            let sources = chunk.arrays()[1]
+                .as_any()
+                .downcast_ref::<Utf8Array<i32>>()
+                .expect("strings");
+            assert!(sources.values_iter().all(|source| source == "feed"));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn needs_a_seq() -> Result<()> {
+    let schema = parse_schema("id:i64")?;
+    assert!(RollingPacker::new("out.parquet", &schema, 3).is_err());
+    Ok(())
+}
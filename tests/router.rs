@@ -0,0 +1,54 @@
+mod common;
+
+use anyhow::{bail, Result};
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use pack_it::router::Router;
+use pack_it::schema::parse_schema;
+
+use common::read;
+
+#[test]
+fn a_file_per_tag() -> Result<()> {
+    let mut looked_up = Vec::new();
+    let mut router = Router::new(|tag: &&str| {
+        looked_up.push(tag.to_string());
+        let schema = match *tag {
+            "click" => "id:i64",
+            "view" => "page:string",
+            other => bail!("no schema for {:?}", other),
+        };
+        Ok((Vec::new(), parse_schema(schema)?))
+    });
+
+    for i in 0..3 {
+        let page = format!("/{}", i);
+        let mut row = router.row(&"view")?;
+        row.set_str("page", &page)?;
+        row.commit()?;
+        let mut row = router.row(&"click")?;
+        row.set_i64("id", i)?;
+        row.commit()?;
+        router.consider_flushing()?;
+    }
+    assert!(router.row(&"scroll").is_err());
+    assert_eq!(vec![&"view", &"click"], router.tags().collect::<Vec<_>>());
+
+    let finished = router.finish()?;
+    assert_eq!(vec!["view", "click", "scroll"], looked_up);
+    assert_eq!(
+        vec!["view", "click"],
+        finished.iter().map(|(tag, _)| *tag).collect::<Vec<_>>()
+    );
+
+    let (_, views) = read(&finished[0].1)?;
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["/0", "/1", "/2"]) as &dyn Array,
+        views[0].arrays()[0].as_ref()
+    );
+    let (_, clicks) = read(&finished[1].1)?;
+    assert_eq!(
+        &Int64Array::from_slice([0, 1, 2]) as &dyn Array,
+        clicks[0].arrays()[0].as_ref()
+    );
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use arrow2::array::{Array, BinaryArray, Utf8Array};
+use pack_it::geo::point_wkb;
+use pack_it::row::Value;
+use pack_it::schema::{parse_schema, NameMatching};
+use pack_it::{Kind, Packer, PackerOptions, Table, TableField, WriterOptions};
+
+fn schema(id: &str, name: &str) -> [TableField; 2] {
+    [
+        TableField::new(id, Kind::I64, false),
+        TableField::new(name, Kind::String, true),
+    ]
+}
+
+#[test]
+fn set_by_name() -> Result<()> {
+    let mut table = Table::from_fields(&schema("id", "name"), 4);
+    let mut row = table.row();
+    row.set_i64("id", 1)?.set_str("name", "one")?;
+    row.commit()?;
+    assert!(table.row().set_i64("Id", 2).is_err());
+    assert_eq!(Some(1), table.column_index("name"));
+    assert_eq!(None, table.column_index("Name"));
+    let names = Utf8Array::<i32>::from([Some("one")]);
+    assert_eq!(&names as &dyn Array, table.take_batch()[1].as_ref());
+    Ok(())
+}
+
+#[test]
+fn set_by_name_as_the_writer_matches() -> Result<()> {
+    let options = PackerOptions {
+        writer: WriterOptions {
+            name_matching: NameMatching {
+                case_insensitive: true,
+                trim: true,
+                nfc: false,
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut packer = Packer::with_options(Vec::new(), &schema("Id", "Name"), options)?;
+    let table = packer.table();
+    assert_eq!(Some(1), table.column_index(" name"));
+    let mut row = table.row();
+    row.set_i64("id", 1)?.set_str("NAME", "one")?;
+    row.commit()?;
+    assert_eq!(1, table.rows());
+    Ok(())
+}
+
+#[test]
+fn geometry_takes_wkb() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("at:geometry")?, 4);
+    let wkb = point_wkb(1.5, -2.0);
+    table.push_row(&[Value::Bytes(&wkb)])?;
+    let mut row = table.row();
+    row.set_bytes("at", &wkb)?;
+    row.commit()?;
+    assert!(table.push_row(&[Value::Bytes(b"nope")]).is_err());
+    assert!(table.row().set_bytes("at", b"nope").is_err());
+    assert_eq!(2, table.rows());
+    let expected = BinaryArray::<i32>::from_slice([wkb, wkb]);
+    assert_eq!(&expected as &dyn Array, table.take_batch()[0].as_ref());
+    Ok(())
+}
+
+#[test]
+fn nested_only_take_null() -> Result<()> {
+    let schema = parse_schema("tags:list<string>?, point:struct<x:f64>?, attrs:map<string, i64>?")?;
+    let mut table = Table::from_fields(&schema, 4);
+    table.push_row(&[Value::Null, Value::Null, Value::Null])?;
+    assert!(table
+        .push_row(&[Value::Str("a"), Value::Null, Value::Null])
+        .is_err());
+    assert!(table
+        .push_row(&[Value::Null, Value::F64(1.0), Value::Null])
+        .is_err());
+    assert!(table
+        .push_row(&[Value::Null, Value::Null, Value::I64(1)])
+        .is_err());
+    assert_eq!(1, table.rows());
+    Ok(())
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "serde")]
+
+use std::fs;
+
+use anyhow::Result;
+use arrow2::io::parquet::write::{CompressionOptions, Encoding, ZstdLevel};
+use pack_it::schema::{compression_from_name, compression_name, load_schema, parse_schema};
+
+#[test]
+fn compression_names() -> Result<()> {
+    for compression in [
+        CompressionOptions::Uncompressed,
+        CompressionOptions::Snappy,
+        CompressionOptions::Gzip(None),
+        CompressionOptions::Zstd(None),
+        CompressionOptions::Zstd(Some(ZstdLevel::try_new(3)?)),
+        CompressionOptions::Lz4Raw,
+    ] {
+        assert_eq!(
+            compression,
+            compression_from_name(&compression_name(compression))?
+        );
+    }
+    assert!(compression_from_name("zstd(99)").is_err());
+    assert!(compression_from_name("snappy(1)").is_err());
+    assert!(compression_from_name("zstd(3").is_err());
+    Ok(())
+}
+
+#[test]
+fn saved_and_loaded() -> Result<()> {
+    let mut schema = parse_schema("id:i64, name:string?, body:string?")?;
+    schema[1] = schema[1].clone().compression(CompressionOptions::Snappy);
+    schema[2] = schema[2]
+        .clone()
+        .compression(CompressionOptions::Zstd(Some(ZstdLevel::try_new(9)?)));
+    schema[2].encoding = Encoding::DeltaLengthByteArray;
+
+    let dir = tempfile::tempdir()?;
+    let json = dir.path().join("schema.json");
+    fs::write(&json, serde_json::to_string(&schema)?)?;
+    let yaml = dir.path().join("schema.yaml");
+    fs::write(&yaml, serde_yaml::to_string(&schema)?)?;
+
+    assert_eq!(schema, load_schema(&json)?);
+    assert_eq!(schema, load_schema(&yaml)?);
+    assert_eq!(None, load_schema(&json)?[0].compression);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array};
+use pack_it::schema::parse_schema;
+use pack_it::{Writer, WriterOptions};
+use sha2::{Digest, Sha256};
+
+fn writer(sha256: bool) -> Result<Writer<Vec<u8>>> {
+    let options = WriterOptions {
+        sha256,
+        ..Default::default()
+    };
+    let mut writer = Writer::with_options(vec![Vec::new(); 2], &parse_schema("id:i64")?, options)?;
+    for i in 0..4 {
+        let batch: Vec<Arc<dyn Array>> = vec![Arc::new(Int64Array::from_slice([i]))];
+        writer.submit_batch(batch)?;
+    }
+    Ok(writer)
+}
+
+#[test]
+fn hashes_each_sink() -> Result<()> {
+    let finished = writer(true)?.finish_with_sha256()?;
+    assert_eq!(2, finished.len());
+    for (file, sha256) in finished {
+        assert!(!file.is_empty());
+        assert_eq!(<[u8; 32]>::from(Sha256::digest(&file)), sha256);
+    }
+    Ok(())
+}
+
+#[test]
+fn only_with_the_option() -> Result<()> {
+    assert!(writer(false)?.finish_with_sha256().is_err());
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array};
+use pack_it::{Durable, Kind, Packer, TableField, Writer};
+
+struct Sink {
+    id: usize,
+    written: usize,
+    // how much had been written at the last sync
+    synced: Option<usize>,
+}
+
+impl Durable for Sink {
+    fn sync(&mut self) -> io::Result<()> {
+        self.synced = Some(self.written);
+        Ok(())
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn finished_ids(single_threaded: bool) -> Result<Vec<usize>> {
+    let sinks = (0..4)
+        .map(|id| Sink {
+            id,
+            written: 0,
+            synced: None,
+        })
+        .collect::<Vec<_>>();
+    let schema = [TableField::new("id", Kind::I64, false)];
+    let mut writer = if single_threaded {
+        Writer::single_threaded(sinks, &schema)?
+    } else {
+        Writer::new(sinks, &schema)?
+    };
+    for i in 0..8 {
+        let batch: Vec<Arc<dyn Array>> = vec![Arc::new(Int64Array::from_slice([i]))];
+        writer.submit_batch(batch)?;
+    }
+    let finished = writer.finish()?;
+    assert!(finished.iter().all(|sink| sink.written > 0));
+    Ok(finished.iter().map(|sink| sink.id).collect())
+}
+
+#[test]
+fn finish_returns_the_sinks_in_order() -> Result<()> {
+    assert_eq!(vec![0, 1, 2, 3], finished_ids(true)?);
+    assert_eq!(vec![0, 1, 2, 3], finished_ids(false)?);
+    Ok(())
+}
+
+#[test]
+fn finish_durably_syncs_everything_written() -> Result<()> {
+    let sink = Sink {
+        id: 0,
+        written: 0,
+        synced: None,
+    };
+    let mut packer = Packer::new(
+        BufWriter::new(sink),
+        &[TableField::new("id", Kind::I64, false)],
+    )?;
+    packer.table().push_primitive(0, Some(1i64))?;
+    let buffered = packer.finish_durably()?;
+    let sink = buffered.get_ref();
+    assert!(sink.written > 0);
+    assert_eq!(Some(sink.written), sink.synced);
+    Ok(())
+}
+
+#[test]
+fn finish_durably_through_a_box() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out.parquet");
+    let file = Box::new(File::create(&path)?);
+    let mut packer = Packer::new(file, &[TableField::new("id", Kind::I64, false)])?;
+    packer.table().push_primitive(0, Some(1i64))?;
+    packer.finish_durably()?;
+    assert!(fs::metadata(&path)?.len() > 0);
+    Ok(())
+}
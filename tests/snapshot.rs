@@ -0,0 +1,125 @@
+mod common;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array};
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+use common::{assert_same, table};
+
+#[test]
+fn snapshot_batch_leaves_the_rows() -> Result<()> {
+    let mut table = table()?;
+    let snapshot = table.snapshot_batch()?;
+    assert_eq!(5, snapshot[0].len());
+    assert_eq!(5, table.rows());
+    assert_same(&snapshot, &table.take_batch());
+    Ok(())
+}
+
+#[test]
+fn snapshot_batch_includes_constants() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, source:i64")?, 4);
+    table.set_constant(1, Int64Array::from_slice([7]).boxed())?;
+    table.push_primitive(0, Some(1i64))?;
+    table.push_primitive(0, Some(2i64))?;
+    let snapshot = table.snapshot_batch()?;
+    assert_eq!(
+        &Int64Array::from_slice([7, 7]) as &dyn Array,
+        snapshot[1].as_ref()
+    );
+    assert_same(&snapshot, &table.take_batch());
+    Ok(())
+}
+
+#[test]
+fn snapshot_batch_of_a_partial_row() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("a:i64, b:i64")?, 4);
+    table.push_primitive(0, Some(1i64))?;
+    assert!(table.snapshot_batch().is_err());
+    // still there to finish
+    table.push_primitive(1, Some(2i64))?;
+    assert_eq!(1, table.snapshot_batch()?[0].len());
+    Ok(())
+}
+
+// only flat columns; restore_nested_columns has the rest
+fn flat() -> Result<Table> {
+    let mut table = Table::from_fields(&parse_schema("id:i64, name:string?, score:f64?")?, 8);
+    for i in 0..5i64 {
+        table.push_primitive(0, Some(i))?;
+        table.push_str(1, (i % 2 == 0).then_some("name"))?;
+        table.push_primitive(2, Some(i as f64 / 2.))?;
+    }
+    Ok(table)
+}
+
+#[test]
+fn restore_after_the_buffered_rows() -> Result<()> {
+    let mut table = flat()?;
+    let mut snapshot = Vec::new();
+    table.snapshot(&mut snapshot)?;
+
+    let mut other = Table::from_fields(table.schema(), 8);
+    other.restore(&snapshot[..])?;
+    other.restore(&snapshot[..])?;
+    assert_eq!(10, other.rows());
+    let expected = table.take_batch();
+    for (expected, actual) in expected.iter().zip(other.take_batch()) {
+        assert_eq!(expected.as_ref(), &*actual.sliced(0, 5));
+        assert_eq!(expected.as_ref(), &*actual.sliced(5, 5));
+    }
+    Ok(())
+}
+
+#[test]
+fn restore_nested_columns() -> Result<()> {
+    let table = table()?;
+    let mut snapshot = Vec::new();
+    table.snapshot(&mut snapshot)?;
+
+    let mut other = Table::from_fields(table.schema(), 8);
+    other.restore(&snapshot[..])?;
+    assert_same(&table.snapshot_batch()?, &other.take_batch());
+    Ok(())
+}
+
+#[test]
+fn restore_checks_the_schema() -> Result<()> {
+    let mut snapshot = Vec::new();
+    flat()?.snapshot(&mut snapshot)?;
+    let mut other = Table::from_fields(&parse_schema("id:i64, name:string?")?, 8);
+    assert!(other.restore(&snapshot[..]).is_err());
+    assert_eq!(0, other.rows());
+    Ok(())
+}
+
+#[test]
+fn restore_of_a_truncated_snapshot() -> Result<()> {
+    let table = flat()?;
+    let mut snapshot = Vec::new();
+    table.snapshot(&mut snapshot)?;
+
+    let mut other = Table::from_fields(table.schema(), 8);
+    other.push_primitive(0, Some(9i64))?;
+    other.push_null(1)?;
+    other.push_null(2)?;
+    assert!(other.restore(&snapshot[..snapshot.len() - 20]).is_err());
+    assert_eq!(1, other.rows());
+    other.check_consistent()?;
+    Ok(())
+}
+
+#[test]
+fn restore_of_nulls_into_a_required_column() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("id:i64?")?, 4);
+    table.push_primitive(0, Some(1i64))?;
+    table.push_null(0)?;
+    let mut snapshot = Vec::new();
+    table.snapshot(&mut snapshot)?;
+
+    let mut other = Table::from_fields(&parse_schema("id:i64")?, 4);
+    assert!(other.restore(&snapshot[..]).is_err());
+    assert_eq!(0, other.rows());
+    Ok(())
+}
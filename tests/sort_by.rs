@@ -0,0 +1,66 @@
+mod common;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+use common::table;
+
+#[test]
+fn sort_by_reorders_every_column() -> Result<()> {
+    let mut table = table()?;
+    let before = table.snapshot_batch()?;
+    // the decimals go up with the ids, so sort them backwards
+    table.push_primitive(0, Some(-1i64))?;
+    table.push_str(1, None)?;
+    table.push_null(2)?;
+    table.push_struct(3, |row| {
+        row.set(0, Some(0.0))?;
+        Ok(())
+    })?;
+    table.push_null(4)?;
+    table.push_str(5, Some("red"))?;
+    table.push_fsb(6, None::<[u8; 4]>)?;
+    table.push_decimal(7, Some(-5))?;
+    table.sort_by(&[7])?;
+
+    let after = table.take_batch();
+    assert_eq!(6, after[0].len());
+    assert_eq!(
+        &Int64Array::from_slice([-1, 0, 1, 2, 3, 4]) as &dyn Array,
+        after[0].as_ref()
+    );
+    for (before, after) in before.iter().zip(&after) {
+        assert_eq!(before.as_ref(), &*after.sliced(1, 5));
+    }
+    Ok(())
+}
+
+#[test]
+fn sort_by_several_keys() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("a:string, b:i64")?, 4);
+    for (a, b) in [("y", 2i64), ("x", 3), ("y", 1), ("x", 1)] {
+        table.push_str(0, Some(a))?;
+        table.push_primitive(1, Some(b))?;
+    }
+    table.sort_by(&[0, 1])?;
+    let batch = table.take_batch();
+    assert_eq!(
+        &Utf8Array::<i32>::from_slice(["x", "x", "y", "y"]) as &dyn Array,
+        batch[0].as_ref()
+    );
+    assert_eq!(
+        &Int64Array::from_slice([1, 3, 1, 2]) as &dyn Array,
+        batch[1].as_ref()
+    );
+    Ok(())
+}
+
+#[test]
+fn sort_by_a_missing_column() -> Result<()> {
+    let mut table = table()?;
+    assert!(table.sort_by(&[8]).is_err());
+    assert_eq!(5, table.rows());
+    Ok(())
+}
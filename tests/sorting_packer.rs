@@ -0,0 +1,180 @@
+mod common;
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array, Utf8Array};
+use arrow2::io::parquet::read::{infer_schema, read_metadata, FileReader};
+use pack_it::schema::parse_schema;
+use pack_it::sort::{SortOptions, SortingPacker};
+use pack_it::PackerOptions;
+
+use common::{assert_same, read, table, written};
+
+// (key, the order it was pushed in)
+fn sorted(rows: &[i64], options: SortOptions) -> Result<Vec<Vec<Box<dyn Array>>>> {
+    let schema = parse_schema("key:i64?, seq:string")?;
+    let mut packer = SortingPacker::with_options(Vec::new(), &schema, options)?;
+    for (seq, key) in rows.iter().enumerate() {
+        let table = packer.table();
+        match key {
+            0 => table.push_null(0)?,
+            key => table.push_primitive(0, Some(*key))?,
+        }
+        table.push_str(1, Some(&seq.to_string()))?;
+        packer.consider_spilling()?;
+    }
+    let mut file = Cursor::new(packer.finish()?);
+    let metadata = read_metadata(&mut file)?;
+    let schema = infer_schema(&metadata)?;
+    FileReader::new(file, metadata.row_groups, schema, None, None, None)
+        .map(|chunk| Ok(chunk?.into_arrays()))
+        .collect()
+}
+
+fn options(memory_limit: usize) -> SortOptions {
+    SortOptions {
+        keys: vec!["key".to_string()],
+        memory_limit,
+        spill_dir: None,
+        packer: Default::default(),
+    }
+}
+
+fn grouped_options(memory_limit: usize, max_rows_per_group: usize) -> SortOptions {
+    SortOptions {
+        packer: PackerOptions {
+            max_rows_per_group: Some(max_rows_per_group),
+            ..Default::default()
+        },
+        ..options(memory_limit)
+    }
+}
+
+// nulls (0s) first, and equal keys in the order they were pushed
+const ROWS: [i64; 7] = [3, 1, 0, 3, 2, 1, 0];
+
+fn assert_sorted(groups: &[Vec<Box<dyn Array>>]) {
+    let mut keys = Vec::new();
+    let mut seqs = Vec::new();
+    for group in groups {
+        keys.extend(
+            group[0]
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .expect("i64s")
+                .iter()
+                .map(|v| v.copied()),
+        );
+        seqs.extend(
+            group[1]
+                .as_any()
+                .downcast_ref::<Utf8Array<i32>>()
+                .expect("strings")
+                .values_iter()
+                .map(|v| v.to_string()),
+        );
+    }
+    assert_eq!(
+        vec![None, None, Some(1), Some(1), Some(2), Some(3), Some(3)],
+        keys
+    );
+    assert_eq!(vec!["2", "6", "1", "5", "4", "0", "3"], seqs);
+}
+
+#[test]
+fn sorts_in_memory() -> Result<()> {
+    let groups = sorted(&ROWS, options(usize::MAX))?;
+    assert_eq!(1, groups.len());
+    assert_sorted(&groups);
+    Ok(())
+}
+
+#[test]
+fn sorts_across_spills() -> Result<()> {
+    // every row is spilled on its own
+    assert_sorted(&sorted(&ROWS, options(0))?);
+    Ok(())
+}
+
+#[test]
+fn keeps_to_the_row_group_size() -> Result<()> {
+    for memory_limit in [0, usize::MAX] {
+        let groups = sorted(&ROWS, grouped_options(memory_limit, 3))?;
+        assert_eq!(
+            vec![3, 3, 1],
+            groups.iter().map(|g| g[0].len()).collect::<Vec<_>>()
+        );
+        assert_sorted(&groups);
+    }
+    Ok(())
+}
+
+#[test]
+fn merges_across_chunks() -> Result<()> {
+    // runs of a few thousand rows each, read back, and copied out, in several goes
+    let rows = (0..20_000)
+        .map(|i| (i * 7919) % 1000 + 1)
+        .collect::<Vec<i64>>();
+    let groups = sorted(&rows, options(64 * 1024))?;
+    let mut seen = Vec::new();
+    for group in &groups {
+        let keys = group[0]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .expect("i64s");
+        let seqs = group[1]
+            .as_any()
+            .downcast_ref::<Utf8Array<i32>>()
+            .expect("strings");
+        for (key, seq) in keys.values_iter().zip(seqs.values_iter()) {
+            seen.push((*key, seq.parse::<usize>()?));
+        }
+    }
+    let mut expected = rows.iter().copied().zip(0..).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(expected, seen);
+    Ok(())
+}
+
+#[test]
+fn missing_keys() -> Result<()> {
+    let schema = parse_schema("id:i64")?;
+    assert!(SortingPacker::new(Vec::new(), &schema, &["missing"]).is_err());
+    assert!(SortingPacker::new(Vec::new(), &schema, &["id"]).is_ok());
+    Ok(())
+}
+
+#[test]
+fn nested_keys() -> Result<()> {
+    let schema = parse_schema("tags:list<string>, id:i64")?;
+    assert!(SortingPacker::new(Vec::new(), &schema, &["tags"]).is_err());
+    assert!(SortingPacker::new(Vec::new(), &schema, &["id"]).is_ok());
+    Ok(())
+}
+
+#[test]
+fn copies_nested_columns() -> Result<()> {
+    // two copies, so there are equal keys, and, spilling, two runs to merge
+    let mut expected = table()?;
+    expected.append(table()?)?;
+    expected.sort_by(&[5])?;
+    let (_, expected) = read(&written(expected)?)?;
+
+    for memory_limit in [0, usize::MAX] {
+        let schema = table()?.schema().to_vec();
+        let options = SortOptions {
+            keys: vec!["colour".to_string()],
+            ..options(memory_limit)
+        };
+        let mut packer = SortingPacker::with_options(Vec::new(), &schema, options)?;
+        for _ in 0..2 {
+            packer.table().append(table()?)?;
+            packer.consider_spilling()?;
+        }
+        let (_, actual) = read(&packer.finish()?)?;
+        assert_eq!(1, actual.len());
+        assert_same(expected[0].arrays(), actual[0].arrays());
+    }
+    Ok(())
+}
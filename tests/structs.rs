@@ -0,0 +1,39 @@
+use anyhow::Result;
+use pack_it::schema::parse_schema;
+use pack_it::{Kind, Table};
+
+#[test]
+fn children_keep_their_nullability() -> Result<()> {
+    let schema = parse_schema("point:struct<x:f64, y:f64?>")?;
+    let kind = &schema[0].kind;
+    let Kind::Struct(children) = kind else {
+        panic!("{} isn't a struct", kind);
+    };
+    assert!(!children[0].nullable);
+    assert!(children[1].nullable);
+    assert_eq!("struct<x: f64, y: f64?>", kind.to_string());
+    assert_eq!(*kind, kind.to_string().parse::<Kind>()?);
+    assert_eq!(*kind, Kind::from_arrow(&kind.to_arrow())?);
+    Ok(())
+}
+
+#[test]
+fn required_children_are_set() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("point:struct<x:f64, y:f64?>?")?, 4);
+    table.push_struct(0, |row| {
+        row.set(0, Some(1.0))?;
+        Ok(())
+    })?;
+    assert!(table
+        .push_struct(0, |row| {
+            row.set(0, None::<f64>)?;
+            Ok(())
+        })
+        .is_err());
+    assert!(table.push_struct(0, |_| Ok(())).is_err());
+    // the failed rows are nulls
+    let batch = table.take_batch();
+    assert_eq!(3, batch[0].len());
+    assert_eq!(2, batch[0].null_count());
+    Ok(())
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use anyhow::Result;
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+use common::{assert_same, table};
+
+#[test]
+fn take_rows_keeps_the_rest() -> Result<()> {
+    let mut table = table()?;
+    let all = table.snapshot_batch()?;
+    let first = table.take_rows(2)?;
+    assert_eq!(3, table.rows());
+    let rest = table.take_batch();
+    for ((all, first), rest) in all.iter().zip(&first).zip(&rest) {
+        assert_eq!(&*all.sliced(0, 2), first.as_ref());
+        assert_eq!(&*all.sliced(2, 3), rest.as_ref());
+    }
+    Ok(())
+}
+
+#[test]
+fn take_rows_past_the_end() -> Result<()> {
+    let mut table = table()?;
+    let all = table.snapshot_batch()?;
+    assert_same(&all, &table.take_rows(10)?);
+    assert_eq!(0, table.rows());
+    Ok(())
+}
+
+#[test]
+fn take_rows_of_a_partial_row() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("a:i64, b:i64")?, 4);
+    table.push_primitive(0, Some(1i64))?;
+    table.push_primitive(1, Some(2i64))?;
+    table.push_primitive(0, Some(3i64))?;
+    assert!(table.take_rows(1).is_err());
+    table.push_primitive(1, Some(4i64))?;
+    assert_eq!(1, table.take_rows(1)?[0].len());
+    assert_eq!(1, table.rows());
+    Ok(())
+}
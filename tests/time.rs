@@ -0,0 +1,27 @@
+#![cfg(feature = "time")]
+
+use anyhow::Result;
+use arrow2::array::{Array, PrimitiveArray};
+use arrow2::datatypes::DataType;
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+use time::{OffsetDateTime, UtcOffset};
+
+#[test]
+fn offset_date_times_in_the_columns_unit() -> Result<()> {
+    let mut table = Table::from_fields(&parse_schema("at:timestamp[ms]?")?, 4);
+    let at = OffsetDateTime::from_unix_timestamp_nanos(-1_500_000_000)?;
+    table.push_offset_date_time(0, Some(at))?;
+    // the offset doesn't change the instant
+    table.push_offset_date_time(0, Some(at.to_offset(UtcOffset::from_hms(5, 30, 0)?)))?;
+    table.push_offset_date_time(0, None)?;
+    assert!(table
+        .push_offset_date_time(0, Some(at + time::Duration::microseconds(1)))
+        .is_err());
+    let batch = table.take_batch();
+    let expected =
+        PrimitiveArray::from([Some(-1500i64), Some(-1500), None]).to(batch[0].data_type().clone());
+    assert!(matches!(batch[0].data_type(), DataType::Timestamp(..)));
+    assert_eq!(&expected as &dyn Array, batch[0].as_ref());
+    Ok(())
+}
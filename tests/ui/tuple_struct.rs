@@ -0,0 +1,6 @@
+use pack_it::PackRecord;
+
+#[derive(PackRecord)]
+struct Visit(i64, String);
+
+fn main() {}
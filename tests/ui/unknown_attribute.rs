@@ -0,0 +1,9 @@
+use pack_it::PackRecord;
+
+#[derive(PackRecord)]
+struct Visit {
+    #[pack_it(skip)]
+    id: i64,
+}
+
+fn main() {}
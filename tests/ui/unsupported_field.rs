@@ -0,0 +1,9 @@
+use pack_it::PackRecord;
+
+#[derive(PackRecord)]
+struct Visit {
+    id: i64,
+    at: std::time::Instant,
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use pack_it::schema::parse_schema;
+use pack_it::Table;
+
+#[test]
+fn unchecked_pushes_match_the_checked() -> Result<()> {
+    let schema = parse_schema("id:i64, name:string?, score:f32?, at:timestamp[ms]")?;
+    let mut checked = Table::from_fields(&schema, 4);
+    let mut unchecked = Table::from_fields(&schema, 4);
+    for i in 0..10i64 {
+        let name = (i % 3 != 0).then(|| format!("name {}", i));
+        let score = (i % 2 == 0).then_some(i as f32 / 2.);
+        checked.push_primitive(0, Some(i))?;
+        checked.push_str(1, name.as_deref())?;
+        checked.push_primitive(2, score)?;
+        checked.push_primitive(3, Some(i * 1000))?;
+        // the kinds are as above
+        unsafe {
+            unchecked.push_primitive_unchecked(0, Some(i));
+            unchecked.push_str_unchecked(1, name.as_deref());
+            unchecked.push_primitive_unchecked(2, score);
+            unchecked.push_primitive_unchecked(3, Some(i * 1000));
+        }
+    }
+    unchecked.check_consistent()?;
+    assert_eq!(10, unchecked.rows());
+    assert_eq!(checked.take_batch(), unchecked.take_batch());
+    Ok(())
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow2::array::{Array, Int64Array};
+use arrow2::datatypes::{DataType, TimeUnit};
+use pack_it::schema::parse_schema;
+use pack_it::{Table, Writer, WriterOptions};
+
+use common::read;
+
+// the ids from..from + len, as a batch of the one column
+fn ids(from: i64, len: i64) -> Vec<Arc<dyn Array>> {
+    vec![Arc::new(Int64Array::from_vec((from..from + len).collect()))]
+}
+
+// the batches, in this many rows each, numbered in order
+fn write(options: WriterOptions, batches: &[i64]) -> Result<Vec<u8>> {
+    let schema = parse_schema("id:i64")?;
+    let mut writer = Writer::with_options([Vec::new()], &schema, options)?;
+    let mut from = 0;
+    for &len in batches {
+        writer.submit_batch(ids(from, len))?;
+        from += len;
+    }
+    Ok(writer.finish()?.pop().expect("one sink"))
+}
+
+// each row group's ids
+fn row_groups(file: &[u8]) -> Result<Vec<Vec<i64>>> {
+    let (_, groups) = read(file)?;
+    Ok(groups
+        .iter()
+        .map(|group| {
+            let ids = group.arrays()[0]
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .expect("i64");
+            ids.values().to_vec()
+        })
+        .collect())
+}
+
+fn lens(groups: &[Vec<i64>]) -> Vec<usize> {
+    groups.iter().map(|g| g.len()).collect()
+}
+
+#[test]
+fn merge_trailing_rows() -> Result<()> {
+    let options = WriterOptions {
+        single_threaded: true,
+        merge_trailing_rows: Some(100),
+        ..Default::default()
+    };
+    for (batches, expected) in [
+        (vec![1000, 10], vec![1010]),
+        (vec![1000, 1000, 10], vec![1000, 1010]),
+        // exactly at the threshold is big enough to stand alone
+        (vec![1000, 100], vec![1000, 100]),
+        (vec![1000, 99], vec![1099]),
+        (vec![10], vec![10]),
+    ] {
+        let groups = row_groups(&write(options.clone(), &batches)?)?;
+        assert_eq!(expected, lens(&groups), "{:?}", batches);
+        let total = batches.iter().sum::<i64>();
+        assert_eq!(
+            (0..total).collect::<Vec<_>>(),
+            groups.concat(),
+            "{:?}",
+            batches
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn compression_threads_keep_the_order() -> Result<()> {
+    let options = WriterOptions {
+        compression_threads: 4,
+        ..Default::default()
+    };
+    let batches = [300, 1, 2000, 5, 700, 50, 1000, 3];
+    let groups = row_groups(&write(options, &batches)?)?;
+    assert_eq!(
+        batches.iter().map(|&b| b as usize).collect::<Vec<_>>(),
+        lens(&groups)
+    );
+    let total = batches.iter().sum::<i64>();
+    assert_eq!((0..total).collect::<Vec<_>>(), groups.concat());
+    Ok(())
+}
+
+#[test]
+fn rendezvous() -> Result<()> {
+    let batches = [100, 1, 2000, 5];
+    for compression_threads in [0, 2] {
+        let options = WriterOptions {
+            rendezvous: true,
+            compression_threads,
+            ..Default::default()
+        };
+        let groups = row_groups(&write(options, &batches)?)?;
+        assert_eq!(vec![100, 1, 2000, 5], lens(&groups));
+        let total = batches.iter().sum::<i64>();
+        assert_eq!((0..total).collect::<Vec<_>>(), groups.concat());
+    }
+    Ok(())
+}
+
+#[test]
+fn reproducible() -> Result<()> {
+    let schema = parse_schema("id:i64")?;
+    let files = || -> Result<Vec<Vec<u8>>> {
+        let options = WriterOptions {
+            reproducible: true,
+            ..Default::default()
+        };
+        let mut writer = Writer::with_options(vec![Vec::new(); 3], &schema, options)?;
+        for i in 0..20 {
+            writer.submit_batch(ids(i * 2000, 1000 + i))?;
+        }
+        writer.finish()
+    };
+    let first = files()?;
+    assert_eq!(3, first.len());
+    for _ in 0..5 {
+        assert_eq!(first, files()?);
+    }
+    Ok(())
+}
+
+#[test]
+fn utc_timestamps() -> Result<()> {
+    let schema = parse_schema(
+        "at:timestamp[ms], seen:list<timestamp[us]>?, span:struct<start:timestamp[ms]>?, \
+         by:map<string, timestamp[ms]>?",
+    )?;
+    let mut table = Table::from_fields(&schema, 1);
+    table.push_primitive(0, Some(1i64))?;
+    table.push_list(1, Some([Some(2i64)]))?;
+    table.push_struct(2, |row| {
+        row.set(0, Some(3i64))?;
+        Ok(())
+    })?;
+    table.push_map(3, Some([("a", Some(4i64))]))?;
+    let options = WriterOptions {
+        single_threaded: true,
+        utc_timestamps: true,
+        ..Default::default()
+    };
+    let mut writer = Writer::with_options([Vec::new()], &schema, options)?;
+    writer.submit_batch(table.take_batch())?;
+    let (schema, groups) = read(&writer.finish()?.pop().expect("one sink"))?;
+
+    // every timestamp, however deep, is read back as utc
+    fn timestamps(data_type: &DataType, found: &mut Vec<DataType>) {
+        match data_type {
+            DataType::Timestamp(..) => found.push(data_type.clone()),
+            DataType::Struct(children) => {
+                for child in children {
+                    timestamps(&child.data_type, found);
+                }
+            }
+            DataType::List(item) | DataType::Map(item, _) => timestamps(&item.data_type, found),
+            _ => (),
+        }
+    }
+    let mut found = Vec::new();
+    for field in &schema.fields {
+        timestamps(&field.data_type, &mut found);
+    }
+    let utc = |unit| DataType::Timestamp(unit, Some("+00:00".to_string()));
+    assert_eq!(
+        vec![
+            utc(TimeUnit::Millisecond),
+            utc(TimeUnit::Microsecond),
+            utc(TimeUnit::Millisecond),
+            utc(TimeUnit::Millisecond),
+        ],
+        found
+    );
+    assert_eq!(1, groups.len());
+    assert_eq!(1, groups[0].len());
+    Ok(())
+}